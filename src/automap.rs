@@ -0,0 +1,226 @@
+use crate::map::Map;
+
+/// Segmento del automapa entre dos esquinas de la rejilla (en celdas). Siempre es horizontal
+/// o vertical, con (`x0`, `y0`) en el extremo menor.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Segment {
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize,
+}
+
+/// Indica si la celda es sólida para el automapa. Fuera del mapa todo es sólido, así el
+/// contorno exterior de un mapa cerrado no genera líneas.
+fn solid(map: &Map, x: isize, y: isize) -> bool {
+    x < 0 || y < 0 || x as usize >= map.width || y as usize >= map.height || map.cell(x as usize, y as usize) > 0
+}
+
+/// Recorre los bordes entre paredes y espacio vacío del mapa y los devuelve como segmentos.
+/// Los bordes consecutivos sobre la misma línea se unen en un solo segmento.
+pub fn wall_segments(map: &Map) -> Vec<Segment> {
+    let mut segments = Vec::new();
+
+    // Bordes horizontales: la línea `y` separa la fila `y - 1` de la fila `y`
+    for y in 0..=map.height {
+        let mut run_start = None;
+        for x in 0..=map.width {
+            let edge = x < map.width && solid(map, x as isize, y as isize - 1) != solid(map, x as isize, y as isize);
+            match (edge, run_start) {
+                (true, None) => run_start = Some(x),
+                (false, Some(start)) => {
+                    segments.push(Segment { x0: start, y0: y, x1: x, y1: y });
+                    run_start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Bordes verticales: la línea `x` separa la columna `x - 1` de la columna `x`
+    for x in 0..=map.width {
+        let mut run_start = None;
+        for y in 0..=map.height {
+            let edge = y < map.height && solid(map, x as isize - 1, y as isize) != solid(map, x as isize, y as isize);
+            match (edge, run_start) {
+                (true, None) => run_start = Some(y),
+                (false, Some(start)) => {
+                    segments.push(Segment { x0: x, y0: start, x1: x, y1: y });
+                    run_start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    segments
+}
+
+/// Transformación del automapa entre el mundo (en celdas) y la pantalla (en píxeles): qué punto
+/// del mundo queda en el centro de la pantalla y cuántos píxeles ocupa una celda.
+pub struct AutomapView {
+    pub center: (f64, f64),
+    pub zoom: f64,    // Píxeles por celda, dentro de `zoom_range`
+    pub follow: bool, // Si está activo, el centro sigue al jugador; si no, se desplaza a mano
+    zoom_range: (f64, f64),
+}
+
+impl AutomapView {
+    pub fn new(zoom: f64, zoom_range: (f64, f64)) -> Self {
+        Self { center: (0.0, 0.0), zoom: zoom.clamp(zoom_range.0, zoom_range.1), follow: true, zoom_range }
+    }
+
+    /// Punto de la pantalla (de tamaño `screen`) donde se dibuja el punto del mundo (`x`, `y`).
+    pub fn to_screen(&self, x: f64, y: f64, screen: (f64, f64)) -> (f64, f64) {
+        (
+            (x - self.center.0) * self.zoom + screen.0 / 2.0,
+            (y - self.center.1) * self.zoom + screen.1 / 2.0,
+        )
+    }
+
+    /// Punto del mundo que se ve en el píxel (`x`, `y`) de la pantalla; la inversa de `to_screen`.
+    pub fn to_world(&self, x: f64, y: f64, screen: (f64, f64)) -> (f64, f64) {
+        (
+            (x - screen.0 / 2.0) / self.zoom + self.center.0,
+            (y - screen.1 / 2.0) / self.zoom + self.center.1,
+        )
+    }
+
+    /// Desplaza la vista `dx`, `dy` píxeles de pantalla.
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.center.0 += dx / self.zoom;
+        self.center.1 += dy / self.zoom;
+    }
+
+    /// Multiplica el zoom por `factor` (dentro de los límites) dejando fijo el punto del mundo
+    /// que está bajo el píxel `anchor`, como al hacer zoom hacia el cursor.
+    pub fn zoom_at(&mut self, factor: f64, anchor: (f64, f64), screen: (f64, f64)) {
+        let (world_x, world_y) = self.to_world(anchor.0, anchor.1, screen);
+        self.zoom = (self.zoom * factor).clamp(self.zoom_range.0, self.zoom_range.1);
+        let (moved_x, moved_y) = self.to_screen(world_x, world_y, screen);
+        self.pan(moved_x - anchor.0, moved_y - anchor.1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::parse_map;
+
+    fn segments(text: &str) -> Vec<Segment> {
+        let (map, _) = parse_map(text).unwrap();
+        wall_segments(&map)
+    }
+
+    fn segment(x0: usize, y0: usize, x1: usize, y1: usize) -> Segment {
+        Segment { x0, y0, x1, y1 }
+    }
+
+    #[test]
+    fn single_room_has_four_sides() {
+        assert_eq!(
+            segments("111\n1P1\n111\n"),
+            [segment(1, 1, 2, 1), segment(1, 2, 2, 2), segment(1, 1, 1, 2), segment(2, 1, 2, 2)]
+        );
+    }
+
+    #[test]
+    fn consecutive_edges_are_merged() {
+        assert_eq!(
+            segments("1111\n1P01\n1111\n"),
+            [segment(1, 1, 3, 1), segment(1, 2, 3, 2), segment(1, 1, 1, 2), segment(3, 1, 3, 2)]
+        );
+    }
+
+    #[test]
+    fn pillar_splits_the_room() {
+        // Sala de 3x3 con una columna en el centro: cada línea interior queda partida por ella
+        let found = segments("11111\n1P001\n10101\n10001\n11111\n");
+        assert!(found.contains(&segment(2, 2, 3, 2)));
+        assert!(found.contains(&segment(2, 3, 3, 3)));
+        assert!(found.contains(&segment(2, 2, 2, 3)));
+        assert!(found.contains(&segment(3, 2, 3, 3)));
+        assert!(found.contains(&segment(1, 1, 4, 1)));
+        assert_eq!(found.len(), 8);
+    }
+
+    #[test]
+    fn outside_counts_as_solid() {
+        assert!(segments("111\n111\n").is_empty());
+        assert_eq!(
+            segments("P\n"),
+            [segment(0, 0, 1, 0), segment(0, 1, 1, 1), segment(0, 0, 0, 1), segment(1, 0, 1, 1)]
+        );
+    }
+
+    const SCREEN: (f64, f64) = (640.0, 480.0);
+    const RANGE: (f64, f64) = (4.0, 64.0);
+    const EPSILON: f64 = 1e-9;
+
+    fn assert_point(actual: (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (actual.0 - expected.0).abs() < EPSILON && (actual.1 - expected.1).abs() < EPSILON,
+            "expected {:?}, got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    fn view(center: (f64, f64), zoom: f64) -> AutomapView {
+        let mut view = AutomapView::new(zoom, RANGE);
+        view.center = center;
+        view
+    }
+
+    #[test]
+    fn center_is_drawn_at_screen_center() {
+        for (center, zoom) in [((0.0, 0.0), 16.0), ((12.5, 3.25), 4.0), ((-7.0, 100.0), 64.0)] {
+            assert_point(view(center, zoom).to_screen(center.0, center.1, SCREEN), (320.0, 240.0));
+        }
+    }
+
+    #[test]
+    fn to_screen_scales_by_zoom() {
+        let view = view((10.0, 10.0), 8.0);
+        assert_point(view.to_screen(11.0, 10.0, SCREEN), (328.0, 240.0));
+        assert_point(view.to_screen(10.0, 8.0, SCREEN), (320.0, 224.0));
+    }
+
+    #[test]
+    fn to_world_inverts_to_screen() {
+        for (center, zoom) in [((0.0, 0.0), 4.0), ((12.5, 3.25), 16.0), ((-7.0, 100.0), 37.5)] {
+            let view = view(center, zoom);
+            for point in [(0.0, 0.0), (3.5, -2.25), (250.0, 17.0)] {
+                let (x, y) = view.to_screen(point.0, point.1, SCREEN);
+                assert_point(view.to_world(x, y, SCREEN), point);
+            }
+        }
+    }
+
+    #[test]
+    fn zoom_is_clamped() {
+        assert_eq!(AutomapView::new(1000.0, RANGE).zoom, RANGE.1);
+        let mut view = view((0.0, 0.0), 16.0);
+        view.zoom_at(0.001, (320.0, 240.0), SCREEN);
+        assert_eq!(view.zoom, RANGE.0);
+    }
+
+    #[test]
+    fn pan_moves_by_screen_pixels() {
+        let mut view = view((5.0, 5.0), 10.0);
+        view.pan(20.0, -10.0);
+        assert_point(view.center, (7.0, 4.0));
+    }
+
+    #[test]
+    fn zoom_keeps_point_under_cursor() {
+        for anchor in [(0.0, 0.0), (320.0, 240.0), (600.0, 35.0)] {
+            for factor in [0.5, 1.7, 3.0, 100.0] {
+                let mut view = view((12.5, 3.25), 16.0);
+                let before = view.to_world(anchor.0, anchor.1, SCREEN);
+                view.zoom_at(factor, anchor, SCREEN);
+                assert_point(view.to_world(anchor.0, anchor.1, SCREEN), before);
+            }
+        }
+    }
+}
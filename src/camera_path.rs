@@ -0,0 +1,220 @@
+use std::f64::consts::{PI, TAU};
+use std::fs;
+
+/// Pose de la cámara en un instante del recorrido.
+pub struct Keyframe {
+    pub time: f64,      // Segundos desde el inicio del recorrido
+    pub x: f64,
+    pub y: f64,
+    pub direction: f64, // Dirección en radianes, sin normalizar: a menos de media vuelta de la anterior
+}
+
+/// Recorrido de cámara definido por keyframes e interpolado con Catmull-Rom.
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    /// Interpreta un recorrido en formato de texto: una línea `tiempo x y dirección` por keyframe,
+    /// con la dirección en grados. Las líneas vacías y las que empiezan con `#` se ignoran.
+    /// Entre dos keyframes la cámara gira siempre por el camino más corto (de 350° a 10° pasa
+    /// por 0°, no da la vuelta entera).
+    ///
+    /// # Retorna
+    ///
+    /// El recorrido, o un mensaje con la línea del error si algún keyframe es inválido
+    /// o los tiempos no son estrictamente crecientes.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut keyframes: Vec<Keyframe> = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let values: Vec<f64> = line
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|_| format!("line {}: expected numbers 'time x y direction'", i + 1))?;
+            if values.len() != 4 {
+                return Err(format!("line {}: expected 4 values, found {}", i + 1, values.len()));
+            }
+
+            let direction = values[3].to_radians();
+            let keyframe = Keyframe {
+                time: values[0],
+                x: values[1],
+                y: values[2],
+                direction: keyframes.last().map_or(direction, |last| unwrap_angle(last.direction, direction)),
+            };
+            if keyframes.last().is_some_and(|last| keyframe.time <= last.time) {
+                return Err(format!("line {}: keyframe times must increase", i + 1));
+            }
+            keyframes.push(keyframe);
+        }
+
+        if keyframes.is_empty() {
+            return Err("camera path has no keyframes".to_string());
+        }
+        Ok(Self { keyframes })
+    }
+
+    /// Carga un recorrido desde un archivo de texto (ver `parse`).
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        Self::parse(&text)
+    }
+
+    /// Duración total del recorrido (en segundos).
+    pub fn duration(&self) -> f64 {
+        self.keyframes[self.keyframes.len() - 1].time
+    }
+
+    /// Calcula la pose en el instante `time`. Antes del primer keyframe y después del último
+    /// se mantiene la pose del extremo correspondiente.
+    ///
+    /// # Retorna
+    ///
+    /// `(x, y, direction)` con la dirección en radianes, en [0, 2π).
+    pub fn sample(&self, time: f64) -> (f64, f64, f64) {
+        let keys = &self.keyframes;
+        let last = keys.len() - 1;
+
+        if time <= keys[0].time {
+            return (keys[0].x, keys[0].y, keys[0].direction.rem_euclid(TAU));
+        }
+        if time >= keys[last].time {
+            return (keys[last].x, keys[last].y, keys[last].direction.rem_euclid(TAU));
+        }
+
+        // Segmento [i, i + 1] que contiene el instante; los extremos se repiten como vecinos
+        let i = keys.iter().rposition(|key| key.time <= time).unwrap_or(0);
+        let p0 = &keys[i.saturating_sub(1)];
+        let p1 = &keys[i];
+        let p2 = &keys[i + 1];
+        let p3 = &keys[(i + 2).min(last)];
+        let t = (time - p1.time) / (p2.time - p1.time);
+
+        (
+            catmull_rom(p0.x, p1.x, p2.x, p3.x, t),
+            catmull_rom(p0.y, p1.y, p2.y, p3.y, t),
+            catmull_rom(p0.direction, p1.direction, p2.direction, p3.direction, t).rem_euclid(TAU),
+        )
+    }
+}
+
+/// `angle` más o menos las vueltas enteras que lo dejan a menos de media vuelta de `previous`.
+fn unwrap_angle(previous: f64, angle: f64) -> f64 {
+    previous + (angle - previous + PI).rem_euclid(TAU) - PI
+}
+
+/// Interpolación Catmull-Rom entre `p1` (t = 0) y `p2` (t = 1).
+fn catmull_rom(p0: f64, p1: f64, p2: f64, p3: f64, t: f64) -> f64 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1 + (p2 - p0) * t + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2 + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 1e-9;
+
+    fn assert_pose(actual: (f64, f64, f64), expected: (f64, f64, f64)) {
+        let close = |a: f64, b: f64| (a - b).abs() < EPSILON;
+        assert!(
+            close(actual.0, expected.0) && close(actual.1, expected.1) && close(actual.2, expected.2),
+            "expected {:?}, got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    fn path() -> CameraPath {
+        CameraPath::parse("# recorrido\n0 1 1 0\n\n2 3 1 90\n3 3 4 180\n5 6 4 90\n").unwrap()
+    }
+
+    #[test]
+    fn keyframes_are_hit_exactly() {
+        let path = path();
+        for key in &path.keyframes {
+            assert_pose(path.sample(key.time), (key.x, key.y, key.direction.rem_euclid(TAU)));
+        }
+    }
+
+    #[test]
+    fn sample_clamps_at_both_ends() {
+        let path = path();
+        assert_pose(path.sample(-1.0), (1.0, 1.0, 0.0));
+        assert_pose(path.sample(100.0), (6.0, 4.0, 90f64.to_radians()));
+        assert_eq!(path.duration(), 5.0);
+    }
+
+    #[test]
+    fn evenly_spaced_line_moves_forward() {
+        let path = CameraPath::parse("0 0 0 0\n1 1 0 0\n2 2 0 0\n3 3 0 0\n").unwrap();
+        let mut previous = f64::NEG_INFINITY;
+        for step in 0..=300 {
+            let (x, y, _) = path.sample(step as f64 / 100.0);
+            assert!(x >= previous, "x went back at {}", step);
+            assert!(y.abs() < EPSILON);
+            previous = x;
+        }
+    }
+
+    #[test]
+    fn times_must_increase() {
+        assert_eq!(CameraPath::parse("0 0 0 0\n1 0 0 0\n1 0 0 0\n").err().unwrap(), "line 3: keyframe times must increase");
+        assert_eq!(CameraPath::parse("2 0 0 0\n1 0 0 0\n").err().unwrap(), "line 2: keyframe times must increase");
+    }
+
+    #[test]
+    fn invalid_lines_are_reported() {
+        assert_eq!(CameraPath::parse("0 0 0\n").err().unwrap(), "line 1: expected 4 values, found 3");
+        assert_eq!(CameraPath::parse("0 0 x 0\n").err().unwrap(), "line 1: expected numbers 'time x y direction'");
+        assert_eq!(CameraPath::parse("# nada\n").err().unwrap(), "camera path has no keyframes");
+    }
+
+    #[test]
+    fn single_keyframe_is_static() {
+        let path = CameraPath::parse("1 2 3 45\n").unwrap();
+        for time in [0.0, 1.0, 2.0] {
+            assert_pose(path.sample(time), (2.0, 3.0, 45f64.to_radians()));
+        }
+    }
+
+    /// Diferencia entre dos ángulos, por el camino más corto.
+    fn angle_between(a: f64, b: f64) -> f64 {
+        (a - b + PI).rem_euclid(TAU) - PI
+    }
+
+    #[test]
+    fn turning_across_zero_takes_the_short_way() {
+        for text in ["0 0 0 350\n2 0 0 10\n", "0 0 0 10\n2 0 0 350\n", "0 0 0 -170\n2 0 0 170\n"] {
+            let path = CameraPath::parse(text).unwrap();
+            let start = path.sample(0.0).2;
+            for step in 0..=200 {
+                let direction = path.sample(step as f64 / 100.0).2;
+                assert!((0.0..TAU).contains(&direction), "{}: {} out of range", text, direction);
+                assert!(angle_between(direction, start).abs() <= 20f64.to_radians() + EPSILON, "{}: turned to {}", text, direction.to_degrees());
+            }
+        }
+
+        // A mitad de camino entre 350° y 10° mira a 0°, no hacia atrás
+        let midpoint = CameraPath::parse("0 0 0 350\n2 0 0 10\n").unwrap().sample(1.0).2;
+        assert!(angle_between(midpoint, 0.0).abs() < EPSILON, "midpoint at {}", midpoint.to_degrees());
+    }
+
+    #[test]
+    fn several_turns_accumulate_through_keyframes() {
+        // Cuatro cuartos de vuelta seguidos: cada tramo gira 90° hacia el mismo lado
+        let path = CameraPath::parse("0 0 0 0\n1 0 0 90\n2 0 0 180\n3 0 0 270\n4 0 0 0\n").unwrap();
+        assert!((path.sample(1.5).2 - 135f64.to_radians()).abs() < EPSILON);
+        let last_turn = path.sample(3.5).2.to_degrees();
+        assert!(last_turn > 270.0 && last_turn < 360.0, "at {}", last_turn);
+        assert!(path.sample(4.0).2.abs() < EPSILON);
+    }
+}
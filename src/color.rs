@@ -0,0 +1,177 @@
+/// Color RGB empaquetado como 0xRRGGBB, el mismo formato que usa el búfer de la ventana.
+/// Toda la aritmética de canales del juego pasa por aquí.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Color(u32);
+
+impl Color {
+    pub const BLACK: Color = Color(0x000000);
+    pub const WHITE: Color = Color(0xFFFFFF);
+
+    /// Crea un color a partir de un valor 0xRRGGBB (se ignora el byte más alto).
+    pub const fn from_u32(value: u32) -> Self {
+        Self(value & 0xFFFFFF)
+    }
+
+    /// Crea un color a partir de sus tres canales.
+    pub const fn from_rgb(r: u8, g: u8, b: u8) -> Self {
+        Self(((r as u32) << 16) | ((g as u32) << 8) | b as u32)
+    }
+
+    /// Valor 0xRRGGBB del color, para escribirlo en un búfer.
+    #[inline]
+    pub const fn to_u32(self) -> u32 {
+        self.0
+    }
+
+    #[inline]
+    pub const fn r(self) -> u8 {
+        (self.0 >> 16) as u8
+    }
+
+    #[inline]
+    pub const fn g(self) -> u8 {
+        (self.0 >> 8) as u8
+    }
+
+    #[inline]
+    pub const fn b(self) -> u8 {
+        self.0 as u8
+    }
+
+    /// Aplica `f` a cada canal (como u32) y vuelve a empaquetar, saturando en 255.
+    #[inline]
+    fn map_channels(self, other: Color, f: impl Fn(u32, u32) -> u32) -> Self {
+        let channel = |a: u8, b: u8| f(a as u32, b as u32).min(255) as u8;
+        Self::from_rgb(channel(self.r(), other.r()), channel(self.g(), other.g()), channel(self.b(), other.b()))
+    }
+
+    /// Multiplica canal por canal, como una luz de color: blanco deja el color igual y negro lo apaga.
+    #[inline]
+    pub fn mul(self, other: Color) -> Self {
+        self.map_channels(other, |a, b| a * b / 255)
+    }
+
+    /// Multiplica cada canal por `factor` (saturando en 255 si es mayor que 1.0).
+    #[inline]
+    pub fn scale(self, factor: f64) -> Self {
+        self.map_channels(self, |a, _| (a as f64 * factor) as u32)
+    }
+
+    /// Interpola hacia `to`: `t` = 0.0 devuelve este color y `t` = 1.0 devuelve `to`.
+    #[inline]
+    pub fn lerp(self, to: Color, t: f64) -> Self {
+        self.map_channels(to, |a, b| (a as f64 + (b as f64 - a as f64) * t).round() as u32)
+    }
+
+    /// Ilumina el color con una luz ambiental de color `tint` (blanco es luz normal) e intensidad `intensity`.
+    #[inline]
+    pub fn light(self, tint: Color, intensity: f64) -> Self {
+        self.mul(tint).scale(intensity)
+    }
+
+    /// El framebuffer usa 0 para los píxeles sin dibujar, así que el negro puro se cambia por
+    /// 0x000001, que se ve igual, cuando tiene que dibujarse.
+    pub fn drawable(self) -> Self {
+        Self(self.0.max(1))
+    }
+
+    /// Promedio de cuatro colores, canal por canal (redondeando).
+    pub fn average(colors: [Color; 4]) -> Self {
+        let sum = |channel: fn(Color) -> u8| colors.iter().map(|&color| channel(color) as u32).sum::<u32>();
+        let average = |total: u32| ((total + 2) / 4) as u8;
+        Self::from_rgb(average(sum(Color::r)), average(sum(Color::g)), average(sum(Color::b)))
+    }
+}
+
+/// Color de un ciclo de claves `(tiempo, color)` en el instante `t`, interpolando entre la
+/// clave anterior y la siguiente. Los tiempos van de 0.0 a 1.0, ordenados, y el ciclo se repite:
+/// después de la última clave se vuelve a la primera.
+pub fn cycle_color(keys: &[(f64, Color)], t: f64) -> Color {
+    let t = t.rem_euclid(1.0);
+    let next = keys.iter().position(|&(time, _)| time > t).unwrap_or(0);
+    let previous = (next + keys.len() - 1) % keys.len();
+    let (start, from) = keys[previous];
+    let (end, to) = keys[next];
+
+    // Distancia entre las dos claves, dando la vuelta al ciclo si hace falta
+    let span = (end - start).rem_euclid(1.0);
+    if span == 0.0 {
+        return from;
+    }
+    from.lerp(to, (t - start).rem_euclid(1.0) / span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAY: Color = Color::from_rgb(128, 128, 128);
+
+    #[test]
+    fn channels_round_trip_at_extremes() {
+        let color = Color::from_rgb(0, 255, 0);
+        assert_eq!((color.r(), color.g(), color.b()), (0, 255, 0));
+        assert_eq!(Color::from_u32(0xFF123456).to_u32(), 0x123456);
+    }
+
+    #[test]
+    fn mul_by_white_and_black() {
+        let color = Color::from_rgb(0, 128, 255);
+        assert_eq!(color.mul(Color::WHITE), color);
+        assert_eq!(color.mul(Color::BLACK), Color::BLACK);
+        assert_eq!(Color::WHITE.mul(Color::WHITE), Color::WHITE);
+    }
+
+    #[test]
+    fn scale_saturates_above_one() {
+        assert_eq!(GRAY.scale(2.0), Color::from_rgb(255, 255, 255));
+        assert_eq!(Color::from_rgb(0, 100, 255).scale(1.5), Color::from_rgb(0, 150, 255));
+        assert_eq!(Color::WHITE.scale(0.0), Color::BLACK);
+        assert_eq!(Color::WHITE.scale(-1.0), Color::BLACK);
+    }
+
+    #[test]
+    fn lerp_endpoints() {
+        let from = Color::from_rgb(0, 255, 10);
+        let to = Color::from_rgb(255, 0, 20);
+        assert_eq!(from.lerp(to, 0.0), from);
+        assert_eq!(from.lerp(to, 1.0), to);
+        assert_eq!(from.lerp(to, 0.5), Color::from_rgb(128, 128, 15));
+    }
+
+    #[test]
+    fn average_rounds_to_nearest() {
+        let colors = [Color::from_rgb(0, 255, 1), Color::from_rgb(0, 255, 1), Color::from_rgb(0, 255, 0), Color::BLACK];
+        // 765 / 4 = 191.25 redondea hacia abajo y 2 / 4 = 0.5 hacia arriba
+        assert_eq!(Color::average(colors), Color::from_rgb(0, 191, 1));
+        assert_eq!(Color::average([Color::WHITE; 4]), Color::WHITE);
+        let quarter = [Color::from_rgb(1, 0, 3), Color::BLACK, Color::BLACK, Color::BLACK];
+        assert_eq!(Color::average(quarter), Color::from_rgb(0, 0, 1));
+    }
+
+    #[test]
+    fn drawable_only_changes_black() {
+        assert_eq!(Color::BLACK.drawable().to_u32(), 1);
+        assert_eq!(GRAY.drawable(), GRAY);
+    }
+
+    #[test]
+    fn cycle_color_with_single_key_is_constant() {
+        let keys = [(0.5, GRAY)];
+        for t in [0.0, 0.25, 0.5, 0.75, 1.0, 3.2, -0.4] {
+            assert_eq!(cycle_color(&keys, t), GRAY);
+        }
+    }
+
+    #[test]
+    fn cycle_color_wraps_after_last_key() {
+        let keys = [(0.25, Color::BLACK), (0.75, Color::WHITE)];
+        assert_eq!(cycle_color(&keys, 0.25), Color::BLACK);
+        assert_eq!(cycle_color(&keys, 0.75), Color::WHITE);
+        assert_eq!(cycle_color(&keys, 0.5), Color::from_rgb(128, 128, 128));
+        // De 0.75 a 1.25 (la primera clave del ciclo siguiente), pasando por 0.0
+        assert_eq!(cycle_color(&keys, 0.0), Color::from_rgb(128, 128, 128));
+        assert_eq!(cycle_color(&keys, 1.0), cycle_color(&keys, 0.0));
+        assert_eq!(cycle_color(&keys, -0.5), cycle_color(&keys, 0.5));
+    }
+}
@@ -0,0 +1,366 @@
+use crate::color::Color;
+use crate::font::{draw_text, measure_text, GLYPH_HEIGHT};
+use crate::framebuffer::Framebuffer;
+use minifb::{InputCallback, Key, KeyRepeat, Window};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+const OUTPUT_LINES: usize = 6; // Líneas de respuesta visibles sobre la línea de entrada
+const MAX_INPUT: usize = 60;   // Caracteres como máximo en la línea de entrada
+const PROMPT: &str = "> ";
+
+/// Orden ya analizada, lista para que el juego la aplique.
+pub enum Command {
+    Help,
+    Noclip,
+    Teleport(f64, f64),
+    SetFov(f64),
+    Map(String),
+    Timescale(f64),
+    OrbitCamera(bool), // true para la cámara que gira alrededor del jugador, false para la vista normal
+    FinishRun,         // Termina la partida del modo speedrun y exporta sus tiempos
+    ResetRun,          // Vuelve a empezar la partida del modo speedrun
+}
+
+/// Orden registrada: nombre, forma de uso y cómo convertir sus argumentos.
+struct CommandSpec {
+    name: &'static str,
+    usage: &'static str,
+    rest: bool, // El último argumento toma el resto de la línea, espacios incluidos (p. ej. una ruta)
+    parse: fn(&[&str]) -> Result<Command, String>,
+}
+
+const COMMANDS: [CommandSpec; 8] = [
+    CommandSpec { name: "help", usage: "help", rest: false, parse: |_| Ok(Command::Help) },
+    CommandSpec { name: "noclip", usage: "noclip", rest: false, parse: |_| Ok(Command::Noclip) },
+    CommandSpec {
+        name: "tp",
+        usage: "tp <x> <y>",
+        rest: false,
+        parse: |args| Ok(Command::Teleport(number(args, 0, "x")?, number(args, 1, "y")?)),
+    },
+    CommandSpec {
+        name: "setfov",
+        usage: "setfov <degrees>",
+        rest: false,
+        parse: |args| Ok(Command::SetFov(number(args, 0, "degrees")?)),
+    },
+    CommandSpec {
+        name: "map",
+        usage: "map <path>",
+        rest: true,
+        parse: |args| Ok(Command::Map(args[0].to_string())),
+    },
+    CommandSpec {
+        name: "timescale",
+        usage: "timescale <factor>",
+        rest: false,
+        parse: |args| Ok(Command::Timescale(number(args, 0, "factor")?)),
+    },
+    CommandSpec {
+        name: "camera",
+        usage: "camera <first|orbit>",
+        rest: false,
+        parse: |args| match args[0].to_ascii_lowercase().as_str() {
+            "first" => Ok(Command::OrbitCamera(false)),
+            "orbit" => Ok(Command::OrbitCamera(true)),
+            other => Err(format!("unknown camera '{}', expected first or orbit", other)),
+        },
+    },
+    CommandSpec {
+        name: "run",
+        usage: "run <finish|reset>",
+        rest: false,
+        parse: |args| match args[0].to_ascii_lowercase().as_str() {
+            "finish" => Ok(Command::FinishRun),
+            "reset" => Ok(Command::ResetRun),
+            other => Err(format!("unknown run action '{}', expected finish or reset", other)),
+        },
+    },
+];
+
+/// Argumento numérico `index` de una orden, con un error que nombra el argumento si falta o no es un número.
+/// `nan` e `inf` se rechazan: una posición, un FOV o una escala de tiempo así rompen la simulación.
+fn number(args: &[&str], index: usize, name: &str) -> Result<f64, String> {
+    let value = args[index];
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite())
+        .ok_or_else(|| format!("{} must be a number, got '{}'", name, value))
+}
+
+/// Analiza una línea de la consola. Los errores explican qué falló y cómo se usa la orden.
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let line = line.trim();
+    let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    if name.is_empty() {
+        return Err("empty command".to_string());
+    }
+
+    let spec = COMMANDS
+        .iter()
+        .find(|spec| spec.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("unknown command '{}', try help", name))?;
+
+    // El número de palabras en el uso indica cuántos argumentos espera la orden
+    let expected = spec.usage.split_whitespace().count() - 1;
+    let args: Vec<&str> = if spec.rest {
+        // Los primeros argumentos son palabras sueltas y el último es lo que queda de la línea
+        let mut args = Vec::new();
+        let mut tail = rest.trim_start();
+        while args.len() + 1 < expected {
+            let Some((word, more)) = tail.split_once(char::is_whitespace) else { break };
+            args.push(word);
+            tail = more.trim_start();
+        }
+        if !tail.is_empty() {
+            args.push(tail);
+        }
+        args
+    } else {
+        rest.split_whitespace().collect()
+    };
+    if args.len() != expected {
+        return Err(format!("usage: {}", spec.usage));
+    }
+    (spec.parse)(&args)
+}
+
+/// Formas de uso de todas las órdenes, para `help`.
+pub fn usages() -> impl Iterator<Item = &'static str> {
+    COMMANDS.iter().map(|spec| spec.usage)
+}
+
+/// Nombres de las órdenes que empiezan por `input`, sin distinguir mayúsculas. Una vez escrito
+/// el nombre (hay un espacio) ya no se completa nada.
+fn completions(input: &str) -> Vec<&'static str> {
+    if input.contains(' ') {
+        return Vec::new();
+    }
+    let prefix = input.to_ascii_lowercase();
+    COMMANDS.iter().map(|spec| spec.name).filter(|name| name.starts_with(&prefix)).collect()
+}
+
+/// Recibe los caracteres escritos desde la ventana y los deja en una cola compartida con la consola.
+struct CharQueue(Rc<RefCell<Vec<char>>>);
+
+impl InputCallback for CharQueue {
+    fn add_char(&mut self, uni_char: u32) {
+        if let Some(ch) = char::from_u32(uni_char) {
+            self.0.borrow_mut().push(ch);
+        }
+    }
+}
+
+/// Consola de desarrollo: una línea de entrada con historial y las últimas respuestas.
+pub struct Console {
+    open: bool,
+    input: String,
+    chars: Rc<RefCell<Vec<char>>>,
+    history: Vec<String>,
+    history_pos: Option<usize>, // Entrada del historial que se está mostrando, si se navega con las flechas
+    output: VecDeque<String>,
+}
+
+impl Console {
+    /// Crea la consola y registra en la ventana la captura de caracteres que usa.
+    pub fn new(window: &mut Window) -> Self {
+        let chars = Rc::new(RefCell::new(Vec::new()));
+        window.set_input_callback(Box::new(CharQueue(Rc::clone(&chars))));
+        Self {
+            open: false,
+            input: String::new(),
+            chars,
+            history: Vec::new(),
+            history_pos: None,
+            output: VecDeque::new(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.history_pos = None;
+    }
+
+    /// Añade una línea de respuesta, descartando las más viejas.
+    pub fn print(&mut self, line: String) {
+        if self.output.len() == OUTPUT_LINES {
+            self.output.pop_front();
+        }
+        self.output.push_back(line);
+    }
+
+    /// Procesa la entrada de este cuadro. Devuelve la orden si se pulsó Enter con una línea válida;
+    /// los errores de análisis se muestran en la consola.
+    pub fn update(&mut self, window: &Window) -> Option<Command> {
+        let typed: Vec<char> = self.chars.borrow_mut().drain(..).collect();
+        if !self.open {
+            return None;
+        }
+
+        // La tecla que abre la consola también llega como carácter, así que se ignora
+        for ch in typed {
+            if (ch.is_ascii_graphic() || ch == ' ') && ch != '`' && self.input.len() < MAX_INPUT {
+                self.input.push(ch);
+            }
+        }
+
+        if window.is_key_pressed(Key::Backspace, KeyRepeat::Yes) {
+            self.input.pop();
+        }
+        if window.is_key_pressed(Key::Tab, KeyRepeat::No) {
+            self.complete();
+        }
+        if window.is_key_pressed(Key::Up, KeyRepeat::No) {
+            self.browse_history(true);
+        }
+        if window.is_key_pressed(Key::Down, KeyRepeat::No) {
+            self.browse_history(false);
+        }
+        if !window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+            return None;
+        }
+
+        let line = std::mem::take(&mut self.input);
+        self.history_pos = None;
+        if line.trim().is_empty() {
+            return None;
+        }
+        self.print(format!("{}{}", PROMPT, line));
+        self.history.push(line.clone());
+        match parse_command(&line) {
+            Ok(command) => Some(command),
+            Err(message) => {
+                self.print(message);
+                None
+            }
+        }
+    }
+
+    /// Completa el nombre de la orden con Tab: si solo una empieza por lo escrito, se completa
+    /// entera; si hay varias, se muestran.
+    fn complete(&mut self) {
+        match completions(&self.input).as_slice() {
+            [] => {}
+            [name] => self.input = format!("{} ", name),
+            names => self.print(names.join(" ")),
+        }
+    }
+
+    /// Recorre el historial hacia atrás (`older`) o hacia adelante con las flechas.
+    fn browse_history(&mut self, older: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+
+        self.history_pos = match (self.history_pos, older) {
+            (None, true) => Some(self.history.len() - 1),
+            (Some(pos), true) => Some(pos.saturating_sub(1)),
+            (Some(pos), false) if pos + 1 < self.history.len() => Some(pos + 1),
+            (_, false) => None,
+        };
+        self.input = self.history_pos.map_or_else(String::new, |pos| self.history[pos].clone());
+    }
+
+    /// Dibuja la consola en una franja en la parte superior de la pantalla.
+    pub fn draw(&self, framebuffer: &mut Framebuffer, background: Color, text: Color, scale: usize) {
+        if !self.open {
+            return;
+        }
+
+        let margin = 4 * scale;
+        let line_height = (GLYPH_HEIGHT + 3) * scale;
+        let height = margin * 2 + line_height * (OUTPUT_LINES + 1);
+        framebuffer.fill_rect(0, 0, framebuffer.width, height, background.drawable());
+
+        for (i, line) in self.output.iter().enumerate() {
+            draw_text(framebuffer, margin, margin + i * line_height, line, text, scale);
+        }
+
+        // Línea de entrada con un cursor al final
+        let input = format!("{}{}", PROMPT, self.input);
+        let input_y = margin + OUTPUT_LINES * line_height;
+        draw_text(framebuffer, margin, input_y, &input, text, scale);
+        let (input_width, _) = measure_text(&input, scale);
+        framebuffer.fill_rect(margin + input_width + scale, input_y, 4 * scale, GLYPH_HEIGHT * scale, text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(line: &str) -> String {
+        match parse_command(line) {
+            Ok(_) => panic!("'{}' should not parse", line),
+            Err(message) => message,
+        }
+    }
+
+    #[test]
+    fn wrong_argument_count_shows_usage() {
+        assert_eq!(error("tp 1"), "usage: tp <x> <y>");
+        assert_eq!(error("tp 1 2 3"), "usage: tp <x> <y>");
+        assert_eq!(error("setfov"), "usage: setfov <degrees>");
+        assert_eq!(error("noclip now"), "usage: noclip");
+        assert_eq!(error("map"), "usage: map <path>");
+    }
+
+    #[test]
+    fn non_numeric_arguments_are_named() {
+        assert_eq!(error("tp 1 north"), "y must be a number, got 'north'");
+        assert_eq!(error("setfov wide"), "degrees must be a number, got 'wide'");
+        assert_eq!(error("timescale fast"), "factor must be a number, got 'fast'");
+    }
+
+    #[test]
+    fn non_finite_arguments_are_rejected() {
+        assert_eq!(error("tp nan 2"), "x must be a number, got 'nan'");
+        assert_eq!(error("tp 1 inf"), "y must be a number, got 'inf'");
+        assert_eq!(error("setfov -infinity"), "degrees must be a number, got '-infinity'");
+        assert_eq!(error("timescale NaN"), "factor must be a number, got 'NaN'");
+        assert!(matches!(parse_command("tp 1e3 -0.5"), Ok(Command::Teleport(x, y)) if x == 1000.0 && y == -0.5));
+    }
+
+    #[test]
+    fn empty_and_unknown_commands() {
+        assert_eq!(error("   "), "empty command");
+        assert_eq!(error("fly"), "unknown command 'fly', try help");
+    }
+
+    #[test]
+    fn names_are_case_insensitive() {
+        assert!(matches!(parse_command("TP 1.5 2"), Ok(Command::Teleport(x, y)) if x == 1.5 && y == 2.0));
+        assert!(matches!(parse_command("NoClip"), Ok(Command::Noclip)));
+        assert!(matches!(parse_command("Camera ORBIT"), Ok(Command::OrbitCamera(true))));
+    }
+
+    #[test]
+    fn run_actions() {
+        assert!(matches!(parse_command("run finish"), Ok(Command::FinishRun)));
+        assert!(matches!(parse_command("run Reset"), Ok(Command::ResetRun)));
+        assert_eq!(error("run pause"), "unknown run action 'pause', expected finish or reset");
+        assert_eq!(error("run"), "usage: run <finish|reset>");
+    }
+
+    #[test]
+    fn map_path_keeps_spaces() {
+        assert!(matches!(parse_command("map maps/my level.txt"), Ok(Command::Map(path)) if path == "maps/my level.txt"));
+        assert!(matches!(parse_command("  map   a  b.png  "), Ok(Command::Map(path)) if path == "a  b.png"));
+    }
+
+    #[test]
+    fn completion_matches_prefix() {
+        assert_eq!(completions("ti"), ["timescale"]);
+        assert_eq!(completions("T"), ["tp", "timescale"]);
+        assert_eq!(completions("").len(), COMMANDS.len());
+        assert!(completions("x").is_empty());
+        assert!(completions("tp ").is_empty());
+    }
+}
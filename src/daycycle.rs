@@ -0,0 +1,109 @@
+use crate::color::{cycle_color, Color};
+
+// Claves del ciclo de día como fracción del periodo: 0.0 es medianoche y 0.5 mediodía.
+// Los colores multiplican al cielo de la paleta y a la luz ambiental del nivel.
+const SKY_KEYS: [(f64, Color); 4] = [
+    (0.0, Color::from_u32(0x202850)),  // Noche
+    (0.25, Color::from_u32(0xFFB080)), // Amanecer
+    (0.5, Color::from_u32(0xFFFFFF)),  // Día
+    (0.75, Color::from_u32(0xFF9060)), // Atardecer
+];
+const AMBIENT_KEYS: [(f64, Color); 4] = [
+    (0.0, Color::from_u32(0x6070A0)),
+    (0.25, Color::from_u32(0xFFD0A0)),
+    (0.5, Color::from_u32(0xFFFFFF)),
+    (0.75, Color::from_u32(0xFFB090)),
+];
+
+/// Reloj del nivel que recorre el ciclo de día y noche.
+pub struct DayCycle {
+    period: f64, // Segundos reales por ciclo completo
+    time: f64,   // Posición en el ciclo (0.0 a 1.0)
+}
+
+impl DayCycle {
+    /// Empieza a mediodía, que corresponde a los colores de la paleta sin cambios.
+    pub fn new(period: f64) -> Self {
+        Self { period, time: 0.5 }
+    }
+
+    /// Avanza el reloj `dt` segundos.
+    pub fn update(&mut self, dt: f64) {
+        self.time = (self.time + dt / self.period).rem_euclid(1.0);
+    }
+
+    /// Color por el que se multiplica el cielo en este momento del día.
+    pub fn sky_tint(&self) -> Color {
+        cycle_color(&SKY_KEYS, self.time)
+    }
+
+    /// Color por el que se multiplica la luz ambiental en este momento del día.
+    pub fn ambient_tint(&self) -> Color {
+        cycle_color(&AMBIENT_KEYS, self.time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ciclo de 100 segundos en el instante `time` (fracción del periodo).
+    fn at(time: f64) -> DayCycle {
+        let mut cycle = DayCycle::new(100.0);
+        cycle.update((time - 0.5) * 100.0);
+        cycle
+    }
+
+    fn channel_distance(a: Color, b: Color) -> i32 {
+        [(a.r(), b.r()), (a.g(), b.g()), (a.b(), b.b())]
+            .iter()
+            .map(|&(x, y)| (x as i32 - y as i32).abs())
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn starts_at_noon_without_tint() {
+        let cycle = DayCycle::new(60.0);
+        assert_eq!(cycle.sky_tint(), Color::WHITE);
+        assert_eq!(cycle.ambient_tint(), Color::WHITE);
+    }
+
+    #[test]
+    fn keys_are_hit_exactly() {
+        for (index, &(time, color)) in SKY_KEYS.iter().enumerate() {
+            let cycle = at(time);
+            assert_eq!(cycle.sky_tint(), color, "{}", time);
+            assert_eq!(cycle.ambient_tint(), AMBIENT_KEYS[index].1, "{}", time);
+        }
+    }
+
+    #[test]
+    fn colours_are_interpolated_between_keys() {
+        // Mitad de la mañana: entre la noche y el amanecer
+        assert_eq!(at(0.125).sky_tint(), SKY_KEYS[0].1.lerp(SKY_KEYS[1].1, 0.5));
+        assert_eq!(at(0.6).ambient_tint(), AMBIENT_KEYS[2].1.lerp(AMBIENT_KEYS[3].1, 0.4));
+    }
+
+    #[test]
+    fn night_follows_dusk_across_midnight() {
+        // Entre el atardecer (0.75) y la medianoche siguiente se interpola hacia la noche
+        assert_eq!(at(0.875).sky_tint(), SKY_KEYS[3].1.lerp(SKY_KEYS[0].1, 0.5));
+        // Sin saltos a ambos lados de la medianoche
+        assert!(channel_distance(at(0.999).sky_tint(), at(0.0).sky_tint()) <= 2);
+        assert!(channel_distance(at(0.001).sky_tint(), at(0.0).sky_tint()) <= 2);
+    }
+
+    #[test]
+    fn clock_wraps_around() {
+        let mut cycle = DayCycle::new(10.0);
+        cycle.update(10.0);
+        assert!((cycle.time - 0.5).abs() < 1e-12);
+        cycle.update(7.5);
+        assert!((cycle.time - 0.25).abs() < 1e-12);
+        assert_eq!(cycle.sky_tint(), SKY_KEYS[1].1);
+        // Hacia atrás también se queda dentro del ciclo
+        cycle.update(-5.0);
+        assert!((cycle.time - 0.75).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,392 @@
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use image::codecs::png::PngEncoder;
+use image::ColorType;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const FRAME_TIMEOUT: Duration = Duration::from_secs(1); // Espera máxima por un cuadro pedido
+const FRAME_POLL: Duration = Duration::from_millis(5);
+
+/// Algo que se mueve por el mapa, tal como se publica en el estado.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Entity {
+    pub kind: &'static str,     // "player" o "marker"
+    pub id: usize,              // Número de jugador o posición del marcador en la lista
+    pub x: f64,
+    pub y: f64,
+    pub direction: Option<f64>, // Solo para lo que mira hacia algún lado
+}
+
+/// Copia de un cuadro presentado, hecha porque un cliente la pidió.
+struct FrameImage {
+    frame: u64,
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+}
+
+/// Estado de un cuadro. Una vez publicado no cambia: el siguiente cuadro publica otro.
+#[derive(Default)]
+struct Snapshot {
+    frame: u64,
+    player: (f64, f64, f64), // Posición y dirección del jugador 1
+    map: Arc<str>,
+    entities: Vec<Entity>,
+    image: Option<Arc<FrameImage>>, // Último cuadro copiado para un cliente, si hubo alguno
+}
+
+/// Doble búfer del estado sin cerrojos: el bucle principal llena un `Snapshot` nuevo y lo
+/// intercambia con el publicado en una sola operación atómica. El puntero del slot es un
+/// `Arc<Snapshot>` (o nulo), y quien lo saca con `swap` es su único dueño hasta devolverlo, así
+/// que nunca se libera un estado que otro hilo está leyendo.
+struct SnapshotSlot {
+    ptr: AtomicPtr<Snapshot>,
+}
+
+impl SnapshotSlot {
+    fn new() -> Self {
+        Self { ptr: AtomicPtr::new(ptr::null_mut()) }
+    }
+
+    /// Reemplaza el estado publicado. Nunca espera a los lectores.
+    fn publish(&self, snapshot: Snapshot) {
+        let old = self.ptr.swap(Arc::into_raw(Arc::new(snapshot)).cast_mut(), Ordering::AcqRel);
+        if !old.is_null() {
+            // SAFETY: el puntero salió de `Arc::into_raw` y el swap lo sacó del slot, así que
+            // este hilo es su único dueño
+            drop(unsafe { Arc::from_raw(old) });
+        }
+    }
+
+    /// Estado publicado, o `None` si todavía no hay ninguno o justo otro lector lo tiene fuera
+    /// del slot. Con varios lectores a la vez puede devolver por un momento uno anterior al último.
+    fn latest(&self) -> Option<Arc<Snapshot>> {
+        let taken = self.ptr.swap(ptr::null_mut(), Ordering::AcqRel);
+        if taken.is_null() {
+            return None;
+        }
+        // SAFETY: igual que en `publish`, el swap dejó a este hilo como único dueño del puntero
+        let snapshot = unsafe { Arc::from_raw(taken) };
+        let copy = Arc::clone(&snapshot);
+        self.put_back(snapshot);
+        Some(copy)
+    }
+
+    /// Devuelve al slot un estado sacado por `latest`. Si mientras tanto entró otro, se queda
+    /// el de cuadro más nuevo, así el último publicado nunca se pierde.
+    fn put_back(&self, mut snapshot: Arc<Snapshot>) {
+        loop {
+            let frame = snapshot.frame;
+            let other = self.ptr.swap(Arc::into_raw(snapshot).cast_mut(), Ordering::AcqRel);
+            if other.is_null() {
+                return;
+            }
+            // SAFETY: el swap sacó `other` del slot, ahora es de este hilo
+            let other = unsafe { Arc::from_raw(other) };
+            if other.frame <= frame {
+                return;
+            }
+            // Se devolvió uno más viejo que el que había: se vuelve a poner el nuevo
+            snapshot = other;
+        }
+    }
+}
+
+impl Drop for SnapshotSlot {
+    fn drop(&mut self) {
+        let last = *self.ptr.get_mut();
+        if !last.is_null() {
+            // SAFETY: con `&mut self` nadie más puede estar usando el slot
+            drop(unsafe { Arc::from_raw(last) });
+        }
+    }
+}
+
+struct Shared {
+    slot: SnapshotSlot,
+    frame_wanted: AtomicBool, // Un cliente espera una copia del próximo cuadro
+}
+
+/// Servidor de depuración en localhost para herramientas externas.
+///
+/// Protocolo: el cliente envía una orden por línea y recibe una respuesta de 4 bytes con la
+/// longitud (big endian) seguida del contenido:
+/// * `state` - JSON con el número de cuadro, la posición del jugador, el mapa y las entidades.
+/// * `frame` - PNG del próximo cuadro presentado.
+pub struct DebugServer {
+    shared: Arc<Shared>,
+    address: SocketAddr,
+    map: Arc<str>,                  // Nombre del mapa ya publicado, para no copiarlo cada cuadro
+    image: Option<Arc<FrameImage>>, // Último cuadro copiado, que siguen llevando los estados siguientes
+}
+
+impl DebugServer {
+    /// Escucha en `127.0.0.1:port` en un hilo aparte, con un hilo más por cliente. Con el
+    /// puerto 0 el sistema elige uno libre (ver `address`).
+    pub fn start(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let address = listener.local_addr()?;
+        let shared = Arc::new(Shared { slot: SnapshotSlot::new(), frame_wanted: AtomicBool::new(false) });
+
+        let accept_shared = Arc::clone(&shared);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let shared = Arc::clone(&accept_shared);
+                thread::spawn(move || {
+                    // Un cliente que se desconecta o falla solo cierra su conexión
+                    let _ = serve(stream, &shared);
+                });
+            }
+        });
+        Ok(Self { shared, address, map: Arc::from(""), image: None })
+    }
+
+    /// Dirección en la que escucha el servidor.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Publica el estado del cuadro. Se llama una vez por cuadro y nunca espera a los clientes.
+    /// El framebuffer solo se copia si algún cliente lo pidió.
+    pub fn publish(&mut self, frame: u64, player: (f64, f64, f64), map: &str, entities: Vec<Entity>, framebuffer: &Framebuffer) {
+        if *self.map != *map {
+            self.map = Arc::from(map);
+        }
+        if self.shared.frame_wanted.swap(false, Ordering::AcqRel) {
+            self.image = Some(Arc::new(FrameImage {
+                frame,
+                width: framebuffer.width,
+                height: framebuffer.height,
+                pixels: framebuffer.buffer.clone(),
+            }));
+        }
+        self.shared.slot.publish(Snapshot { frame, player, map: Arc::clone(&self.map), entities, image: self.image.clone() });
+    }
+}
+
+/// Atiende las órdenes de un cliente hasta que se desconecta.
+fn serve(stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    // Estado más nuevo que vio este cliente, por si otro lector tiene el publicado fuera del slot
+    let mut last = Arc::new(Snapshot::default());
+    for line in BufReader::new(stream).lines() {
+        if let Some(snapshot) = shared.slot.latest().filter(|snapshot| snapshot.frame >= last.frame) {
+            last = snapshot;
+        }
+        let response = match line?.trim() {
+            "state" => state_json(&last).into_bytes(),
+            "frame" => next_frame_png(shared, last.frame).unwrap_or_else(|err| error_json(&err.to_string())),
+            other => error_json(&format!("unknown request '{}'", other)),
+        };
+        writer.write_all(&(response.len() as u32).to_be_bytes())?;
+        writer.write_all(&response)?;
+    }
+    Ok(())
+}
+
+fn error_json(message: &str) -> Vec<u8> {
+    format!("{{\"error\":\"{}\"}}", escape(message)).into_bytes()
+}
+
+fn state_json(snapshot: &Snapshot) -> String {
+    let (x, y, direction) = snapshot.player;
+    let entities: Vec<String> = snapshot.entities.iter().map(entity_json).collect();
+    format!(
+        "{{\"frame\":{},\"player\":{{\"x\":{},\"y\":{},\"direction\":{}}},\"map\":\"{}\",\"entities\":[{}]}}",
+        snapshot.frame,
+        x,
+        y,
+        direction,
+        escape(&snapshot.map),
+        entities.join(",")
+    )
+}
+
+fn entity_json(entity: &Entity) -> String {
+    let direction = entity.direction.map_or_else(|| "null".to_string(), |direction| direction.to_string());
+    format!(
+        "{{\"kind\":\"{}\",\"id\":{},\"x\":{},\"y\":{},\"direction\":{}}}",
+        entity.kind, entity.id, entity.x, entity.y, direction
+    )
+}
+
+/// Pide una copia de un cuadro posterior a `after` y espera a que el bucle principal la publique.
+fn next_frame_png(shared: &Shared, after: u64) -> io::Result<Vec<u8>> {
+    shared.frame_wanted.store(true, Ordering::Release);
+    let start = Instant::now();
+    loop {
+        let image = shared.slot.latest().and_then(|snapshot| snapshot.image.clone());
+        if let Some(image) = image.filter(|image| image.frame > after) {
+            return encode_png(image.width, image.height, &image.pixels);
+        }
+        if start.elapsed() > FRAME_TIMEOUT {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "no frame was published"));
+        }
+        thread::sleep(FRAME_POLL);
+    }
+}
+
+fn encode_png(width: usize, height: usize, pixels: &[u32]) -> io::Result<Vec<u8>> {
+    let rgb: Vec<u8> = pixels
+        .iter()
+        .map(|&pixel| Color::from_u32(pixel))
+        .flat_map(|color| [color.r(), color.g(), color.b()])
+        .collect();
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png)
+        .encode(&rgb, width as u32, height as u32, ColorType::Rgb8)
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    Ok(png)
+}
+
+/// Escapa un texto para ponerlo entre comillas en JSON.
+fn escape(text: &str) -> String {
+    text.chars()
+        .flat_map(|ch| match ch {
+            '"' | '\\' => vec!['\\', ch],
+            ch if ch.is_control() => format!("\\u{:04x}", ch as u32).chars().collect(),
+            ch => vec![ch],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::sync::atomic::AtomicU64;
+
+    /// Envía una orden y lee la respuesta con su prefijo de longitud.
+    fn request(stream: &mut TcpStream, command: &str) -> Vec<u8> {
+        stream.write_all(format!("{}\n", command).as_bytes()).unwrap();
+        let mut length = [0; 4];
+        stream.read_exact(&mut length).unwrap();
+        let mut response = vec![0; u32::from_be_bytes(length) as usize];
+        stream.read_exact(&mut response).unwrap();
+        response
+    }
+
+    /// Número que sigue a `"key":` en un JSON de una sola línea.
+    fn json_number(json: &str, key: &str) -> f64 {
+        let start = json.find(&format!("\"{}\":", key)).unwrap_or_else(|| panic!("no {} in {}", key, json)) + key.len() + 3;
+        let end = json[start..].find([',', '}']).unwrap() + start;
+        json[start..end].parse().unwrap()
+    }
+
+    fn entities(frame: u64) -> Vec<Entity> {
+        vec![
+            Entity { kind: "player", id: 1, x: 1.5 + frame as f64, y: 2.5, direction: Some(0.25) },
+            Entity { kind: "marker", id: 0, x: 3.0, y: 4.0, direction: None },
+        ]
+    }
+
+    /// Servidor en un puerto libre, con un hilo que publica cuadros hasta que se baja la bandera
+    /// devuelta. También devuelve el último cuadro publicado.
+    fn running_server() -> (SocketAddr, Arc<AtomicBool>, Arc<AtomicU64>) {
+        let mut server = DebugServer::start(0).unwrap();
+        let address = server.address();
+        let running = Arc::new(AtomicBool::new(true));
+        let published = Arc::new(AtomicU64::new(0));
+        let (keep, count) = (Arc::clone(&running), Arc::clone(&published));
+        thread::spawn(move || {
+            let mut framebuffer = Framebuffer::new(4, 3).unwrap();
+            let mut frame = 0;
+            while keep.load(Ordering::Acquire) {
+                frame += 1;
+                framebuffer.buffer.fill(frame as u32 & 0xFF);
+                server.publish(frame, (frame as f64, 2.0, 0.5), "maps/\"test\".txt", entities(frame), &framebuffer);
+                count.store(frame, Ordering::Release);
+                thread::sleep(Duration::from_millis(1));
+            }
+        });
+        // Se espera al primer cuadro para que el estado no sea el vacío
+        while published.load(Ordering::Acquire) == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        (address, running, published)
+    }
+
+    #[test]
+    fn client_sees_monotonic_frames() {
+        let (address, running, _) = running_server();
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut frames = Vec::new();
+        for _ in 0..10 {
+            let json = String::from_utf8(request(&mut stream, "state")).unwrap();
+            assert!(json.starts_with("{\"frame\":") && json.ends_with('}'), "{}", json);
+            assert!(json.contains("\"map\":\"maps/\\\"test\\\".txt\""), "{}", json);
+            assert!(json.contains("\"kind\":\"player\",\"id\":1,"), "{}", json);
+            assert!(json.contains("{\"kind\":\"marker\",\"id\":0,\"x\":3,\"y\":4,\"direction\":null}"), "{}", json);
+
+            let frame = json_number(&json, "frame");
+            assert_eq!(json_number(&json, "x"), frame);
+            frames.push(frame);
+            thread::sleep(Duration::from_millis(3));
+        }
+        assert!(frames.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", frames);
+        assert!(frames[9] > frames[0], "{:?}", frames);
+        running.store(false, Ordering::Release);
+    }
+
+    #[test]
+    fn frame_request_returns_a_later_png() {
+        let (address, running, published) = running_server();
+        let mut stream = TcpStream::connect(address).unwrap();
+        let before = published.load(Ordering::Acquire);
+        let png = request(&mut stream, "frame");
+        let image = image::load_from_memory(&png).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (4, 3));
+        // El azul del cuadro lleva su número: tiene que ser posterior al pedido
+        assert!(image.get_pixel(0, 0)[2] as u64 > before % 256 || before >= 255);
+        running.store(false, Ordering::Release);
+    }
+
+    #[test]
+    fn unknown_requests_get_an_error() {
+        let (address, running, _) = running_server();
+        let mut stream = TcpStream::connect(address).unwrap();
+        assert_eq!(request(&mut stream, "quit"), b"{\"error\":\"unknown request 'quit'\"}");
+        running.store(false, Ordering::Release);
+    }
+
+    #[test]
+    fn slot_keeps_the_latest_snapshot_for_every_reader() {
+        let slot = SnapshotSlot::new();
+        assert!(slot.latest().is_none());
+        slot.publish(Snapshot { frame: 1, ..Snapshot::default() });
+        slot.publish(Snapshot { frame: 2, ..Snapshot::default() });
+        assert_eq!(slot.latest().unwrap().frame, 2);
+        // Leer no lo saca del slot
+        assert_eq!(slot.latest().unwrap().frame, 2);
+    }
+
+    #[test]
+    fn slot_survives_concurrent_readers() {
+        let slot = Arc::new(SnapshotSlot::new());
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let slot = Arc::clone(&slot);
+                thread::spawn(move || {
+                    for _ in 0..10_000 {
+                        if let Some(snapshot) = slot.latest() {
+                            assert!(snapshot.frame <= 10_000);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for frame in 1..=10_000 {
+            slot.publish(Snapshot { frame, ..Snapshot::default() });
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(slot.latest().unwrap().frame, 10_000);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::paths;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs;
+use std::panic;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const INPUT_HISTORY: usize = 120; // Cuadros de entrada que se guardan para el informe
+
+// Bits de la entrada de un cuadro
+pub const INPUT_FORWARD: u8 = 1;
+pub const INPUT_BACKWARD: u8 = 2;
+pub const INPUT_LEFT: u8 = 4;
+pub const INPUT_RIGHT: u8 = 8;
+
+/// Estado del juego que se vuelca en el informe de fallo. El bucle principal lo actualiza
+/// una vez por cuadro; solo copia unos pocos valores, así que es barato.
+#[derive(Default)]
+pub struct DiagnosticsSnapshot {
+    pub frame: u64,
+    pub player: (f64, f64, f64), // Posición y dirección del jugador 1
+    pub map: String,             // Ruta del mapa, o "built-in"
+    pub settings: Vec<(&'static str, String)>,
+    input: VecDeque<u8>, // Bits INPUT_* de los últimos cuadros, del más viejo al más nuevo
+}
+
+impl DiagnosticsSnapshot {
+    /// Registra la entrada del jugador 1 en el cuadro actual.
+    pub fn push_input(&mut self, bits: u8) {
+        if self.input.len() == INPUT_HISTORY {
+            self.input.pop_front();
+        }
+        self.input.push_back(bits);
+    }
+
+    /// Cambia el valor de un ajuste, o lo añade si no existía.
+    pub fn set_setting(&mut self, name: &'static str, value: String) {
+        match self.settings.iter_mut().find(|(key, _)| *key == name) {
+            Some(entry) => entry.1 = value,
+            None => self.settings.push((name, value)),
+        }
+    }
+
+    /// Texto del informe con el estado guardado.
+    fn report(&self) -> String {
+        let mut text = String::new();
+        let (x, y, direction) = self.player;
+        let _ = writeln!(text, "frame: {}", self.frame);
+        let _ = writeln!(text, "map: {}", self.map);
+        let _ = writeln!(text, "player: x={:.3} y={:.3} direction={:.3}", x, y, direction);
+
+        let _ = writeln!(text, "\n[settings]");
+        for (name, value) in &self.settings {
+            let _ = writeln!(text, "{} = {}", name, value);
+        }
+
+        // Una línea por cuadro, empezando por el más viejo: F adelante, B atrás, L y R giros
+        let _ = writeln!(text, "\n[input]");
+        let first = self.frame + 1 - self.input.len() as u64;
+        for (i, bits) in self.input.iter().enumerate() {
+            let flag = |bit: u8, ch: char| if bits & bit != 0 { ch } else { '.' };
+            let _ = writeln!(
+                text,
+                "{} {}{}{}{}",
+                first + i as u64,
+                flag(INPUT_FORWARD, 'F'),
+                flag(INPUT_BACKWARD, 'B'),
+                flag(INPUT_LEFT, 'L'),
+                flag(INPUT_RIGHT, 'R'),
+            );
+        }
+        text
+    }
+}
+
+/// Texto completo del informe de fallo: el mensaje del panic, el backtrace y el estado del
+/// juego, o `unavailable` si no se pudo leer.
+fn crash_report(message: &str, backtrace: &str, state: Option<&DiagnosticsSnapshot>) -> String {
+    let mut text = format!("panic: {}\n", message);
+    let _ = writeln!(text, "\n[backtrace]\n{}", backtrace);
+    match state {
+        Some(snapshot) => text.push_str(&format!("\n[state]\n{}", snapshot.report())),
+        None => text.push_str("\n[state]\nunavailable\n"),
+    }
+    text
+}
+
+/// Instala un panic hook que escribe un informe `crash_<timestamp>.txt` (en la carpeta `crashes`
+/// de `paths::output_dir`) con el mensaje, el backtrace y el estado de `snapshot`, y después
+/// llama al hook anterior.
+pub fn install_panic_hook(snapshot: Arc<Mutex<DiagnosticsSnapshot>>) {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let backtrace = std::backtrace::Backtrace::force_capture().to_string();
+        // try_lock: si el hilo que falló tenía el estado bloqueado, no esperar por él
+        let text = match snapshot.try_lock() {
+            Ok(snapshot) => crash_report(&info.to_string(), &backtrace, Some(&snapshot)),
+            Err(_) => crash_report(&info.to_string(), &backtrace, None),
+        };
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+        let path = paths::output_dir("crashes").join(format!("crash_{}.txt", timestamp));
+        match fs::write(&path, text) {
+            Ok(()) => eprintln!("Crash report written to {}", path.display()),
+            Err(err) => eprintln!("Cannot write crash report {}: {}", path.display(), err),
+        }
+
+        previous(info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> DiagnosticsSnapshot {
+        let mut snapshot = DiagnosticsSnapshot { frame: 41, player: (1.5, 2.25, 0.5), map: "maps/test.txt".to_string(), ..Default::default() };
+        snapshot.set_setting("fov", "60".to_string());
+        snapshot.set_setting("split", "false".to_string());
+        snapshot.set_setting("fov", "90".to_string());
+        snapshot.push_input(INPUT_FORWARD);
+        snapshot.push_input(INPUT_FORWARD | INPUT_LEFT);
+        snapshot.push_input(0);
+        snapshot
+    }
+
+    #[test]
+    fn report_lists_state_settings_and_input() {
+        let expected = "\
+frame: 41
+map: maps/test.txt
+player: x=1.500 y=2.250 direction=0.500
+
+[settings]
+fov = 90
+split = false
+
+[input]
+39 F...
+40 F.L.
+41 ....
+";
+        assert_eq!(snapshot().report(), expected);
+    }
+
+    #[test]
+    fn input_history_keeps_the_last_frames() {
+        let mut snapshot = DiagnosticsSnapshot::default();
+        for frame in 0..INPUT_HISTORY as u64 + 10 {
+            snapshot.frame = frame;
+            snapshot.push_input(if frame % 2 == 0 { INPUT_RIGHT } else { INPUT_BACKWARD });
+        }
+        let report = snapshot.report();
+        let input: Vec<&str> = report.lines().skip_while(|line| *line != "[input]").skip(1).collect();
+        assert_eq!(input.len(), INPUT_HISTORY);
+        assert_eq!(input[0], "10 ...R");
+        assert_eq!(input[INPUT_HISTORY - 1], "129 .B..");
+    }
+
+    #[test]
+    fn crash_report_has_every_section() {
+        let report = crash_report("oops at src/main.rs:1:2", "0: main", Some(&snapshot()));
+        assert!(report.starts_with("panic: oops at src/main.rs:1:2\n\n[backtrace]\n0: main\n\n[state]\nframe: 41\n"), "{}", report);
+        assert!(report.ends_with("41 ....\n"), "{}", report);
+    }
+
+    #[test]
+    fn crash_report_without_state() {
+        assert_eq!(crash_report("oops", "", None), "panic: oops\n\n[backtrace]\n\n\n[state]\nunavailable\n");
+    }
+}
@@ -0,0 +1,100 @@
+use crate::color::Color;
+
+const FADE_BAND: f64 = 0.25; // Fracción final de la distancia máxima en la que se termina de fundir con la niebla
+
+/// Niebla exponencial al cuadrado (exp2), con una distancia máxima opcional a partir de la
+/// cual todo es del color de la niebla.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Fog {
+    pub density: f64,      // 0.0 desactiva la parte exponencial
+    pub color: Color,
+    pub max_distance: f64, // f64::INFINITY si no hay distancia máxima
+}
+
+impl Fog {
+    /// Fracción del color original que se ve a `distance` (1.0 sin niebla, 0.0 solo niebla).
+    pub fn visibility(&self, distance: f64) -> f64 {
+        let exp2 = (-(self.density * distance).powi(2)).exp();
+        if !self.max_distance.is_finite() {
+            return exp2;
+        }
+
+        // Cerca de la distancia máxima se termina de fundir para que no haya un corte brusco
+        let fade = ((self.max_distance - distance) / (self.max_distance * FADE_BAND)).clamp(0.0, 1.0);
+        exp2 * fade
+    }
+
+    /// Mezcla `color` con el color de la niebla según la distancia.
+    pub fn apply(&self, color: Color, distance: f64) -> Color {
+        self.color.lerp(color, self.visibility(distance)).drawable()
+    }
+
+    /// Color de lo que queda completamente dentro de la niebla.
+    pub fn solid_color(&self) -> Color {
+        self.color.drawable()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GREY: Color = Color::from_u32(0x808080);
+    const WALL: Color = Color::from_u32(0xC04020);
+
+    fn fog(density: f64, max_distance: f64) -> Fog {
+        Fog { density, color: GREY, max_distance }
+    }
+
+    #[test]
+    fn near_walls_keep_their_colour() {
+        let fog = fog(0.1, 20.0);
+        assert_eq!(fog.visibility(0.0), 1.0);
+        assert_eq!(fog.apply(WALL, 0.0), WALL);
+        // A un paso la niebla apenas se nota
+        assert!(fog.visibility(1.0) > 0.98);
+    }
+
+    #[test]
+    fn visibility_falls_with_distance() {
+        let fog = fog(0.1, f64::INFINITY);
+        let samples: Vec<f64> = (0..40).map(|step| fog.visibility(step as f64 * 0.5)).collect();
+        assert!(samples.windows(2).all(|pair| pair[1] < pair[0]), "{:?}", samples);
+        // exp2: a 1 / densidad queda exp(-1)
+        assert!((fog.visibility(10.0) - (-1.0_f64).exp()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn far_walls_take_the_fog_colour() {
+        let fog = fog(0.1, 20.0);
+        assert_eq!(fog.visibility(20.0), 0.0);
+        assert_eq!(fog.apply(WALL, 20.0), GREY);
+        assert_eq!(fog.apply(WALL, 1000.0), GREY);
+    }
+
+    #[test]
+    fn fade_band_is_clamped() {
+        // Sin parte exponencial solo queda el fundido de la última cuarta parte
+        let fog = fog(0.0, 8.0);
+        assert_eq!(fog.visibility(-1.0), 1.0);
+        assert_eq!(fog.visibility(6.0), 1.0);
+        assert!((fog.visibility(7.0) - 0.5).abs() < 1e-12);
+        assert_eq!(fog.visibility(8.0), 0.0);
+        assert_eq!(fog.visibility(50.0), 0.0);
+    }
+
+    #[test]
+    fn zero_density_without_limit_is_clear() {
+        let fog = fog(0.0, f64::INFINITY);
+        for distance in [0.0, 10.0, 1e6] {
+            assert_eq!(fog.visibility(distance), 1.0);
+        }
+    }
+
+    #[test]
+    fn black_fog_stays_drawable() {
+        let fog = Fog { density: 1.0, color: Color::from_u32(0), max_distance: 4.0 };
+        assert_eq!(fog.apply(WALL, 10.0), Color::from_u32(1));
+        assert_eq!(fog.solid_color(), Color::from_u32(1));
+    }
+}
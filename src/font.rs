@@ -0,0 +1,160 @@
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+
+/// Alto de un carácter en píxeles de la fuente (a escala 1x).
+pub const GLYPH_HEIGHT: usize = 5;
+const GLYPH_ADVANCE: usize = 6; // Ancho de un carácter más el espacio que lo separa del siguiente
+
+// Fuente de mapa de bits de 5x5: cada byte es una fila y los 5 bits bajos son las columnas
+const FONT: [[u8; 5]; 59] = [
+    [0b01110, 0b10001, 0b10001, 0b10001, 0b01110], // 0
+    [0b00100, 0b01100, 0b00100, 0b00100, 0b01110], // 1
+    [0b01110, 0b10001, 0b00110, 0b01000, 0b11111], // 2
+    [0b01110, 0b10001, 0b00110, 0b10001, 0b01110], // 3
+    [0b00010, 0b00110, 0b01010, 0b11111, 0b00010], // 4
+    [0b11111, 0b10000, 0b11110, 0b00001, 0b11110], // 5
+    [0b01110, 0b10000, 0b11110, 0b10001, 0b01110], // 6
+    [0b11111, 0b00010, 0b00100, 0b01000, 0b10000], // 7
+    [0b01110, 0b10001, 0b01110, 0b10001, 0b01110], // 8
+    [0b01110, 0b10001, 0b01111, 0b00001, 0b01110], // 9
+    [0b01110, 0b10001, 0b11111, 0b10001, 0b10001], // A
+    [0b11110, 0b10001, 0b11110, 0b10001, 0b11110], // B
+    [0b01111, 0b10000, 0b10000, 0b10000, 0b01111], // C
+    [0b11110, 0b10001, 0b10001, 0b10001, 0b11110], // D
+    [0b11111, 0b10000, 0b11110, 0b10000, 0b11111], // E
+    [0b11111, 0b10000, 0b11110, 0b10000, 0b10000], // F
+    [0b01111, 0b10000, 0b10011, 0b10001, 0b01111], // G
+    [0b10001, 0b10001, 0b11111, 0b10001, 0b10001], // H
+    [0b01110, 0b00100, 0b00100, 0b00100, 0b01110], // I
+    [0b00111, 0b00010, 0b00010, 0b10010, 0b01100], // J
+    [0b10010, 0b10100, 0b11000, 0b10100, 0b10010], // K
+    [0b10000, 0b10000, 0b10000, 0b10000, 0b11111], // L
+    [0b10001, 0b11011, 0b10101, 0b10001, 0b10001], // M
+    [0b10001, 0b11001, 0b10101, 0b10011, 0b10001], // N
+    [0b01110, 0b10001, 0b10001, 0b10001, 0b01110], // O
+    [0b11110, 0b10001, 0b11110, 0b10000, 0b10000], // P
+    [0b01110, 0b10001, 0b10101, 0b10010, 0b01101], // Q
+    [0b11110, 0b10001, 0b11110, 0b10100, 0b10010], // R
+    [0b01111, 0b10000, 0b01110, 0b00001, 0b11110], // S
+    [0b11111, 0b00100, 0b00100, 0b00100, 0b00100], // T
+    [0b10001, 0b10001, 0b10001, 0b10001, 0b01110], // U
+    [0b10001, 0b10001, 0b10001, 0b01010, 0b00100], // V
+    [0b10001, 0b10001, 0b10101, 0b11011, 0b10001], // W
+    [0b10001, 0b01010, 0b00100, 0b01010, 0b10001], // X
+    [0b10001, 0b01010, 0b00100, 0b00100, 0b00100], // Y
+    [0b11111, 0b00010, 0b00100, 0b01000, 0b11111], // Z
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00000], // espacio
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00100], // .
+    [0b00000, 0b00000, 0b00000, 0b00100, 0b01000], // ,
+    [0b00000, 0b00100, 0b00000, 0b00100, 0b00000], // :
+    [0b00000, 0b00000, 0b01110, 0b00000, 0b00000], // -
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b11111], // _
+    [0b00001, 0b00010, 0b00100, 0b01000, 0b10000], // /
+    [0b00000, 0b11111, 0b00000, 0b11111, 0b00000], // =
+    [0b00010, 0b00100, 0b01000, 0b00100, 0b00010], // <
+    [0b01000, 0b00100, 0b00010, 0b00100, 0b01000], // >
+    [0b01110, 0b10001, 0b00110, 0b00000, 0b00100], // ?
+    [0b00100, 0b00100, 0b00100, 0b00000, 0b00100], // !
+    [0b00100, 0b00100, 0b00000, 0b00000, 0b00000], // '
+    [0b00010, 0b00100, 0b00100, 0b00100, 0b00010], // (
+    [0b01000, 0b00100, 0b00100, 0b00100, 0b01000], // )
+    // Letras y signos del español; el acento o la tilde ocupan la fila de arriba
+    [0b00010, 0b01110, 0b10001, 0b11111, 0b10001], // Á
+    [0b00010, 0b11111, 0b11100, 0b10000, 0b11111], // É
+    [0b00010, 0b01110, 0b00100, 0b00100, 0b01110], // Í
+    [0b00010, 0b01110, 0b10001, 0b10001, 0b01110], // Ó
+    [0b00010, 0b10001, 0b10001, 0b10001, 0b01110], // Ú
+    [0b01110, 0b00000, 0b11001, 0b10101, 0b10011], // Ñ
+    [0b00100, 0b00000, 0b01100, 0b10001, 0b01110], // ¿
+    [0b00100, 0b00000, 0b00100, 0b00100, 0b00100], // ¡
+];
+
+const PUNCTUATION: &str = " .,:-_/=<>?!'()ÁÉÍÓÚÑ¿¡";
+
+fn draw_glyph(framebuffer: &mut Framebuffer, x: usize, y: usize, index: usize, color: Color, scale: usize) {
+    if index >= FONT.len() { return; }
+
+    // Cada píxel de la fuente se dibuja como un bloque de `scale` x `scale`
+    for (row, byte) in FONT[index].iter().enumerate() {
+        for col in 0..5 {
+            if byte & (1 << (4 - col)) != 0 {
+                for py in 0..scale {
+                    for px in 0..scale {
+                        framebuffer.point(x + col * scale + px, y + row * scale + py, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Índice del carácter en `FONT`, o `None` si la fuente no lo tiene.
+/// Las minúsculas se dibujan como mayúsculas, también las acentuadas.
+fn glyph_index(ch: char) -> Option<usize> {
+    match ch.to_uppercase().next().unwrap_or(ch) {
+        ch @ '0'..='9' => Some(ch as usize - '0' as usize),
+        ch @ 'A'..='Z' => Some(10 + ch as usize - 'A' as usize),
+        ch => PUNCTUATION.chars().position(|other| other == ch).map(|i| 36 + i),
+    }
+}
+
+/// Tamaño en píxeles (ancho, alto) que ocupa `text` al dibujarlo con `draw_text`.
+pub fn measure_text(text: &str, scale: usize) -> (usize, usize) {
+    let glyphs = text.chars().filter_map(glyph_index).count();
+    let width = (glyphs * GLYPH_ADVANCE).saturating_sub(1) * scale; // Sin el espacio tras el último carácter
+    (width, GLYPH_HEIGHT * scale)
+}
+
+pub fn draw_text(framebuffer: &mut Framebuffer, x: usize, y: usize, text: &str, color: Color, scale: usize) {
+    let mut x_offset = 0;
+
+    for index in text.chars().filter_map(glyph_index) {
+        draw_glyph(framebuffer, x + x_offset, y, index, color, scale);
+        x_offset += GLYPH_ADVANCE * scale; // Espacio entre caracteres
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Píxeles encendidos de `text` dibujado a escala 1, fila por fila.
+    fn render(text: &str) -> Vec<Vec<bool>> {
+        let (width, height) = measure_text(text, 1);
+        let mut framebuffer = Framebuffer::new(width.max(1), height).unwrap();
+        draw_text(&mut framebuffer, 0, 0, text, Color::WHITE, 1);
+        (0..height).map(|y| (0..width).map(|x| framebuffer.pixel(x, y) == Color::WHITE).collect()).collect()
+    }
+
+    #[test]
+    fn spanish_letters_have_glyphs() {
+        for ch in "áéíóúñ¿¡ÁÉÍÓÚÑ".chars() {
+            assert!(glyph_index(ch).is_some(), "no glyph for '{}'", ch);
+        }
+        assert_eq!(glyph_index('ñ'), glyph_index('Ñ'));
+    }
+
+    #[test]
+    fn enie_is_drawn_without_dropping_glyphs() {
+        assert_eq!(measure_text("año", 1), (3 * GLYPH_ADVANCE - 1, GLYPH_HEIGHT));
+        let word = render("año");
+        let letter = render("Ñ");
+        // La Ñ queda en la segunda posición, entre la A y la O
+        for (row, pixels) in word.iter().enumerate() {
+            assert_eq!(&pixels[GLYPH_ADVANCE..GLYPH_ADVANCE + 5], &letter[row][..]);
+        }
+        assert_eq!(word, render("AÑO"));
+    }
+
+    #[test]
+    fn unknown_characters_are_skipped() {
+        assert_eq!(glyph_index('€'), None);
+        assert_eq!(measure_text("a€b", 1), measure_text("ab", 1));
+    }
+
+    #[test]
+    fn measure_text_scales() {
+        assert_eq!(measure_text("", 2), (0, GLYPH_HEIGHT * 2));
+        assert_eq!(measure_text("ok", 3), ((2 * GLYPH_ADVANCE - 1) * 3, GLYPH_HEIGHT * 3));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::color::Color;
+
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
@@ -5,17 +7,116 @@ pub struct Framebuffer {
 }
 
 impl Framebuffer {
-    pub fn new(width: usize, height: usize) -> Self {
-        Self {
+    /// Crea un framebuffer de `width` x `height` píxeles. Las rutinas de dibujo suponen al menos
+    /// un píxel, así que un tamaño 0 en cualquiera de los dos ejes es un error.
+    pub fn new(width: usize, height: usize) -> Result<Self, String> {
+        if width == 0 || height == 0 {
+            return Err(format!("framebuffer must be at least 1x1, got {}x{}", width, height));
+        }
+        Ok(Self {
             width,
             height,
             buffer: vec![0; width * height],
-        }
+        })
     }
 
-    pub fn point(&mut self, x: usize, y: usize, color: u32) {
+    pub fn point(&mut self, x: usize, y: usize, color: Color) {
         if x < self.width && y < self.height {
-            self.buffer[y * self.width + x] = color;
+            self.buffer[y * self.width + x] = color.to_u32();
+        }
+    }
+
+    /// Color del píxel (x, y), que debe estar dentro del framebuffer.
+    #[inline]
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        Color::from_u32(self.buffer[y * self.width + x])
+    }
+
+    /// Rellena un rectángulo de `width` x `height` con su esquina superior izquierda en (`x`, `y`),
+    /// recortando lo que quede fuera del framebuffer.
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        for py in y..(y + height).min(self.height) {
+            for px in x..(x + width).min(self.width) {
+                self.buffer[py * self.width + px] = color.to_u32();
+            }
+        }
+    }
+
+    /// Dibuja una línea de 1 píxel entre dos puntos (algoritmo de Bresenham). Los puntos pueden
+    /// estar fuera del framebuffer; solo se dibuja la parte visible.
+    pub fn line(&mut self, x0: isize, y0: isize, x1: isize, y1: isize, color: Color) {
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (step_x, step_y) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+        let (mut x, mut y) = (x0, y0);
+        let mut error = dx + dy;
+
+        loop {
+            if x >= 0 && y >= 0 {
+                self.point(x as usize, y as usize, color);
+            }
+            if x == x1 && y == y1 {
+                break;
+            }
+
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Escala la región superior izquierda de `src` (de `src_width` x `src_height` píxeles)
+    /// para cubrir la región `area` de este framebuffer, usando el vecino más cercano.
+    pub fn upscale_into(&mut self, src: &Framebuffer, src_width: usize, src_height: usize, area: Viewport) {
+        for y in 0..area.height {
+            let sy = y * src_height / area.height;
+            let row = (area.y + y) * self.width + area.x;
+            for x in 0..area.width {
+                let sx = x * src_width / area.width;
+                self.buffer[row + x] = src.buffer[sy * src.width + sx];
+            }
+        }
+    }
+
+    /// Reduce a la mitad la región superior izquierda de este framebuffer (de `2 * width` x
+    /// `2 * height` píxeles) y la escribe en la esquina superior izquierda de `dst`, promediando
+    /// cada bloque de 2x2 píxeles.
+    pub fn downsample_into(&self, dst: &mut Framebuffer, width: usize, height: usize) {
+        for y in 0..height.min(dst.height) {
+            let top = 2 * y * self.width;
+            let bottom = top + self.width;
+            for x in 0..width.min(dst.width) {
+                let left = 2 * x;
+                let pixel = |i: usize| Color::from_u32(self.buffer[i]);
+                dst.buffer[y * dst.width + x] = Color::average([
+                    pixel(top + left),
+                    pixel(top + left + 1),
+                    pixel(bottom + left),
+                    pixel(bottom + left + 1),
+                ])
+                .to_u32();
+            }
+        }
+    }
+
+    /// Copia `src` completo con su esquina superior izquierda en (`dst_x`, `dst_y`),
+    /// recortando lo que quede fuera de este framebuffer.
+    pub fn blit(&mut self, src: &Framebuffer, dst_x: usize, dst_y: usize) {
+        if dst_x >= self.width || dst_y >= self.height {
+            return;
+        }
+
+        let width = src.width.min(self.width - dst_x);
+        let height = src.height.min(self.height - dst_y);
+        for y in 0..height {
+            let src_start = y * src.width;
+            let dst_start = (dst_y + y) * self.width + dst_x;
+            self.buffer[dst_start..dst_start + width].copy_from_slice(&src.buffer[src_start..src_start + width]);
         }
     }
 
@@ -27,3 +128,122 @@ impl Framebuffer {
         }
     }
 }
+
+/// Región rectangular del framebuffer donde se dibuja una vista 3D.
+#[derive(Clone, Copy)]
+pub struct Viewport {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Viewport {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// Indica si el punto (x, y) está dentro de la región.
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+
+    /// Píxel del framebuffer bajo la posición (`x`, `y`) de la ventana, como la del cursor, o
+    /// `None` si cae fuera de la región (en las barras o fuera de la ventana). La ventana no
+    /// escala la imagen, así que cada píxel de la ventana es uno del framebuffer.
+    pub fn to_framebuffer(self, x: f32, y: f32) -> Option<(usize, usize)> {
+        if !(x >= 0.0 && y >= 0.0) {
+            return None;
+        }
+        let (x, y) = (x as usize, y as usize);
+        self.contains(x, y).then_some((x, y))
+    }
+
+    /// Región centrada más grande de una ventana de `window_width` x `window_height` que conserva
+    /// la proporción de una imagen de `width` x `height`. El resto de la ventana queda como barras.
+    pub fn letterbox(width: usize, height: usize, window_width: usize, window_height: usize) -> Self {
+        let (fit_width, fit_height) = if window_width * height > window_height * width {
+            // Ventana más ancha que la imagen: barras a los lados
+            (width * window_height / height, window_height)
+        } else {
+            // Ventana más alta que la imagen: barras arriba y abajo
+            (window_width, height * window_width / width)
+        };
+        Self::new(
+            (window_width - fit_width) / 2,
+            (window_height - fit_height) / 2,
+            fit_width,
+            fit_height,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(view: Viewport) -> (usize, usize, usize, usize) {
+        (view.x, view.y, view.width, view.height)
+    }
+
+    #[test]
+    fn letterbox_wider_window_has_side_bars() {
+        assert_eq!(rect(Viewport::letterbox(640, 480, 1280, 600)), (240, 0, 800, 600));
+        assert_eq!(rect(Viewport::letterbox(320, 240, 1920, 1080)), (240, 0, 1440, 1080));
+        assert_eq!(rect(Viewport::letterbox(4, 3, 5, 3)), (0, 0, 4, 3));
+    }
+
+    #[test]
+    fn letterbox_taller_window_has_top_and_bottom_bars() {
+        assert_eq!(rect(Viewport::letterbox(640, 480, 640, 800)), (0, 160, 640, 480));
+        assert_eq!(rect(Viewport::letterbox(320, 240, 300, 1000)), (0, 387, 300, 225));
+    }
+
+    #[test]
+    fn letterbox_exact_aspect_fills_the_window() {
+        for (width, height, window_width, window_height) in [(640, 480, 640, 480), (640, 480, 1280, 960), (320, 240, 160, 120)] {
+            assert_eq!(rect(Viewport::letterbox(width, height, window_width, window_height)), (0, 0, window_width, window_height));
+        }
+    }
+
+    #[test]
+    fn letterbox_keeps_the_aspect() {
+        for (window_width, window_height) in [(1280, 600), (641, 480), (640, 481), (333, 777), (1920, 1200)] {
+            let view = Viewport::letterbox(640, 480, window_width, window_height);
+            assert!(view.x + view.width <= window_width && view.y + view.height <= window_height);
+            // Ancho / alto = 4 / 3, salvo el redondeo de un píxel
+            assert!((view.width as isize * 3 - view.height as isize * 4).abs() <= 4, "{}x{}", view.width, view.height);
+        }
+    }
+
+    #[test]
+    fn mouse_in_the_bars_is_outside() {
+        // Ventana más ancha: barras de 240 px a los lados
+        let wide = Viewport::letterbox(640, 480, 1280, 600);
+        assert_eq!(wide.to_framebuffer(100.0, 300.0), None);
+        assert_eq!(wide.to_framebuffer(1279.0, 300.0), None);
+        assert_eq!(wide.to_framebuffer(240.0, 0.0), Some((240, 0)));
+        assert_eq!(wide.to_framebuffer(1039.9, 599.5), Some((1039, 599)));
+        assert_eq!(wide.to_framebuffer(1040.0, 599.0), None);
+
+        // Ventana más alta: barras de 160 px arriba y abajo
+        let tall = Viewport::letterbox(640, 480, 640, 800);
+        assert_eq!(tall.to_framebuffer(320.0, 100.0), None);
+        assert_eq!(tall.to_framebuffer(320.5, 400.25), Some((320, 400)));
+        assert_eq!(tall.to_framebuffer(320.0, 640.0), None);
+
+        // Misma proporción: toda la ventana es imagen
+        let exact = Viewport::letterbox(640, 480, 640, 480);
+        assert_eq!(exact.to_framebuffer(0.0, 0.0), Some((0, 0)));
+        assert_eq!(exact.to_framebuffer(639.9, 479.9), Some((639, 479)));
+    }
+
+    #[test]
+    fn mouse_outside_the_window_is_outside() {
+        let view = Viewport::new(0, 0, 100, 100);
+        assert_eq!(view.to_framebuffer(-0.5, 10.0), None);
+        assert_eq!(view.to_framebuffer(10.0, -3.0), None);
+        assert_eq!(view.to_framebuffer(f32::NAN, 10.0), None);
+        assert_eq!(view.to_framebuffer(100.0, 10.0), None);
+    }
+}
@@ -1,236 +1,1531 @@
-use minifb::{Key, Window, WindowOptions};
-use std::time::{Duration, Instant};
-use rodio::{Decoder, OutputStream, Sink, Source};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, ScaleMode, Window, WindowOptions};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
 use std::fs::File;
 use std::io::BufReader;
+use std::collections::{HashSet, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 mod framebuffer;
 mod map;
 
-use framebuffer::Framebuffer;
-use map::{initialize_map, Map};
+use framebuffer::{Framebuffer, Viewport};
+use map::{has_errors, initialize_map, load_map, Map};
 
 mod player;
 use player::Player;
 
 mod raycaster;
-use raycaster::cast_ray;
+use raycaster::{cast_ray, RayHit};
+
+mod raycore;
+use raycore::wall_slice;
+
+mod portal;
+
+mod orbit;
+use orbit::OrbitCamera;
+
+mod resolution;
+use resolution::ResolutionController;
+
+mod palette;
+use palette::{find_palette, Palette, PALETTES};
+
+mod camera_path;
+use camera_path::CameraPath;
+
+mod automap;
+use automap::{wall_segments, AutomapView, Segment};
+
+mod console;
+use console::{usages, Command, Console};
+
+mod diagnostics;
+use diagnostics::{install_panic_hook, DiagnosticsSnapshot, INPUT_BACKWARD, INPUT_FORWARD, INPUT_LEFT, INPUT_RIGHT};
+
+mod color;
+use color::Color;
+
+mod font;
+use font::{draw_text, measure_text, GLYPH_HEIGHT};
+
+mod fog;
+use fog::Fog;
+
+mod daycycle;
+use daycycle::DayCycle;
+
+mod paths;
+
+mod recorder;
+use recorder::Recorder;
+
+mod strings;
+use strings::Strings;
+
+mod music;
+use music::{MusicPlayer, Playlist};
+
+mod underwater;
+
+#[cfg(feature = "debug-server")]
+mod debug_server;
+
+#[cfg(feature = "ray-stats")]
+mod raystats;
+
+mod markers;
+use markers::{Markers, MARKER_KINDS};
+
+mod timestep;
+use timestep::FixedTimestep;
+
+mod speedrun;
+use speedrun::{export_summary, format_delta, format_run_time, PersonalBest, SpeedrunTimer, BEST_FILE};
+
+#[cfg(test)]
+mod test_dir;
 
 const WIDTH: usize = 640;  // Ancho de la ventana (en píxeles)
 const HEIGHT: usize = 480; // Altura de la ventana (en píxeles)
-const CELL_SIZE: usize = 1; // Tamaño de cada celda (en píxeles)
+const CELL_SIZE: usize = 1; // Tamaño mínimo de cada celda en `draw_2d_map` (en píxeles)
 
-const COLOR_FONDO: u32 = 0xADD8E6;
-const COLOR_PARED: u32 = 0x000000;
 
+/// Teclas que controlan a un jugador.
+struct Controls {
+    forward: &'static [Key],
+    backward: &'static [Key],
+    left: &'static [Key],
+    right: &'static [Key],
+}
 
+// Un solo jugador: WASD o flechas
+const CONTROLS_SINGLE: Controls = Controls {
+    forward: &[Key::W, Key::Up],
+    backward: &[Key::S, Key::Down],
+    left: &[Key::A, Key::Left],
+    right: &[Key::D, Key::Right],
+};
 
-fn render_scene(map: &Map, player: &Player, framebuffer: &mut Framebuffer) {
-    for x in 0..framebuffer.width {
-        // Calcular el ángulo del rayo para esta columna de la pantalla
-        let camera_x = 2.0 * (x as f64) / (framebuffer.width as f64) - 1.0;
-        let angle_offset = player.fov / 2.0 * camera_x;
+// Pantalla dividida: el jugador 1 usa WASD
+const CONTROLS_P1: Controls = Controls {
+    forward: &[Key::W],
+    backward: &[Key::S],
+    left: &[Key::A],
+    right: &[Key::D],
+};
+
+// Pantalla dividida: el jugador 2 usa IJKL o flechas
+const CONTROLS_P2: Controls = Controls {
+    forward: &[Key::I, Key::Up],
+    backward: &[Key::K, Key::Down],
+    left: &[Key::J, Key::Left],
+    right: &[Key::L, Key::Right],
+};
 
-        // Lanzar el rayo y obtener la distancia a la pared
-        let (perp_wall_dist, is_horizontal) = cast_ray(map, player, angle_offset);
 
-        // Calcular la altura de la pared en la pantalla
-        let mut wall_height = (framebuffer.height as f64 / perp_wall_dist) as usize;
-        
-        // Limitar la altura máxima de la pared
-        wall_height = wall_height.min(framebuffer.height);
+// Proporción para la que el FOV del jugador es exacto. En viewports con otra proporción
+// (p. ej. cada mitad de la pantalla dividida) el ángulo horizontal se ajusta para que la
+// escala horizontal y la vertical sigan siendo las mismas y la imagen no se deforme.
+const REFERENCE_ASPECT: f64 = 4.0 / 3.0;
 
-        let start = (framebuffer.height / 2).saturating_sub(wall_height / 2);
-        let end = (framebuffer.height / 2) + wall_height / 2;
+const AO_EDGE: f64 = 0.1; // Fracción de la cara (o de la altura) que se oscurece junto a los bordes
 
-        // Dibujar la pared en la pantalla
-        let color = if is_horizontal { 0xCCCCCC } else { 0xAAAAAA }; // Diferente color para paredes horizontales y verticales
-        for y in start..end {
-            framebuffer.point(x, y, color);
+/// Factor de oclusión ambiental (1.0 = sin oscurecer) para un punto a distancia `edge_distance`
+/// del borde más cercano, medida en fracción de la cara. Oscurece de forma cuadrática dentro de `AO_EDGE`.
+fn ambient_occlusion(edge_distance: f64, strength: f64) -> f64 {
+    let t = (edge_distance / AO_EDGE).min(1.0);
+    1.0 - strength * (1.0 - t) * (1.0 - t)
+}
+
+/// Opciones del render 3D elegidas al iniciar el juego.
+#[derive(Clone, Copy, PartialEq)]
+struct RenderOptions {
+    ao_strength: f64,     // Intensidad del oscurecimiento en bordes (0.0 lo desactiva)
+    light_tint: Color,    // Color de la luz ambiental (blanco es luz normal)
+    light_intensity: f64, // Intensidad de la luz ambiental (1.0 es normal)
+    fog: Fog,
+    reflections: bool,    // Si el suelo pulido o mojado refleja las paredes
+    #[cfg(feature = "ray-stats")]
+    cost_overlay: bool,   // Franja con el costo del DDA de cada columna (F7)
+}
+
+/// Todo lo que determina la imagen de la escena 3D. Si no cambia entre dos cuadros, la escena
+/// del cuadro anterior sigue siendo válida y no hace falta volver a lanzar los rayos. El mapa
+/// no forma parte de la clave: al cargar otro se descarta la escena guardada.
+#[derive(PartialEq)]
+struct SceneKey {
+    poses: Vec<(f64, f64, f64, f64)>, // Posición, dirección y campo de visión de cada vista
+    size: (usize, usize),
+    palette: Palette,
+    options: RenderOptions,
+    mirror: bool,
+}
+
+impl SceneKey {
+    /// Clave de una escena con las vistas `views` (la cámara del jugador 1, que puede ser la
+    /// orbital, y luego cada jugador) dibujada a `size` píxeles.
+    fn new(views: &[&Player], size: (usize, usize), palette: &Palette, options: &RenderOptions, mirror: bool) -> Self {
+        Self {
+            poses: views.iter().map(|p| (p.x, p.y, p.direction, p.fov)).collect(),
+            size,
+            palette: *palette,
+            options: *options,
+            mirror,
         }
     }
 }
 
+/// Datos de depuración de un render de la escena.
+#[derive(Clone, Copy, Default)]
+struct SceneStats {
+    near_columns: usize, // Columnas con la pared más cerca que `NEAR_PLANE`, dibujadas como pared sólida
+    #[cfg(feature = "ray-stats")]
+    steps: raystats::StepStats,
+}
 
+impl SceneStats {
+    /// Junta los datos de dos vistas (pantalla dividida).
+    fn merge(self, other: SceneStats) -> SceneStats {
+        SceneStats {
+            near_columns: self.near_columns + other.near_columns,
+            #[cfg(feature = "ray-stats")]
+            steps: self.steps.merge(other.steps),
+        }
+    }
+}
 
-fn draw_2d_map(map: &Map, framebuffer: &mut Framebuffer) {
-    let cell_width = framebuffer.width / map.width;
-    let cell_height = framebuffer.height / map.height;
+/// Color de la cara de pared que golpeó el rayo: el de su tipo si el mapa lo define o, si no,
+/// el de la paleta, diferente para paredes horizontales y verticales.
+fn wall_color(map: &Map, palette: &Palette, hit: &RayHit) -> Color {
+    map.wall_color(hit.wall_type, hit.face)
+        .unwrap_or(if hit.is_horizontal { palette.wall_light } else { palette.wall_dark })
+}
 
-    for y in 0..map.height {
-        for x in 0..map.width {
-            let color = if map.is_wall(x as f64, y as f64) {
-                COLOR_PARED
-            } else {
-                COLOR_FONDO
-            };
+/// Mitad del campo de visión horizontal de `player` en `viewport`, ajustada a su proporción.
+fn half_fov(player: &Player, viewport: Viewport) -> f64 {
+    let aspect = viewport.width as f64 / viewport.height as f64;
+    ((player.fov / 2.0).tan() * aspect / REFERENCE_ASPECT).atan()
+}
 
-            for py in 0..cell_height {
-                for px in 0..cell_width {
-                    framebuffer.point(x * cell_width + px, y * cell_height + py, color);
+/// Dibuja la vista 3D de `player` en `viewport`.
+fn render_scene(map: &Map, player: &Player, framebuffer: &mut Framebuffer, viewport: Viewport, palette: &Palette, options: &RenderOptions) -> SceneStats {
+    let mut stats = SceneStats::default();
+    #[cfg(feature = "ray-stats")]
+    let mut column_steps = Vec::with_capacity(viewport.width);
+    let half_fov = half_fov(player, viewport);
+
+    for col in 0..viewport.width {
+        // Calcular el ángulo del rayo para esta columna del viewport
+        let camera_x = 2.0 * (col as f64) / (viewport.width as f64) - 1.0;
+        let angle_offset = half_fov * camera_x;
+
+        // Lanzar el rayo y obtener la distancia a la pared
+        let hit = cast_ray(map, player, angle_offset, map.to_cells(options.fog.max_distance));
+        #[cfg(feature = "ray-stats")]
+        {
+            stats.steps.record(hit.steps);
+            column_steps.push(hit.steps);
+        }
+
+        // Con la pared casi encima de la cámara la proyección puede mostrar por un cuadro la
+        // cara opuesta o la niebla; la columna entera se dibuja con el color de la pared cercana
+        if hit.distance < NEAR_PLANE && !hit.fogged {
+            let color = wall_color(map, palette, &hit).light(options.light_tint, options.light_intensity).drawable();
+            framebuffer.fill_rect(viewport.x + col, viewport.y, 1, viewport.height, color);
+            stats.near_columns += 1;
+            continue;
+        }
+
+        // Calcular la franja de pared en la pantalla (limitada a la altura del viewport)
+        let (slice_start, slice_end) = wall_slice(hit.distance, viewport.height);
+        let start = viewport.y + slice_start;
+        let end = viewport.y + slice_end;
+
+        // Dibujar cielo o techo sobre la pared, según la celda del lado cercano.
+        // Se decide una sola vez por columna para que no haya cortes a mitad de columna.
+        let ceiling = if map.is_outdoor(hit.near_x as f64, hit.near_y as f64) {
+            palette.sky
+        } else {
+            palette.ceiling
+        };
+        let ceiling = ceiling.light(options.light_tint, options.light_intensity);
+        let horizon = viewport.y + viewport.height / 2;
+        for y in viewport.y..start {
+            // Cada fila del techo está a la misma distancia que la fila simétrica del suelo
+            let distance = viewport.height as f64 / (2.0 * (horizon - y) as f64);
+            framebuffer.point(viewport.x + col, y, options.fog.apply(ceiling, map.to_meters(distance)));
+        }
+
+        if hit.fogged {
+            // Un rayo que se perdió en la niebla se dibuja como una franja sólida del color de la niebla
+            for y in start..end {
+                framebuffer.point(viewport.x + col, y, options.fog.solid_color());
+            }
+        } else {
+            // Dibujar la pared en la pantalla, oscureciendo cerca de los bordes de cada cara
+            let color = wall_color(map, palette, &hit);
+            let color = color.light(options.light_tint, options.light_intensity);
+            let column_ao = ambient_occlusion(hit.wall_x.min(1.0 - hit.wall_x), options.ao_strength);
+            let slice_height = viewport.height as f64 / hit.distance;
+            let slice_top = horizon as f64 - slice_height / 2.0;
+            for y in start..end {
+                let v = (y as f64 - slice_top) / slice_height;
+                let ao = column_ao * ambient_occlusion(v.min(1.0 - v), options.ao_strength);
+                framebuffer.point(viewport.x + col, y, options.fog.apply(color.scale(ao), map.to_meters(hit.distance)));
+            }
+        }
+
+        // Dibujar el suelo bajo la pared: cada fila corresponde a una distancia fija desde
+        // el jugador, y el material se toma de la celda que cae en esa distancia (del otro lado
+        // del portal si el rayo cruzó uno antes)
+        for y in end.max(horizon + 1)..viewport.y + viewport.height {
+            let distance = viewport.height as f64 / (2.0 * (y - horizon) as f64);
+            let (floor_x, floor_y) = hit.point_at(distance);
+            let material = map.floor_at(floor_x, floor_y);
+            let color = palette.floor[material.index()].light(options.light_tint, options.light_intensity);
+            let mut color = options.fog.apply(color, map.to_meters(distance));
+
+            // Reflejo: la fila de la pared simétrica respecto de su base, ya dibujada en esta
+            // columna. Si cae fuera de la franja de pared o la pared se perdió en la niebla, no
+            // hay reflejo.
+            let reflectivity = if options.reflections { material.reflectivity() } else { 0.0 };
+            if reflectivity > 0.0 && !hit.fogged {
+                if let Some(mirrored) = (2 * end).checked_sub(y + 1).filter(|&row| row >= start && row < end) {
+                    color = color.lerp(framebuffer.pixel(viewport.x + col, mirrored), reflectivity);
                 }
             }
+            framebuffer.point(viewport.x + col, y, color);
         }
     }
+
+    // Franja de costo: las primeras filas de cada columna, teñidas según los pasos de su rayo
+    #[cfg(feature = "ray-stats")]
+    for (col, &steps) in column_steps.iter().enumerate().filter(|_| options.cost_overlay) {
+        for y in viewport.y..viewport.y + raystats::OVERLAY_ROWS.min(viewport.height) {
+            let color = raystats::tint(framebuffer.pixel(viewport.x + col, y), steps);
+            framebuffer.point(viewport.x + col, y, color);
+        }
+    }
+    stats
 }
 
-fn draw_minimap(map: &Map, player: &Player, framebuffer: &mut Framebuffer) {
-    let minimap_scale = 4;
-    let minimap_width = map.width * minimap_scale;
-    let minimap_height = map.height * minimap_scale;
 
-    // Calcula las coordenadas de inicio para la parte inferior derecha
-    let start_x = framebuffer.width - minimap_width - 10; // 10 píxeles de margen desde el borde
-    let start_y = framebuffer.height - minimap_height - 10;
+
+const NEAR_PLANE: f64 = 0.05; // Distancia mínima (en celdas) a la que se proyecta una pared
+
+const ORBIT_RADIUS: f64 = 3.0;      // Distancia inicial (en celdas) de la cámara orbital al jugador
+const BILLBOARD_WIDTH: f64 = 0.4;   // Tamaño del jugador visto desde la cámara orbital (en celdas)
+const BILLBOARD_HEIGHT: f64 = 0.7;
+
+/// Dibuja a `target` visto desde `camera` como un rectángulo de color `color` apoyado en el
+/// suelo. Las columnas en las que una pared queda más cerca que el jugador no se dibujan.
+fn draw_billboard(map: &Map, camera: &Player, target: &Player, framebuffer: &mut Framebuffer, viewport: Viewport, color: Color, options: &RenderOptions) {
+    let (dx, dy) = (target.x - camera.x, target.y - camera.y);
+    let distance = dx.hypot(dy);
+    let half_fov = half_fov(camera, viewport);
+    // Ángulo del jugador respecto del centro de la vista, entre -π y π
+    let angle = (dy.atan2(dx) - camera.direction + std::f64::consts::PI).rem_euclid(std::f64::consts::TAU) - std::f64::consts::PI;
+    if distance < NEAR_PLANE || angle.abs() > std::f64::consts::FRAC_PI_2 {
+        return;
+    }
+
+    // Misma proyección que las paredes: el ángulo da la columna y la distancia el tamaño
+    let center = (angle / half_fov + 1.0) / 2.0 * viewport.width as f64;
+    let size = viewport.height as f64 / distance;
+    let half_width = size * BILLBOARD_WIDTH / 2.0;
+    let first = (center - half_width).max(0.0) as usize;
+    let last = ((center + half_width).max(0.0) as usize).min(viewport.width);
+    let bottom = ((viewport.height as f64 + size) / 2.0).min(viewport.height as f64) as usize;
+    let top = ((viewport.height as f64 + size) / 2.0 - size * BILLBOARD_HEIGHT).max(0.0) as usize;
+    let color = options.fog.apply(color.light(options.light_tint, options.light_intensity), map.to_meters(distance)).drawable();
+
+    for col in first..last {
+        let camera_x = 2.0 * (col as f64) / (viewport.width as f64) - 1.0;
+        if !cast_ray(map, camera, half_fov * camera_x, distance).fogged {
+            continue; // Una pared tapa al jugador en esta columna
+        }
+        for y in top..bottom {
+            framebuffer.point(viewport.x + col, viewport.y + y, color);
+        }
+    }
+}
+
+const MINIMAP_SCALE: usize = 4; // Píxeles por celda en el minimapa (a escala 1x del HUD)
+const HUD_MARGIN: usize = 10;    // Margen entre el HUD y los bordes de la pantalla (a escala 1x)
+const MAX_HUD_SCALE: usize = 4;
+
+const AUTOMAP_ZOOM: f64 = 16.0;       // Píxeles por celda al abrir el automapa a pantalla completa
+const AUTOMAP_ZOOM_RANGE: (f64, f64) = (4.0, 64.0);
+const AUTOMAP_ZOOM_RATE: f64 = 2.0;   // Factor de zoom por segundo con +/- pulsado
+const AUTOMAP_PAN_SPEED: f64 = 400.0; // Píxeles por segundo al desplazar el automapa con las flechas
+const AUTOMAP_ARROW: f64 = 0.6;       // Largo de la flecha del jugador (en celdas)
+const AUTOMAP_MARKER: f64 = 0.3;      // Radio de los marcadores (en celdas)
+
+/// Estilo del minimapa.
+#[derive(Clone, Copy, PartialEq)]
+enum MinimapStyle {
+    Cells, // Celdas rellenas
+    Lines, // Solo los bordes entre paredes y espacio vacío, como líneas
+}
+
+/// Dibuja el mapa completo visto desde arriba, estirado para llenar el framebuffer, con los
+/// colores de paredes y suelo del minimapa de la paleta. Cada celda mide al menos `CELL_SIZE`
+/// píxeles; lo que no cabe queda recortado.
+fn draw_2d_map(map: &Map, framebuffer: &mut Framebuffer, palette: &Palette) {
+    if map.width == 0 || map.height == 0 {
+        return;
+    }
+    let cell_width = (framebuffer.width / map.width).max(CELL_SIZE);
+    let cell_height = (framebuffer.height / map.height).max(CELL_SIZE);
 
     for y in 0..map.height {
-        for x in 0..map.width {
-            let color = if map.is_wall(x as f64, y as f64) {
-                0xFFFFFF
-            } else {
-                0x000000
-            };
+        for (x, &value) in map.row(y).iter().enumerate() {
+            let color = if value > 0 { palette.minimap_wall } else { palette.minimap_floor };
+            framebuffer.fill_rect(x * cell_width, y * cell_height, cell_width, cell_height, color);
+        }
+    }
+}
+
+/// Dibuja el mapa en una capa aparte. Como el mapa no cambia durante el juego,
+/// la capa se genera una sola vez (o al cambiar de paleta, escala o estilo) y luego solo se copia.
+fn render_minimap_layer(map: &Map, segments: &[Segment], palette: &Palette, hud_scale: usize, style: MinimapStyle) -> Framebuffer {
+    let cell = MINIMAP_SCALE * hud_scale;
+    // Un mapa vacío deja una capa de 1x1 píxel sin dibujar
+    let mut layer = Framebuffer::new((map.width * cell).max(1), (map.height * cell).max(1)).unwrap();
+
+    if style == MinimapStyle::Lines {
+        // Los bordes del lado derecho e inferior del mapa caen justo fuera de la capa
+        let clamp = |value: usize, limit: usize| (value * cell).min(limit - 1) as isize;
+        for segment in segments {
+            layer.line(
+                clamp(segment.x0, layer.width),
+                clamp(segment.y0, layer.height),
+                clamp(segment.x1, layer.width),
+                clamp(segment.y1, layer.height),
+                palette.minimap_wall,
+            );
+        }
+        return layer;
+    }
+
+    // La capa mide justo `cell` píxeles por celda, así que el mapa estirado cae en su sitio
+    draw_2d_map(map, &mut layer, palette);
+    layer
+}
+
+/// Orientación del minimapa.
+#[derive(Clone, Copy, PartialEq)]
+enum MinimapMode {
+    NorthUp,  // Alineado con el mapa
+    PlayerUp, // Gira con el jugador para que su dirección siempre apunte hacia arriba
+}
+
+fn draw_minimap(layer: &Framebuffer, players: &[&Player], markers: &Markers, framebuffer: &mut Framebuffer, palette: &Palette, hud_scale: usize, mode: MinimapMode) {
+    let cell = MINIMAP_SCALE * hud_scale;
+
+    // Calcula las coordenadas de inicio para la parte inferior derecha
+    // (si la ventana es más pequeña que el minimapa, se pega a la esquina superior izquierda)
+    let start_x = framebuffer.width.saturating_sub(layer.width + HUD_MARGIN * hud_scale);
+    let start_y = framebuffer.height.saturating_sub(layer.height + HUD_MARGIN * hud_scale);
+
+    if mode == MinimapMode::PlayerUp {
+        draw_minimap_rotated(layer, players, markers, framebuffer, palette, cell, (start_x, start_y));
+        return;
+    }
+
+    // Copiar las celdas ya dibujadas
+    framebuffer.blit(layer, start_x, start_y);
+
+    for marker in markers.iter() {
+        let x = start_x as f64 + (marker.x as f64 + 0.5) * cell as f64;
+        let y = start_y as f64 + (marker.y as f64 + 0.5) * cell as f64;
+        draw_marker(framebuffer, x as isize, y as isize, (cell / 2) as isize, palette.markers[marker.kind]);
+    }
+
+    // Dibujar la posición de cada jugador en el minimapa
+    for (player, color) in players.iter().zip(palette.players) {
+        let player_x = (player.x * cell as f64) as usize;
+        let player_y = (player.y * cell as f64) as usize;
+        for py in 0..cell {
+            for px in 0..cell {
+                framebuffer.point(start_x + player_x + px, start_y + player_y + py, color);
+            }
+        }
+    }
+}
+
+/// Minimapa circular centrado en el primer jugador y girado para que mire hacia arriba.
+/// Cada píxel del círculo se transforma de vuelta al mapa y se muestrea en la capa ya dibujada;
+/// lo que cae fuera del mapa no se dibuja.
+fn draw_minimap_rotated(layer: &Framebuffer, players: &[&Player], markers: &Markers, framebuffer: &mut Framebuffer, palette: &Palette, cell: usize, origin: (usize, usize)) {
+    let center = players[0];
+    let radius = (layer.width.min(layer.height) / 2) as isize;
+    let center_x = (origin.0 + layer.width / 2) as isize;
+    let center_y = (origin.1 + layer.height / 2) as isize;
+
+    // Girar un desplazamiento en pantalla por este ángulo lo lleva al mapa
+    let (sin, cos) = (center.direction + std::f64::consts::FRAC_PI_2).sin_cos();
+    let center_px = center.x * cell as f64;
+    let center_py = center.y * cell as f64;
+
+    for sy in -radius..radius {
+        for sx in -radius..radius {
+            if sx * sx + sy * sy > radius * radius {
+                continue;
+            }
+
+            let map_px = center_px + sx as f64 * cos - sy as f64 * sin;
+            let map_py = center_py + sx as f64 * sin + sy as f64 * cos;
+            if map_px < 0.0 || map_py < 0.0 || map_px >= layer.width as f64 || map_py >= layer.height as f64 {
+                continue;
+            }
+
+            let color = Color::from_u32(layer.buffer[map_py as usize * layer.width + map_px as usize]);
+            framebuffer.point((center_x + sx) as usize, (center_y + sy) as usize, color);
+        }
+    }
+
+    // Marcadores en su posición girada, si caen dentro del círculo
+    for marker in markers.iter() {
+        let dx = (marker.x as f64 + 0.5 - center.x) * cell as f64;
+        let dy = (marker.y as f64 + 0.5 - center.y) * cell as f64;
+        let sx = (dx * cos + dy * sin) as isize;
+        let sy = (dy * cos - dx * sin) as isize;
+        if sx * sx + sy * sy <= radius * radius {
+            draw_marker(framebuffer, center_x + sx, center_y + sy, (cell / 2) as isize, palette.markers[marker.kind]);
+        }
+    }
 
-            for py in 0..minimap_scale {
-                for px in 0..minimap_scale {
-                    framebuffer.point(start_x + x * minimap_scale + px, start_y + y * minimap_scale + py, color);
+    // Dibujar a cada jugador en su posición girada, si cae dentro del círculo
+    let half = (cell / 2) as isize;
+    for (player, color) in players.iter().zip(palette.players) {
+        let dx = (player.x - center.x) * cell as f64;
+        let dy = (player.y - center.y) * cell as f64;
+        let sx = (dx * cos + dy * sin) as isize;
+        let sy = (dy * cos - dx * sin) as isize;
+        if sx * sx + sy * sy > radius * radius {
+            continue;
+        }
+
+        for py in 0..cell as isize {
+            for px in 0..cell as isize {
+                let x = center_x + sx - half + px;
+                let y = center_y + sy - half + py;
+                if x >= 0 && y >= 0 {
+                    framebuffer.point(x as usize, y as usize, color);
                 }
             }
         }
     }
+}
+
+/// Automapa a pantalla completa: los bordes de las paredes como líneas, centrados en el primer
+/// jugador, con una flecha por jugador. `zoom` son píxeles por celda.
+fn draw_automap(segments: &[Segment], players: &[&Player], markers: &Markers, framebuffer: &mut Framebuffer, palette: &Palette, view: &AutomapView) {
+    framebuffer.buffer.fill(palette.minimap_floor.drawable().to_u32());
+
+    let screen = (framebuffer.width as f64, framebuffer.height as f64);
+    let to_screen = |x: f64, y: f64| {
+        let (x, y) = view.to_screen(x, y, screen);
+        (x as isize, y as isize)
+    };
+
+    for segment in segments {
+        let (x0, y0) = to_screen(segment.x0 as f64, segment.y0 as f64);
+        let (x1, y1) = to_screen(segment.x1 as f64, segment.y1 as f64);
+        // Los segmentos son horizontales o verticales: si caen fuera de la pantalla se saltan
+        if x1 < 0 || y1 < 0 || x0 >= framebuffer.width as isize || y0 >= framebuffer.height as isize {
+            continue;
+        }
+        framebuffer.line(x0, y0, x1, y1, palette.minimap_wall);
+    }
+
+    for marker in markers.iter() {
+        let (x, y) = to_screen(marker.x as f64 + 0.5, marker.y as f64 + 0.5);
+        let size = ((view.zoom * AUTOMAP_MARKER) as isize).max(2);
+        draw_marker(framebuffer, x, y, size, palette.markers[marker.kind]);
+    }
+
+    // Flecha de cada jugador: una línea en su dirección y dos alas hacia atrás
+    for (player, color) in players.iter().zip(palette.players) {
+        let (dir_x, dir_y) = (player.direction.cos(), player.direction.sin());
+        let tip = to_screen(player.x + dir_x * AUTOMAP_ARROW / 2.0, player.y + dir_y * AUTOMAP_ARROW / 2.0);
+        let tail = to_screen(player.x - dir_x * AUTOMAP_ARROW / 2.0, player.y - dir_y * AUTOMAP_ARROW / 2.0);
+        framebuffer.line(tail.0, tail.1, tip.0, tip.1, color);
+        for wing in [2.5_f64, -2.5] {
+            let angle = player.direction + wing;
+            let end = to_screen(
+                player.x + dir_x * AUTOMAP_ARROW / 2.0 + angle.cos() * AUTOMAP_ARROW / 2.0,
+                player.y + dir_y * AUTOMAP_ARROW / 2.0 + angle.sin() * AUTOMAP_ARROW / 2.0,
+            );
+            framebuffer.line(tip.0, tip.1, end.0, end.1, color);
+        }
+    }
+}
 
-    // Dibujar la posición del jugador en el minimapa
-    let player_x = (player.x * minimap_scale as f64) as usize;
-    let player_y = (player.y * minimap_scale as f64) as usize;
-    for py in 0..minimap_scale {
-        for px in 0..minimap_scale {
-            framebuffer.point(start_x + player_x + px, start_y + player_y + py, 0xFF0000); // Rojo para la posición del jugador
+/// Dibuja un marcador como un rombo relleno de radio `size` centrado en (`x`, `y`), recortando
+/// lo que quede fuera del framebuffer.
+fn draw_marker(framebuffer: &mut Framebuffer, x: isize, y: isize, size: isize, color: Color) {
+    for dy in -size..=size {
+        for dx in -(size - dy.abs())..=(size - dy.abs()) {
+            if x + dx >= 0 && y + dy >= 0 {
+                framebuffer.point((x + dx) as usize, (y + dy) as usize, color);
+            }
         }
     }
 }
 
-const FONT: [[u8; 5]; 13] = [
-    [0b01110, 0b10001, 0b10001, 0b10001, 0b01110], // 0
-    [0b00100, 0b01100, 0b00100, 0b00100, 0b01110], // 1
-    [0b01110, 0b10001, 0b00110, 0b01000, 0b11111], // 2
-    [0b01110, 0b10001, 0b00110, 0b10001, 0b01110], // 3
-    [0b00010, 0b00110, 0b01010, 0b11111, 0b00010], // 4
-    [0b11111, 0b10000, 0b11110, 0b00001, 0b11110], // 5
-    [0b01110, 0b10000, 0b11110, 0b10001, 0b01110], // 6
-    [0b11111, 0b00010, 0b00100, 0b01000, 0b10000], // 7
-    [0b01110, 0b10001, 0b01110, 0b10001, 0b01110], // 8
-    [0b01110, 0b10001, 0b01111, 0b00001, 0b01110], // 9
-    [0b11111, 0b10000, 0b11110, 0b10000, 0b10000], // F
-    [0b11110, 0b10001, 0b11110, 0b10000, 0b10000], // P
-    [0b01111, 0b10000, 0b01110, 0b00001, 0b11110], // S
-];
+fn is_any_key_down(window: &Window, keys: &[Key]) -> bool {
+    keys.iter().any(|&key| window.is_key_down(key))
+}
+
+/// Celdas que avanza `player` en `dt` segundos. El material del suelo bajo el jugador puede
+/// frenarlo (p. ej. el agua).
+fn move_distance(map: &Map, player: &Player, dt: f64) -> f64 {
+    map.to_cells(MOVE_SPEED * dt) * map.floor_at(player.x, player.y).speed_factor()
+}
+
+/// Mueve al jugador según las teclas pulsadas durante `dt` segundos de simulación. La velocidad
+/// está en metros por segundo y se pasa a celdas con la escala del mapa.
+fn handle_movement(window: &Window, controls: &Controls, player: &mut Player, map: &Map, dt: f64) {
+    let speed = move_distance(map, player, dt);
+    let turn = TURN_SPEED * dt;
+    if is_any_key_down(window, controls.forward) {
+        player.move_forward(speed, map); // Reduce la velocidad de movimiento
+    }
+    if is_any_key_down(window, controls.backward) {
+        player.move_backward(speed, map); // Reduce la velocidad de movimiento
+    }
+    if is_any_key_down(window, controls.left) {
+        player.turn_left(turn); // Reduce la velocidad de rotación
+    }
+    if is_any_key_down(window, controls.right) {
+        player.turn_right(turn); // Reduce la velocidad de rotación
+    }
+}
+
+/// Teclas de movimiento pulsadas en este cuadro, como bits `INPUT_*`.
+fn input_bits(window: &Window, controls: &Controls) -> u8 {
+    let mut bits = 0;
+    for (keys, bit) in [
+        (controls.forward, INPUT_FORWARD),
+        (controls.backward, INPUT_BACKWARD),
+        (controls.left, INPUT_LEFT),
+        (controls.right, INPUT_RIGHT),
+    ] {
+        if is_any_key_down(window, keys) {
+            bits |= bit;
+        }
+    }
+    bits
+}
+
+const INPUT_KEY_SIZE: usize = 8; // Lado de cada tecla del indicador de entrada (a escala 1x del HUD)
+const INPUT_KEY_GAP: usize = 2;
+
+/// Dibuja abajo al centro el tiempo de la partida, a su derecha la diferencia del último tiempo
+/// parcial con el récord (con el color de cómo va) y, encima, las teclas de movimiento pulsadas
+/// (bits `INPUT_*`) dispuestas como flechas: avanzar arriba; girar y retroceder debajo.
+fn draw_speedrun(framebuffer: &mut Framebuffer, timer: &SpeedrunTimer, best: &PersonalBest, input: u8, palette: &Palette, hud_scale: usize) {
+    let margin = HUD_MARGIN * hud_scale;
+    let text = format_run_time(timer.seconds());
+    let (text_width, text_height) = measure_text(&text, hud_scale);
+    let text_x = framebuffer.width.saturating_sub(text_width) / 2;
+    let text_y = framebuffer.height.saturating_sub(margin + text_height);
+    draw_text(framebuffer, text_x, text_y, &text, palette.hud_text, hud_scale);
+
+    let splits = timer.splits();
+    if let Some(last) = splits.len().checked_sub(1) {
+        if let Some(delta) = best.delta(splits, last) {
+            let color = best.status(splits, last).map_or(palette.hud_text, |status| palette.splits[status.index()]);
+            draw_text(framebuffer, text_x + text_width + margin, text_y, &format_delta(delta), color, hud_scale);
+        }
+    }
+
+    let (size, gap) = (INPUT_KEY_SIZE * hud_scale, INPUT_KEY_GAP * hud_scale);
+    let left = framebuffer.width.saturating_sub(3 * size + 2 * gap) / 2;
+    let bottom_row = text_y.saturating_sub(gap + size);
+    let top_row = bottom_row.saturating_sub(gap + size);
+    for (bit, column, y) in [
+        (INPUT_FORWARD, 1, top_row),
+        (INPUT_LEFT, 0, bottom_row),
+        (INPUT_BACKWARD, 1, bottom_row),
+        (INPUT_RIGHT, 2, bottom_row),
+    ] {
+        let color = if input & bit != 0 { palette.hud_text } else { palette.minimap_floor.drawable() };
+        framebuffer.fill_rect(left + column * (size + gap), y, size, size, color);
+    }
+}
+
+const VOLUME_RATE: f64 = 0.5;     // Cambio de volumen por segundo con la tecla pulsada
+const VOLUME_BAR_TIME: f64 = 1.0; // Segundos que se muestra la barra de volumen tras un cambio
+const VOLUME_BAR_WIDTH: usize = 100; // Tamaño de la barra de volumen (a escala 1x del HUD)
+const VOLUME_BAR_HEIGHT: usize = 6;
+
+const MIRROR_WIDTH: usize = 160; // Resolución del retrovisor; se dibuja ampliado según la escala del HUD
+const MIRROR_HEIGHT: usize = 60;
+
+/// Dibuja en `mirror` la vista hacia atrás del jugador, invertida de izquierda a derecha como
+/// en un espejo.
+fn render_mirror(map: &Map, player: &Player, mirror: &mut Framebuffer, palette: &Palette, options: &RenderOptions) {
+    let mut rear = Player::new(player.x, player.y, player.direction + std::f64::consts::PI);
+    rear.fov = player.fov;
+
+    mirror.buffer.fill(palette.background.light(options.light_tint, options.light_intensity).to_u32());
+    render_scene(map, &rear, mirror, Viewport::new(0, 0, mirror.width, mirror.height), palette, options);
+    for row in mirror.buffer.chunks_mut(mirror.width) {
+        row.reverse();
+    }
+}
+
+/// Dibuja el retrovisor arriba al centro, con un borde de 1 píxel (a escala del HUD). Si la
+/// ventana es demasiado pequeña para que quepa, no se dibuja.
+fn draw_mirror(framebuffer: &mut Framebuffer, mirror: &Framebuffer, palette: &Palette, hud_scale: usize) {
+    let (width, height) = (mirror.width * hud_scale, mirror.height * hud_scale);
+    let x = framebuffer.width.saturating_sub(width) / 2;
+    let y = HUD_MARGIN * hud_scale;
+    if x < hud_scale || x + width + hud_scale > framebuffer.width || y + height + hud_scale > framebuffer.height {
+        return;
+    }
+
+    framebuffer.fill_rect(x - hud_scale, y - hud_scale, width + 2 * hud_scale, height + 2 * hud_scale, palette.hud_text);
+    framebuffer.upscale_into(mirror, mirror.width, mirror.height, Viewport::new(x, y, width, height));
+}
+
+/// Volumen después de mantener pulsada la tecla de subir (`direction` 1.0) o bajar (-1.0)
+/// durante `dt` segundos, siempre entre 0.0 y 1.0.
+fn ramp_volume(volume: f64, direction: f64, dt: f64) -> f64 {
+    (volume + direction * VOLUME_RATE * dt).clamp(0.0, 1.0)
+}
 
-fn draw_digit(framebuffer: &mut Framebuffer, x: usize, y: usize, index: usize, color: u32) {
-    if index >= FONT.len() { return; }
+/// Dibuja la barra de volumen con el porcentaje encima, en la esquina inferior izquierda.
+fn draw_volume_bar(framebuffer: &mut Framebuffer, volume: f64, palette: &Palette, hud_scale: usize) {
+    let margin = HUD_MARGIN * hud_scale;
+    let (bar_width, bar_height) = (VOLUME_BAR_WIDTH * hud_scale, VOLUME_BAR_HEIGHT * hud_scale);
+    let bar_y = framebuffer.height.saturating_sub(margin + bar_height);
+    framebuffer.fill_rect(margin, bar_y, bar_width, bar_height, palette.minimap_floor.drawable());
+    let filled = (bar_width as f64 * volume).round() as usize;
+    framebuffer.fill_rect(margin, bar_y, filled, bar_height, palette.hud_text);
 
-    for (row, byte) in FONT[index].iter().enumerate() {
-        for col in 0..5 {
-            if byte & (1 << (4 - col)) != 0 {
-                framebuffer.point(x + col, y + row, color);
+    let text = format!("{}", (volume * 100.0).round() as u32);
+    let (_, text_height) = measure_text(&text, hud_scale);
+    draw_text(framebuffer, margin, bar_y.saturating_sub(hud_scale * 2 + text_height), &text, palette.hud_text, hud_scale);
+}
+
+const MAX_TIME_SCALE: f64 = 4.0;
+
+const MOVE_SPEED: f64 = 3.0; // Velocidad del jugador en metros por segundo
+const TURN_SPEED: f64 = 1.8; // Velocidad de giro en radianes por segundo
+
+const MIN_FOV: f64 = 30.0; // Límites del campo de visión (en grados) para la orden setfov
+const MAX_FOV: f64 = 150.0;
+
+/// Posición inicial del jugador: el centro de la celda `P` del mapa, o (12, 12) si no la tiene.
+fn spawn_point(map: &Map) -> (f64, f64) {
+    map.spawn
+        .map_or((12.0, 12.0), |(x, y)| (x as f64 + 0.5, y as f64 + 0.5))
+}
+
+/// Posición inicial del segundo jugador: el centro de la celda libre más cercana a la del
+/// primero, contando pasos por celdas libres, para que no aparezcan uno dentro del otro ni al
+/// otro lado de una pared. Si no hay otra celda libre alcanzable, comparte la del primero.
+fn second_spawn_point(map: &Map) -> (f64, f64) {
+    let (spawn_x, spawn_y) = spawn_point(map);
+    let start = (spawn_x as isize, spawn_y as isize);
+    let is_open = |(x, y): (isize, isize)| {
+        x >= 0 && y >= 0 && (x as usize) < map.width && (y as usize) < map.height && map.cell_at(x, y) == 0
+    };
+
+    let mut visited = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(cell) = queue.pop_front() {
+        if cell != start && map.thin_wall(cell.0, cell.1).is_none() {
+            return (cell.0 as f64 + 0.5, cell.1 as f64 + 0.5);
+        }
+        for (dx, dy) in [(0, -1), (1, 0), (0, 1), (-1, 0)] {
+            let next = (cell.0 + dx, cell.1 + dy);
+            if is_open(next) && visited.insert(next) {
+                queue.push_back(next);
             }
         }
     }
+    (spawn_x, spawn_y)
 }
 
-fn draw_text(framebuffer: &mut Framebuffer, x: usize, y: usize, text: &str, color: u32) {
-    let mut x_offset = 0;
+/// Devuelve el valor que sigue a una opción de la línea de comandos (p. ej. `--map ruta`).
+fn arg_value<'a>(args: &'a [String], option: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == option)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
 
-    for ch in text.chars() {
-        let index = match ch {
-            '0'..='9' => ch as usize - '0' as usize,
-            'F' => 10,
-            'P' => 11,
-            'S' => 12,
-            _ => continue,
-        };
+/// Valida un archivo de mapa sin abrir la ventana e imprime un reporte.
+///
+/// # Returns
+/// * `i32` - Código de salida: 0 si el mapa se puede jugar (aunque tenga avisos), 1 si tiene
+///   errores o no se pudo leer.
+fn check_map(path: &str) -> i32 {
+    match load_map(path) {
+        Ok((map, issues)) => {
+            for issue in &issues {
+                println!("{}:{}", path, issue);
+            }
+            let errors = issues.iter().filter(|issue| issue.error).count();
+            if errors > 0 {
+                println!("{}: {} error(s), {} warning(s)", path, errors, issues.len() - errors);
+                1
+            } else if issues.is_empty() {
+                println!("{}: OK ({}x{})", path, map.width, map.height);
+                0
+            } else {
+                println!("{}: OK ({}x{}) with {} warning(s)", path, map.width, map.height, issues.len());
+                0
+            }
+        }
+        Err(err) => {
+            eprintln!("{}: cannot read map: {}", path, err);
+            1
+        }
+    }
+}
+
+const MUSIC_PATH: &str = "src/Zack Hemsey - ＂Mind Heist＂.wav";
+const MUSIC_DIR: &str = "music"; // Si existe, sus pistas forman la lista de reproducción
+const TOAST_TIME: f64 = 3.0; // Segundos que se muestra un aviso (pista que empieza, grabación guardada)
+const MAX_TOASTS: usize = 4; // Avisos en cola como máximo; si llegan más se descarta el más viejo en espera
+const AMBIENCE_VOLUME: f32 = 0.05;    // Volumen del sonido ambiental, por debajo de la música
+const AMBIENCE_FADE_IN: Duration = Duration::from_secs(2);
+
+/// Avisos en pantalla, mostrados de uno en uno durante TOAST_TIME en el orden en que llegan,
+/// para que uno nuevo no tape al que todavía no se leyó.
+#[derive(Default)]
+struct Toasts {
+    queue: VecDeque<String>,
+    timer: f64, // Tiempo que le queda al primero de la cola
+}
+
+impl Toasts {
+    fn push(&mut self, text: String) {
+        if self.queue.is_empty() {
+            self.timer = TOAST_TIME;
+        } else if self.queue.len() == MAX_TOASTS {
+            // El que se está mostrando se deja terminar
+            self.queue.remove(1);
+        }
+        self.queue.push_back(text);
+    }
+
+    fn update(&mut self, dt: f64) {
+        if self.queue.is_empty() {
+            return;
+        }
+        self.timer -= dt;
+        if self.timer <= 0.0 {
+            self.queue.pop_front();
+            self.timer = TOAST_TIME;
+        }
+    }
 
-        draw_digit(framebuffer, x + x_offset, y, index, color);
-        x_offset += 6; // Espacio entre caracteres
+    fn current(&self) -> Option<&str> {
+        self.queue.front().map(String::as_str)
     }
 }
 
+/// Reproduce un sonido ambiental en bucle en su propio sink, subiendo desde silencio al empezar.
+/// El archivo se decodifica una sola vez y se repite desde memoria, sin cortes entre vueltas.
+fn play_ambience(stream_handle: &OutputStreamHandle, path: &str) -> Result<Sink, String> {
+    let file = File::open(path).map_err(|err| err.to_string())?;
+    let source = Decoder::new(BufReader::new(file)).map_err(|err| err.to_string())?;
+    let sink = Sink::try_new(stream_handle).map_err(|err| err.to_string())?;
+    sink.set_volume(AMBIENCE_VOLUME);
+    sink.append(source.repeat_infinite().fade_in(AMBIENCE_FADE_IN));
+    Ok(sink)
+}
+
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    // Modo de validación de mapas (--check-map ruta): no abre la ventana
+    if let Some(path) = arg_value(&args, "--check-map") {
+        std::process::exit(check_map(path));
+    }
+
+    // Si el juego entra en pánico se escribe un informe con el último estado conocido
+    let diagnostics = Arc::new(Mutex::new(DiagnosticsSnapshot::default()));
+    install_panic_hook(Arc::clone(&diagnostics));
+
     // Inicializa el sistema de audio
     let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-    let sink = Sink::try_new(&stream_handle).unwrap();
 
-    // Cargar el archivo de música
-    let file = BufReader::new(File::open("src/Zack Hemsey - ＂Mind Heist＂.wav").unwrap());
+    // Lista de reproducción: las pistas de --music-dir o de music/ si existe, o la pista de
+    // siempre. Se decodifican en segundo plano y empiezan a sonar cuando están listas.
+    let mut playlist = match arg_value(&args, "--music-dir") {
+        Some(dir) => Playlist::from_dir(Path::new(dir)).unwrap_or_else(|err| {
+            eprintln!("warning: {}: {}", dir, err);
+            Playlist::single(Path::new(MUSIC_PATH))
+        }),
+        None => Playlist::from_dir(Path::new(MUSIC_DIR)).unwrap_or_else(|_| Playlist::single(Path::new(MUSIC_PATH))),
+    };
+    // --shuffle SEMILLA mezcla la lista; sin una semilla válida se usa la hora
+    if args.iter().any(|arg| arg == "--shuffle") {
+        let seed = arg_value(&args, "--shuffle").and_then(|value| value.parse::<u64>().ok()).unwrap_or_else(|| {
+            SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64)
+        });
+        playlist.shuffle(seed);
+    }
 
-    // Decodificar el archivo de música
-    let source = Decoder::new(file).unwrap();
+    // Establecer el volumen inicial
+    let mut volume: f64 = 0.1;
+    let mut music = MusicPlayer::new(stream_handle.clone(), playlist, volume);
+    let mut volume_bar_timer = 0.0; // Tiempo restante con la barra de volumen en pantalla
+    let mut toasts = Toasts::default();
 
-    // Hacer que la música se repita indefinidamente
-    sink.append(source.repeat_infinite());
+    // Sonido ambiental en bucle (--ambience ruta); el sink debe seguir vivo para que suene
+    let _ambience = arg_value(&args, "--ambience").and_then(|path| {
+        play_ambience(&stream_handle, path)
+            .map_err(|err| eprintln!("warning: {}: {}", path, err))
+            .ok()
+    });
 
-    // Establecer el volumen inicial
-    let mut volume = 0.1;
-    sink.set_volume(volume);
+    // Inicialización del juego: el mapa puede venir de un archivo (--map ruta)
+    let mut map = match arg_value(&args, "--map") {
+        Some(path) => {
+            let (map, issues) = load_map(path).unwrap_or_else(|err| {
+                eprintln!("error: {}: cannot read map: {}", path, err);
+                std::process::exit(1);
+            });
+            for issue in &issues {
+                eprintln!("{}:{}", path, issue);
+            }
+            if has_errors(&issues) {
+                eprintln!("error: {}: map cannot be played", path);
+                std::process::exit(1);
+            }
+            map
+        }
+        None => initialize_map(),
+    };
+    let (spawn_x, spawn_y) = spawn_point(&map);
+    let mut player = Player::new(spawn_x, spawn_y, 0.0);
+
+    // Modo de dos jugadores con pantalla dividida (--split)
+    let split_screen = args.iter().any(|arg| arg == "--split");
+    let (spawn2_x, spawn2_y) = second_spawn_point(&map);
+    let mut player2 = Player::new(spawn2_x, spawn2_y, 0.0);
 
-    // Comienza a reproducir la música en segundo plano
-    sink.play();
+    // Resolución interna adaptativa para mantener los 60 FPS (--adaptive)
+    let adaptive_resolution = args.iter().any(|arg| arg == "--adaptive");
+    let mut resolution = ResolutionController::new();
 
-    // Inicialización del juego
-    let map = initialize_map();
-    let mut player = Player::new(12.0, 12.0, 0.0);
+    // Esquema de colores (--palette nombre); F2 cambia al siguiente
+    let mut palette_index = match arg_value(&args, "--palette") {
+        Some(name) => find_palette(name).unwrap_or_else(|| {
+            eprintln!("warning: unknown palette '{}', using default", name);
+            0
+        }),
+        None => 0,
+    };
+
+    // Idioma de los textos del HUD y la consola (--lang en, es o la ruta de un archivo de textos)
+    let language = arg_value(&args, "--lang").unwrap_or("en");
+    let strings = Strings::load(language).unwrap_or_else(|err| {
+        eprintln!("warning: {}: {}, using English", language, err);
+        Strings::load("en").unwrap()
+    });
+
+    // Opciones del render 3D: oscurecimiento en bordes de las paredes (--ao intensidad,
+    // 0.0 lo desactiva) y luz ambiental del nivel (--tint RRGGBB y --ambient intensidad)
+    let render_options = RenderOptions {
+        ao_strength: arg_value(&args, "--ao")
+            .and_then(|value| value.parse::<f64>().ok())
+            .unwrap_or(0.3)
+            .clamp(0.0, 1.0),
+        light_tint: arg_value(&args, "--tint")
+            .and_then(|value| u32::from_str_radix(value.trim_start_matches('#'), 16).ok())
+            .map_or(Color::WHITE, Color::from_u32),
+        light_intensity: arg_value(&args, "--ambient")
+            .and_then(|value| value.parse::<f64>().ok())
+            .unwrap_or(1.0)
+            .max(0.0),
+        // Niebla exp2 (--fog densidad por metro y --fog-color RRGGBB); --fog-distance funde todo
+        // con la niebla a esa distancia en metros y los rayos dejan de recorrer el mapa más allá
+        fog: Fog {
+            density: arg_value(&args, "--fog")
+                .and_then(|value| value.parse::<f64>().ok())
+                .unwrap_or(0.0)
+                .max(0.0),
+            color: arg_value(&args, "--fog-color")
+                .and_then(|value| u32::from_str_radix(value.trim_start_matches('#'), 16).ok())
+                .map_or(Color::BLACK, Color::from_u32),
+            max_distance: arg_value(&args, "--fog-distance")
+                .and_then(|value| value.parse::<f64>().ok())
+                .filter(|distance| *distance > 0.0)
+                .unwrap_or(f64::INFINITY),
+        },
+        // Reflejo de las paredes en el suelo de metal y en el agua (--reflections)
+        reflections: args.iter().any(|arg| arg == "--reflections"),
+        #[cfg(feature = "ray-stats")]
+        cost_overlay: false,
+    };
+
+    // Ciclo de día y noche para el cielo y la luz ambiental (--day-length segundos por ciclo)
+    let mut day_cycle = arg_value(&args, "--day-length")
+        .and_then(|value| value.parse::<f64>().ok())
+        .filter(|period| *period > 0.0)
+        .map(DayCycle::new);
+
+    // Escala del HUD (--hud-scale 1-4); F3 cambia a la siguiente
+    let mut hud_scale = arg_value(&args, "--hud-scale")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(1)
+        .clamp(1, MAX_HUD_SCALE);
+
+    // Bordes de las paredes para el minimapa de líneas y el automapa (el mapa no cambia)
+    let mut segments = wall_segments(&map);
+    // Estilo del minimapa; F5 alterna entre celdas y líneas
+    let mut minimap_style = MinimapStyle::Cells;
+    let mut minimap_layer = render_minimap_layer(&map, &segments, &PALETTES[palette_index], hud_scale, minimap_style);
+
+    // Orientación del minimapa; F4 alterna entre norte arriba y jugador arriba
+    let mut minimap_mode = MinimapMode::NorthUp;
+
+    // Automapa a pantalla completa con Tab; mientras está abierto, +/- cambian el zoom hacia el
+    // cursor y F alterna entre seguir al jugador y desplazarlo con las flechas o arrastrando
+    let mut automap_open = false;
+    let mut automap_view = AutomapView::new(AUTOMAP_ZOOM, AUTOMAP_ZOOM_RANGE);
+    let mut drag_from: Option<(f64, f64)> = None;
+
+    // Marcadores del nivel: con el automapa abierto, 1 a 4 ponen uno en la celda del jugador (o
+    // en la del cursor si se desplaza a mano) y Suprimir quita el más cercano
+    let mut markers = Markers::default();
+
+    // Recorrido de cámara de introducción (--camera-path ruta); Espacio lo salta
+    let mut intro = arg_value(&args, "--camera-path").and_then(|path| {
+        CameraPath::load(path)
+            .map_err(|err| eprintln!("warning: {}: {}", path, err))
+            .ok()
+    });
+    let mut intro_time = 0.0;
+
+    // Grabación de GIF con F10, capturando uno de cada N cuadros (--record-every N)
+    let mut recorder = Recorder::new(
+        arg_value(&args, "--record-every")
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or(2),
+    );
+
+    // Ruta del mapa actual, para el informe de fallos y el servidor de depuración
+    let mut map_name = arg_value(&args, "--map").unwrap_or("built-in").to_string();
+
+    // Ajustes que no cambian durante la partida
+    if let Ok(mut snapshot) = diagnostics.lock() {
+        snapshot.map = map_name.clone();
+        snapshot.set_setting("args", args[1..].join(" "));
+        snapshot.set_setting("split", split_screen.to_string());
+        snapshot.set_setting("adaptive", adaptive_resolution.to_string());
+        snapshot.set_setting("ao", render_options.ao_strength.to_string());
+        snapshot.set_setting("tint", format!("{:06X}", render_options.light_tint.to_u32()));
+        snapshot.set_setting("ambient", render_options.light_intensity.to_string());
+    }
 
-    let window_width = WIDTH;
-    let window_height = HEIGHT;
 
     let target_fps = 60;
     let frame_duration = Duration::from_secs_f64(1.0 / target_fps as f64);
 
-    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    // La escena 3D se dibuja en `scene` (solo se usa la parte que indica la escala) y se escala
+    // a la región con barras de `framebuffer`, que tiene el tamaño de la ventana y donde el HUD
+    // se dibuja a la resolución de la ventana
+    // Con --supersample la escena se dibuja al doble de resolución en cada eje y se reduce a
+    // `resolved` promediando bloques de 2x2 (cuesta unas cuatro veces más)
+    let supersample = if args.iter().any(|arg| arg == "--supersample") { 2 } else { 1 };
+    let mut scene = Framebuffer::new(WIDTH * supersample, HEIGHT * supersample).unwrap();
+    let mut resolved = Framebuffer::new(WIDTH, HEIGHT).unwrap();
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT).unwrap();
     let mut window = Window::new(
         "3D Raycaster",
-        window_width,
-        window_height,
-        WindowOptions::default(),
+        WIDTH,
+        HEIGHT,
+        WindowOptions {
+            resize: true,
+            scale_mode: ScaleMode::UpperLeft, // El escalado con barras lo hace el juego
+            ..WindowOptions::default()
+        },
     )
     .unwrap();
 
+    // Consola de desarrollo, se abre y se cierra con la tecla `
+    let mut console = Console::new(&mut window);
+
     let mut last_time = Instant::now();
     let mut frame_count = 0;
     let mut fps = 0;
 
-    while window.is_open() && !window.is_key_down(Key::Escape) {
+    let mut previous_start = Instant::now();
+    let mut frame_number: u64 = 0;
+
+    // Escala del tiempo de la simulación (la orden timescale de la consola, y [ y ] en las
+    // compilaciones de depuración). El render, la entrada y el audio siguen a velocidad normal.
+    // Con escala 0 el juego se detiene y \ avanza exactamente un cuadro.
+    let mut time_scale = 1.0;
+    // La simulación avanza en pasos fijos de un cuadro del objetivo de FPS
+    let mut timestep = FixedTimestep::new(frame_duration.as_secs_f64());
+
+    // Reloj de la ondulación bajo el agua; sigue el tiempo de la simulación
+    let mut underwater_time = 0.0;
+
+    // Retrovisor arriba al centro (--mirror, F6 lo alterna; solo con un jugador). Casi duplica
+    // el costo de lanzar rayos en sus columnas, así que el HUD muestra cuánto tarda
+    let mut mirror_enabled = args.iter().any(|arg| arg == "--mirror");
+
+    // Modo speedrun (--speedrun): tiempo de la partida en pasos de simulación y teclas pulsadas
+    // en pantalla. Cargar otro mapa registra el tiempo parcial del anterior, comparado con el
+    // récord personal; "run finish" termina la partida y exporta sus tiempos
+    let mut speedrun = args.iter().any(|arg| arg == "--speedrun").then(|| SpeedrunTimer::new(timestep.step()));
+    let speedrun_dir = speedrun.as_ref().map(|_| paths::output_dir("speedruns"));
+    let mut personal_best = match &speedrun_dir {
+        Some(dir) => PersonalBest::load(&dir.join(BEST_FILE)).unwrap_or_else(|err| {
+            eprintln!("warning: {}: {}", dir.join(BEST_FILE).display(), err);
+            PersonalBest::default()
+        }),
+        None => PersonalBest::default(),
+    };
+    let mut mirror = Framebuffer::new(MIRROR_WIDTH, MIRROR_HEIGHT).unwrap();
+    let mut mirror_cost = Duration::ZERO;
+
+    // Servidor de depuración en localhost (--debug-port puerto), solo si se compiló con la
+    // feature debug-server
+    #[cfg(feature = "debug-server")]
+    let mut debug_server = arg_value(&args, "--debug-port")
+        .and_then(|value| value.parse::<u16>().ok())
+        .and_then(|port| {
+            debug_server::DebugServer::start(port)
+                .map_err(|err| eprintln!("warning: cannot start debug server on port {}: {}", port, err))
+                .ok()
+        });
+    #[cfg(feature = "debug-server")]
+    if let Some(server) = &debug_server {
+        println!("Debug server listening on {}", server.address());
+    }
+
+    // Columnas de la última escena con una pared más cerca que NEAR_PLANE (se avisa en el HUD
+    // de las compilaciones de depuración)
+    let mut scene_stats = SceneStats::default();
+
+    // Entradas de la última escena dibujada; None obliga a dibujarla de nuevo
+    let mut last_scene: Option<SceneKey> = None;
+
+    // Franja de costo del DDA por columna; solo existe con la feature ray-stats y empieza oculta
+    #[cfg(feature = "ray-stats")]
+    let mut cost_overlay = false;
+
+    // Cámara de depuración alrededor del jugador 1 (orden "camera orbit"); None es la vista normal
+    let mut orbit: Option<OrbitCamera> = None;
+
+    while window.is_open() {
         let start_time = Instant::now();
+        let dt = start_time.duration_since(previous_start).as_secs_f64();
+        previous_start = start_time;
 
-        framebuffer.buffer.fill(COLOR_FONDO);
+        // Escape cierra la consola si está abierta y, si no, sale del juego
+        if window.is_key_pressed(Key::Escape, KeyRepeat::No) {
+            if !console.is_open() {
+                break;
+            }
+            console.toggle();
+        }
+        if window.is_key_pressed(Key::Backquote, KeyRepeat::No) {
+            console.toggle();
+        }
+        if let Some(command) = console.update(&window) {
+            match command {
+                Command::Help => {
+                    for usage in usages() {
+                        console.print(usage.to_string());
+                    }
+                }
+                Command::Noclip => {
+                    player.noclip = !player.noclip;
+                    console.print(strings.get(if player.noclip { "console.noclip_on" } else { "console.noclip_off" }).to_string());
+                }
+                Command::Teleport(x, y) => {
+                    if x < 0.0 || y < 0.0 || x >= map.width as f64 || y >= map.height as f64 {
+                        console.print(strings.format("console.outside_map", &[&x, &y, &map.width, &map.height]));
+                    } else {
+                        (player.x, player.y) = (x, y);
+                    }
+                }
+                Command::SetFov(degrees) => {
+                    if (MIN_FOV..=MAX_FOV).contains(&degrees) {
+                        player.set_fov(degrees);
+                    } else {
+                        console.print(strings.format("console.fov_range", &[&MIN_FOV, &MAX_FOV]));
+                    }
+                }
+                Command::Timescale(factor) => {
+                    if (0.0..=MAX_TIME_SCALE).contains(&factor) {
+                        time_scale = factor;
+                    } else {
+                        console.print(strings.format("console.timescale_range", &[&MAX_TIME_SCALE]));
+                    }
+                }
+                Command::OrbitCamera(enabled) => {
+                    orbit = enabled.then(|| OrbitCamera::new(&player, ORBIT_RADIUS));
+                }
+                Command::FinishRun => match (&mut speedrun, &speedrun_dir) {
+                    (Some(timer), Some(dir)) if !timer.is_finished() => {
+                        timer.finish(&map_name);
+                        // El resumen se compara con el récord de antes de esta partida
+                        match export_summary(dir, timer.splits(), &personal_best) {
+                            Ok(path) => console.print(strings.format("console.run_finished", &[&format_run_time(timer.seconds()), &path.display()])),
+                            Err(err) => console.print(format!("{}: {}", dir.display(), err)),
+                        }
+                        if personal_best.record(timer.splits()) {
+                            console.print(strings.get("console.personal_best").to_string());
+                        }
+                        let best_path = dir.join(BEST_FILE);
+                        if let Err(err) = personal_best.save(&best_path) {
+                            console.print(format!("{}: {}", best_path.display(), err));
+                        }
+                    }
+                    (Some(_), _) => console.print(strings.get("console.run_already_finished").to_string()),
+                    (None, _) => console.print(strings.get("console.speedrun_off").to_string()),
+                },
+                Command::ResetRun => match &mut speedrun {
+                    Some(timer) => {
+                        timer.reset();
+                        console.print(strings.get("console.run_reset").to_string());
+                    }
+                    None => console.print(strings.get("console.speedrun_off").to_string()),
+                },
+                Command::Map(path) => match load_map(&path) {
+                    // Un mapa con errores no se carga y se sigue jugando en el actual
+                    Ok((_, issues)) if has_errors(&issues) => {
+                        for issue in issues.iter().filter(|issue| issue.error) {
+                            console.print(format!("{}:{}", path, issue));
+                        }
+                        console.print(strings.format("console.map_rejected", &[&path]));
+                    }
+                    Ok((new_map, issues)) => {
+                        map = new_map;
+                        segments = wall_segments(&map);
+                        minimap_layer = render_minimap_layer(&map, &segments, &PALETTES[palette_index], hud_scale, minimap_style);
+                        (player.x, player.y) = spawn_point(&map);
+                        (player2.x, player2.y) = second_spawn_point(&map);
+                        last_scene = None;
+                        markers.clear();
+                        if let Some(timer) = &mut speedrun {
+                            timer.split(&map_name);
+                        }
+                        map_name = path.clone();
+                        if let Ok(mut snapshot) = diagnostics.lock() {
+                            snapshot.map = map_name.clone();
+                        }
+                        console.print(strings.format("console.map_loaded", &[&path, &issues.len()]));
+                    }
+                    Err(err) => console.print(format!("{}: {}", path, err)),
+                },
+            }
+        }
 
-        // Capturar entradas del teclado para mover al jugador
-        if window.is_key_down(Key::W) || window.is_key_down(Key::Up) {
-            player.move_forward(0.05, &map); // Reduce la velocidad de movimiento
+        if window.is_key_pressed(Key::F2, KeyRepeat::No) {
+            palette_index = (palette_index + 1) % PALETTES.len();
+            minimap_layer = render_minimap_layer(&map, &segments, &PALETTES[palette_index], hud_scale, minimap_style);
         }
-        if window.is_key_down(Key::S) || window.is_key_down(Key::Down) {
-            player.move_backward(0.05, &map); // Reduce la velocidad de movimiento
+        if window.is_key_pressed(Key::F4, KeyRepeat::No) {
+            minimap_mode = match minimap_mode {
+                MinimapMode::NorthUp => MinimapMode::PlayerUp,
+                MinimapMode::PlayerUp => MinimapMode::NorthUp,
+            };
         }
-        if window.is_key_down(Key::A) || window.is_key_down(Key::Left) {
-            player.turn_left(0.03); // Reduce la velocidad de rotación
+        if window.is_key_pressed(Key::F6, KeyRepeat::No) {
+            mirror_enabled = !mirror_enabled;
         }
-        if window.is_key_down(Key::D) || window.is_key_down(Key::Right) {
-            player.turn_right(0.03); // Reduce la velocidad de rotación
+        let show_mirror = mirror_enabled && !split_screen;
+        if window.is_key_pressed(Key::F5, KeyRepeat::No) {
+            minimap_style = match minimap_style {
+                MinimapStyle::Cells => MinimapStyle::Lines,
+                MinimapStyle::Lines => MinimapStyle::Cells,
+            };
+            minimap_layer = render_minimap_layer(&map, &segments, &PALETTES[palette_index], hud_scale, minimap_style);
+        }
+        if cfg!(debug_assertions) && !console.is_open() {
+            if window.is_key_pressed(Key::LeftBracket, KeyRepeat::No) {
+                time_scale /= 2.0;
+            }
+            if window.is_key_pressed(Key::RightBracket, KeyRepeat::No) {
+                time_scale = (time_scale * 2.0).min(MAX_TIME_SCALE);
+            }
         }
+        let single_step = time_scale == 0.0 && !console.is_open() && window.is_key_pressed(Key::Backslash, KeyRepeat::Yes);
+        // Pasos fijos que da la simulación en este cuadro, según el tiempo real por la escala
+        let sim_ticks = if single_step { 1 } else { timestep.advance(dt * time_scale) };
+        let sim_dt = sim_ticks as f64 * timestep.step();
 
-        // Control del volumen
-        if window.is_key_down(Key::Equal) { // Tecla "+"
-            volume = (volume + 0.001).min(4.0); // Aumenta el volumen hasta un máximo de 1.0
-            sink.set_volume(volume);
+        if !console.is_open() && window.is_key_pressed(Key::Tab, KeyRepeat::No) {
+            automap_open = !automap_open;
         }
-        if window.is_key_down(Key::Minus) { // Tecla "-"
-            volume = (volume - 0.001).max(0.0); // Disminuye el volumen hasta un mínimo de 0.0
-            sink.set_volume(volume);
+        if automap_open && !console.is_open() && window.is_key_pressed(Key::F, KeyRepeat::No) {
+            automap_view.follow = !automap_view.follow;
         }
+        let automap_scrolling = automap_open && !automap_view.follow;
+        if window.is_key_pressed(Key::F10, KeyRepeat::No) {
+            // Al terminar de grabar se avisa dónde queda el GIF
+            if let Some(path) = recorder.toggle() {
+                toasts.push(strings.format("hud.recording_path", &[&path.display()]));
+            }
+        }
+        if window.is_key_pressed(Key::F3, KeyRepeat::No) {
+            hud_scale = hud_scale % MAX_HUD_SCALE + 1;
+            minimap_layer = render_minimap_layer(&map, &segments, &PALETTES[palette_index], hud_scale, minimap_style);
+        }
+        // Colores y luz de este cuadro, según la hora del día si el ciclo está activo
+        let mut palette = PALETTES[palette_index];
+        let mut frame_options = render_options;
+        if let Some(cycle) = &mut day_cycle {
+            cycle.update(sim_dt);
+            palette.sky = palette.sky.light(cycle.sky_tint(), 1.0);
+            frame_options.light_tint = render_options.light_tint.light(cycle.ambient_tint(), 1.0);
+        }
+        // F7 muestra u oculta la franja de costo del DDA y los pasos por rayo del HUD
+        #[cfg(feature = "ray-stats")]
+        {
+            if !console.is_open() && window.is_key_pressed(Key::F7, KeyRepeat::No) {
+                cost_overlay = !cost_overlay;
+            }
+            frame_options.cost_overlay = cost_overlay;
+        }
+        let palette = &palette;
+
+        // Si la ventana cambió de tamaño, el framebuffer la sigue y el HUD se reacomoda solo,
+        // porque se ancla a los bordes del framebuffer
+        let (window_width, window_height) = window.get_size();
+        let (window_width, window_height) = (window_width.max(1), window_height.max(1));
+        if (framebuffer.width, framebuffer.height) != (window_width, window_height) {
+            framebuffer = Framebuffer::new(window_width, window_height).unwrap();
+        }
+        framebuffer.buffer.fill(0);
+        let view = Viewport::letterbox(WIDTH, HEIGHT, window_width, window_height);
 
-        // Renderiza la escena 3D
-        render_scene(&map, &player, &mut framebuffer);
+        underwater_time += sim_dt;
 
-        // Dibujar el minimapa en la esquina superior izquierda
-        draw_minimap(&map, &player, &mut framebuffer);
+        // Mientras se reproduce la introducción, el recorrido controla la cámara y se ignora la entrada
+        if let Some(path) = &intro {
+            intro_time += sim_dt;
+            let (x, y, direction) = path.sample(intro_time);
+            (player.x, player.y) = (x, y);
+            player.set_direction(direction);
+            if intro_time >= path.duration() || window.is_key_pressed(Key::Space, KeyRepeat::No) {
+                intro = None;
+            }
+        } else if console.is_open() || automap_scrolling {
+            // Mientras la consola está abierta, el teclado escribe en ella y los jugadores no se
+            // mueven; lo mismo mientras las flechas desplazan el automapa
+        } else {
+            for _ in 0..sim_ticks {
+                if split_screen {
+                    // Capturar entradas del teclado para mover a los jugadores
+                    handle_movement(&window, &CONTROLS_P1, &mut player, &map, timestep.step());
+                    handle_movement(&window, &CONTROLS_P2, &mut player2, &map, timestep.step());
+                } else {
+                    handle_movement(&window, &CONTROLS_SINGLE, &mut player, &map, timestep.step());
+                }
+            }
+        }
+        if let Some(timer) = &mut speedrun {
+            timer.advance(sim_ticks, intro.is_some());
+        }
+        let hud_visible = intro.is_none();
+
+        // Música: N pasa a la siguiente pista; al empezar una se muestra su nombre
+        if !console.is_open() && window.is_key_pressed(Key::N, KeyRepeat::No) {
+            music.skip();
+        }
+        if let Some(track) = music.update(dt) {
+            toasts.push(strings.format("hud.now_playing", &[&track]));
+        }
+
+        // Grabación que terminó de guardarse (o que no se pudo guardar)
+        match recorder.poll_saved() {
+            Some(Ok(path)) => toasts.push(strings.format("hud.recording_saved", &[&path.display()])),
+            Some(Err(err)) => {
+                eprintln!("warning: {}", err);
+                toasts.push(strings.get("hud.recording_failed").to_string());
+            }
+            None => {}
+        }
+        toasts.update(dt);
+
+        // Control del volumen (o del zoom del automapa): la velocidad depende del tiempo pulsado, no de los FPS
+        let key_direction = match (window.is_key_down(Key::Equal), window.is_key_down(Key::Minus)) {
+            _ if console.is_open() => 0.0,
+            (true, false) => 1.0,  // Tecla "+"
+            (false, true) => -1.0, // Tecla "-"
+            _ => 0.0,
+        };
+        let volume_direction = if automap_open { 0.0 } else { key_direction };
+        if automap_open {
+            let screen = (framebuffer.width as f64, framebuffer.height as f64);
+            // El automapa ocupa todo el framebuffer: el cursor cuenta en cualquier punto de la ventana
+            let mouse = window
+                .get_mouse_pos(MouseMode::Pass)
+                .and_then(|(x, y)| Viewport::new(0, 0, framebuffer.width, framebuffer.height).to_framebuffer(x, y))
+                .map(|(x, y)| (x as f64, y as f64));
+            let anchor = mouse.unwrap_or((screen.0 / 2.0, screen.1 / 2.0));
+            automap_view.zoom_at(AUTOMAP_ZOOM_RATE.powf(key_direction * dt), anchor, screen);
+
+            if automap_view.follow {
+                automap_view.center = (player.x, player.y);
+                drag_from = None;
+            } else {
+                let axis = |positive: Key, negative: Key| match (window.is_key_down(positive), window.is_key_down(negative)) {
+                    _ if console.is_open() => 0.0,
+                    (true, false) => 1.0,
+                    (false, true) => -1.0,
+                    _ => 0.0,
+                };
+                let step = AUTOMAP_PAN_SPEED * dt;
+                automap_view.pan(axis(Key::Right, Key::Left) * step, axis(Key::Down, Key::Up) * step);
+
+                // Arrastrar con el botón izquierdo mueve el mapa junto con el cursor
+                drag_from = match (mouse, window.get_mouse_down(MouseButton::Left)) {
+                    (Some((x, y)), true) => {
+                        if let Some((from_x, from_y)) = drag_from {
+                            automap_view.pan(from_x - x, from_y - y);
+                        }
+                        Some((x, y))
+                    }
+                    _ => None,
+                };
+            }
+
+            // Marcadores: en la posición del jugador, o bajo el cursor si se desplaza a mano
+            let target = match mouse {
+                Some((x, y)) if !automap_view.follow => automap_view.to_world(x, y, screen),
+                _ if !automap_view.follow => automap_view.center,
+                _ => (player.x, player.y),
+            };
+            let in_map = target.0 >= 0.0 && target.1 >= 0.0 && (target.0 as usize) < map.width && (target.1 as usize) < map.height;
+            let marker_keys: [Key; MARKER_KINDS] = [Key::Key1, Key::Key2, Key::Key3, Key::Key4];
+            for (kind, &key) in marker_keys.iter().enumerate() {
+                if console.is_open() || !in_map || !window.is_key_pressed(key, KeyRepeat::No) {
+                    continue;
+                }
+                if !markers.place(target.0 as usize, target.1 as usize, kind) {
+                    console.print(strings.get("console.marker_limit").to_string());
+                }
+            }
+            if !console.is_open() && window.is_key_pressed(Key::Delete, KeyRepeat::No) {
+                markers.remove_nearest(target.0, target.1);
+            }
+        }
+        if volume_direction != 0.0 {
+            volume = ramp_volume(volume, volume_direction, dt);
+            music.set_volume(volume);
+            volume_bar_timer = VOLUME_BAR_TIME;
+        }
+        volume_bar_timer = (volume_bar_timer - dt).max(0.0);
+
+        // Resolución interna de la escena para este cuadro
+        let scale = if adaptive_resolution { resolution.scale() } else { 1.0 };
+
+        // Teclas de movimiento del jugador 1 en este cuadro, para el informe de fallos y el modo speedrun
+        let controls = if split_screen { &CONTROLS_P1 } else { &CONTROLS_SINGLE };
+        let captured = intro.is_some() || console.is_open();
+        let input = if captured { 0 } else { input_bits(&window, controls) };
+
+        // Estado para el informe de fallos, antes de renderizar
+        if let Ok(mut snapshot) = diagnostics.lock() {
+            snapshot.frame = frame_number;
+            snapshot.player = (player.x, player.y, player.direction);
+            snapshot.push_input(input);
+            snapshot.set_setting("palette", palette.name.to_string());
+            snapshot.set_setting("hud_scale", hud_scale.to_string());
+            snapshot.set_setting("render_scale", scale.to_string());
+            snapshot.set_setting("volume", volume.to_string());
+            snapshot.set_setting("time_scale", time_scale.to_string());
+            snapshot.set_setting("mirror", show_mirror.to_string());
+        }
+        frame_number += 1;
+
+        let render_width = ((scene.width as f64 * scale) as usize).max(1);
+        let render_height = ((scene.height as f64 * scale) as usize).max(1);
+
+        let players: &[&Player] = if split_screen { &[&player, &player2] } else { &[&player] };
+
+        // Pose desde la que se dibuja la vista del jugador 1: la suya o la de la cámara orbital.
+        // La rueda del ratón acerca o aleja la cámara orbital.
+        if let Some(orbit) = &mut orbit {
+            let wheel = if console.is_open() { 0.0 } else { window.get_scroll_wheel().map_or(0.0, |(_, y)| y as f64) };
+            orbit.update(dt, wheel);
+        }
+        let orbit_camera = orbit.as_ref().map(|orbit| orbit.pose(&map, &player));
+        let view1 = orbit_camera.as_ref().unwrap_or(&player);
+
+        if automap_open {
+            // El automapa ocupa toda la pantalla y reemplaza la vista 3D y el minimapa
+            draw_automap(&segments, players, &markers, &mut framebuffer, palette, &automap_view);
+        } else {
+            // Renderiza la escena 3D (dividida verticalmente en modo de dos jugadores), solo si
+            // algo de lo que se ve cambió desde el último cuadro dibujado
+            let views: Vec<&Player> = std::iter::once(view1).chain(players.iter().copied()).collect();
+            let key = SceneKey::new(&views, (render_width, render_height), palette, &frame_options, show_mirror);
+            if last_scene.as_ref() != Some(&key) {
+                scene.buffer.fill(palette.background.light(frame_options.light_tint, frame_options.light_intensity).to_u32());
+                let viewport1 = if split_screen {
+                    Viewport::new(0, 0, render_width / 2, render_height)
+                } else {
+                    Viewport::new(0, 0, render_width, render_height)
+                };
+                scene_stats = render_scene(&map, view1, &mut scene, viewport1, palette, &frame_options);
+                if split_screen {
+                    let half = render_width / 2;
+                    scene_stats = scene_stats.merge(render_scene(&map, &player2, &mut scene, Viewport::new(half, 0, render_width - half, render_height), palette, &frame_options));
+                }
+                if let Some(camera) = &orbit_camera {
+                    draw_billboard(&map, camera, &player, &mut scene, viewport1, palette.players[0], &frame_options);
+                }
+                if supersample > 1 {
+                    scene.downsample_into(&mut resolved, render_width / 2, render_height / 2);
+                }
+                if show_mirror {
+                    let mirror_start = Instant::now();
+                    render_mirror(&map, &player, &mut mirror, palette, &frame_options);
+                    mirror_cost = mirror_start.elapsed();
+                }
+                last_scene = Some(key);
+            }
+
+            // El HUD se dibuja después sobre `framebuffer`, así que la escena guardada queda limpia
+            if supersample > 1 {
+                framebuffer.upscale_into(&resolved, render_width / 2, render_height / 2, view);
+            } else {
+                framebuffer.upscale_into(&scene, render_width, render_height, view);
+            }
+
+            // Vista ondulada y azul para el jugador que está bajo el agua (solo su mitad en pantalla dividida)
+            for (i, p) in players.iter().enumerate() {
+                if map.floor_at(p.x, p.y).is_submerged() {
+                    let area = if split_screen {
+                        let half = view.width / 2;
+                        Viewport::new(view.x + i * half, view.y, if i == 0 { half } else { view.width - half }, view.height)
+                    } else {
+                        view
+                    };
+                    underwater::apply(&mut framebuffer, area, underwater_time);
+                }
+            }
+
+            // Dibujar el minimapa en la esquina inferior derecha (el HUD se oculta durante la introducción)
+            if hud_visible {
+                draw_minimap(&minimap_layer, players, &markers, &mut framebuffer, palette, hud_scale, minimap_mode);
+                if show_mirror {
+                    draw_mirror(&mut framebuffer, &mirror, palette, hud_scale);
+                }
+            }
+        }
 
         // Calcular FPS
         let current_time = Instant::now();
@@ -242,28 +1537,633 @@ fn main() {
             frame_count = 0;
         }
 
-        // Dibujar FPS en la esquina superior derecha con la palabra "FPS"
-        let width = framebuffer.width;
-        draw_text(&mut framebuffer, width - 70, 10, &format!("{}FPS", fps), 0xFFFFFF);
+        // Dibujar FPS en la esquina superior derecha
+        if hud_visible {
+            let fps_text = strings.format("hud.fps", &[&fps]);
+            let (text_width, _) = measure_text(&fps_text, hud_scale);
+            let x = framebuffer.width.saturating_sub(text_width + HUD_MARGIN * hud_scale);
+            draw_text(&mut framebuffer, x, HUD_MARGIN * hud_scale, &fps_text, palette.hud_text, hud_scale);
+
+            // Costo del retrovisor debajo de los FPS (el del último cuadro en que se dibujó)
+            if show_mirror && !automap_open {
+                let cost_ms = format!("{:.1}", mirror_cost.as_secs_f64() * 1000.0);
+                let cost_text = strings.format("hud.mirror_cost", &[&cost_ms]);
+                let (text_width, text_height) = measure_text(&cost_text, hud_scale);
+                let x = framebuffer.width.saturating_sub(text_width + HUD_MARGIN * hud_scale);
+                let y = HUD_MARGIN * hud_scale + text_height + 2 * hud_scale;
+                draw_text(&mut framebuffer, x, y, &cost_text, palette.hud_text, hud_scale);
+            }
+        }
+
+        // Indicador de grabación en la esquina superior izquierda
+        if recorder.is_recording() {
+            let margin = HUD_MARGIN * hud_scale;
+            draw_text(&mut framebuffer, margin, margin, strings.get("hud.recording"), palette.hud_alert, hud_scale);
+        }
+
+        // Aviso de depuración si la cámara quedó pegada a una pared, debajo del de grabación
+        if cfg!(debug_assertions) && scene_stats.near_columns > 0 && !automap_open {
+            let margin = HUD_MARGIN * hud_scale;
+            let y = margin + (GLYPH_HEIGHT + 2) * hud_scale;
+            draw_text(&mut framebuffer, margin, y, strings.get("hud.near_clamp"), palette.hud_alert, hud_scale);
+        }
+
+        // Pasos del DDA por columna en el último render de la escena: mínimo, promedio y máximo
+        #[cfg(feature = "ray-stats")]
+        if cost_overlay && !automap_open {
+            let (min, average, max) = scene_stats.steps.summary();
+            let average = format!("{:.1}", average);
+            let text = strings.format("hud.ray_steps", &[&min, &average, &max]);
+            let margin = HUD_MARGIN * hud_scale;
+            let y = margin + 2 * (GLYPH_HEIGHT + 2) * hud_scale;
+            draw_text(&mut framebuffer, margin, y, &text, palette.hud_text, hud_scale);
+        }
+
+        // Barra de volumen durante un momento después de cambiarlo
+        if volume_bar_timer > 0.0 {
+            draw_volume_bar(&mut framebuffer, volume, palette, hud_scale);
+        }
 
-        // Actualiza el búfer de pantalla con el contenido del framebuffer
-        let mut display_buffer = vec![COLOR_FONDO; window_width * window_height];
-        for y in 0..HEIGHT {
-            for x in 0..WIDTH {
-                if framebuffer.is_point_set(x, y) {
-                    display_buffer[y * window_width + x] = framebuffer.buffer[y * WIDTH + x];
+        if let Some(timer) = &speedrun {
+            draw_speedrun(&mut framebuffer, timer, &personal_best, input, palette, hud_scale);
+        }
+
+        // Aviso del momento, en la esquina inferior derecha
+        if let Some(text) = toasts.current() {
+            let (text_width, text_height) = measure_text(text, hud_scale);
+            let margin = HUD_MARGIN * hud_scale;
+            let x = framebuffer.width.saturating_sub(margin + text_width);
+            let y = framebuffer.height.saturating_sub(margin + text_height);
+            draw_text(&mut framebuffer, x, y, text, palette.hud_text, hud_scale);
+        }
+
+        console.draw(&mut framebuffer, palette.minimap_floor, palette.hud_text, hud_scale);
+
+        // Imagen final del cuadro: los píxeles sin dibujar toman el color de fondo dentro de la
+        // imagen y el de las barras fuera de ella
+        for y in 0..framebuffer.height {
+            for x in 0..framebuffer.width {
+                if !framebuffer.is_point_set(x, y) {
+                    let color = if view.contains(x, y) { palette.background } else { palette.letterbox };
+                    framebuffer.point(x, y, color);
                 }
             }
         }
+        recorder.capture(&framebuffer.buffer, framebuffer.width, framebuffer.height);
+        #[cfg(feature = "debug-server")]
+        if let Some(server) = &mut debug_server {
+            let players: &[&Player] = if split_screen { &[&player, &player2] } else { &[&player] };
+            let entities = players
+                .iter()
+                .enumerate()
+                .map(|(index, p)| debug_server::Entity { kind: "player", id: index + 1, x: p.x, y: p.y, direction: Some(p.direction) })
+                .chain(markers.iter().enumerate().map(|(index, marker)| debug_server::Entity {
+                    kind: "marker",
+                    id: index,
+                    x: marker.x as f64 + 0.5,
+                    y: marker.y as f64 + 0.5,
+                    direction: None,
+                }))
+                .collect();
+            server.publish(frame_number, (player.x, player.y, player.direction), &map_name, entities, &framebuffer);
+        }
 
         window
-            .update_with_buffer(&display_buffer, window_width, window_height)
+            .update_with_buffer(&framebuffer.buffer, framebuffer.width, framebuffer.height)
             .unwrap();
 
         // Control estricto del tiempo para mantener los FPS
         let elapsed_time = start_time.elapsed();
+        if adaptive_resolution {
+            resolution.update(elapsed_time.as_secs_f64(), dt);
+        }
         if frame_duration > elapsed_time {
             std::thread::sleep(frame_duration - elapsed_time);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::FloorMaterial;
+    use crate::test_dir::TestDir;
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    #[test]
+    fn second_spawn_is_next_to_first() {
+        let (map, _) = map::parse_map("11111\n1P001\n11111\n").unwrap();
+        assert_eq!(spawn_point(&map), (1.5, 1.5));
+        assert_eq!(second_spawn_point(&map), (2.5, 1.5));
+    }
+
+    #[test]
+    fn second_spawn_shares_enclosed_cell() {
+        // La celda libre (3, 1) está detrás de una pared y no se puede llegar a ella
+        let (map, _) = map::parse_map("11111\n1P101\n11111\n").unwrap();
+        assert_eq!(second_spawn_point(&map), spawn_point(&map));
+    }
+
+    #[test]
+    fn move_speed_is_converted_with_the_map_scale() {
+        let (mut map, _) = map::parse_map("111\n1P1\n111\n").unwrap();
+        let player = Player::new(1.5, 1.5, 0.0);
+        // MOVE_SPEED es en metros por segundo: con celdas de 2 m se avanza la mitad de celdas
+        let one_meter = move_distance(&map, &player, 1.0);
+        assert_eq!(one_meter, MOVE_SPEED);
+        map.scale = 2.0;
+        assert_eq!(move_distance(&map, &player, 1.0), MOVE_SPEED / 2.0);
+        assert_eq!(move_distance(&map, &player, 0.25), MOVE_SPEED / 8.0);
+    }
+
+    #[test]
+    fn water_halves_the_move_distance() {
+        let (map, _) = map::parse_map("11111\n1P001\n11111\n\n[floor]\n.....\n.w~m.\n.....\n").unwrap();
+        let distance = |x: f64| move_distance(&map, &Player::new(x, 1.5, 0.0), 0.5);
+        assert_eq!(distance(1.5), MOVE_SPEED * 0.5);
+        assert_eq!(distance(3.5), distance(1.5));
+        assert_eq!(distance(2.5), distance(1.5) * 0.5);
+    }
+
+    #[test]
+    fn floor_is_coloured_by_material() {
+        // El píxel de abajo de la columna central es el suelo a una celda por delante
+        for (symbol, material) in [('.', FloorMaterial::Stone), ('w', FloorMaterial::Wood), ('m', FloorMaterial::Metal), ('~', FloorMaterial::Water)] {
+            let floor = format!("{}\n", symbol.to_string().repeat(7)).repeat(3);
+            let map = map::parse_map(&format!("1111111\n1P00001\n1111111\n\n[floor]\n{}", floor)).unwrap().0;
+            let column = center_column(&map, &Player::new(1.5, 1.5, 0.0));
+            assert_eq!(column[39], PALETTES[0].floor[material.index()], "{}", symbol);
+        }
+    }
+
+    #[test]
+    fn deep_water_slows_the_player_to_swimming() {
+        let (map, _) = map::parse_map("11111\n1P001\n11111\n\n[floor]\n.....\n..W..\n.....\n").unwrap();
+        let walking = move_distance(&map, &Player::new(1.5, 1.5, 0.0), 0.5);
+        let swimming = move_distance(&map, &Player::new(2.5, 1.5, 0.0), 0.5);
+        assert!((walking - MOVE_SPEED * 0.5).abs() < 1e-12);
+        assert!((swimming - walking * 0.35).abs() < 1e-12);
+        assert!(map.floor_at(2.5, 1.5).is_submerged());
+        assert!(!map.floor_at(1.5, 1.5).is_submerged());
+    }
+
+    /// Oscurecimiento de la columna en la posición `wall_x` de la cara, como en render_scene.
+    fn column_ao(wall_x: f64, strength: f64) -> f64 {
+        ambient_occlusion(wall_x.min(1.0 - wall_x), strength)
+    }
+
+    #[test]
+    fn ambient_occlusion_darkens_only_near_edges() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+        for strength in [0.3, 1.0] {
+            assert!(close(column_ao(0.0, strength), 1.0 - strength));
+            // A mitad de la franja la curva cuadrática deja una cuarta parte del oscurecimiento
+            assert!(close(column_ao(0.05, strength), 1.0 - strength * 0.25));
+            assert!(close(column_ao(0.5, strength), 1.0));
+            assert!(close(column_ao(0.95, strength), column_ao(0.05, strength)));
+            assert!(close(column_ao(1.0, strength), column_ao(0.0, strength)));
+            assert!(close(column_ao(AO_EDGE, strength), 1.0));
+        }
+    }
+
+    #[test]
+    fn ambient_occlusion_curve_is_smooth_and_can_be_disabled() {
+        let samples: Vec<f64> = (0..=20).map(|step| column_ao(step as f64 * 0.005, 0.5)).collect();
+        assert!(samples.windows(2).all(|pair| pair[1] > pair[0]), "{:?}", samples);
+        // Sin intensidad no se oscurece nada
+        for wall_x in [0.0, 0.05, 0.5, 0.95, 1.0] {
+            assert_eq!(column_ao(wall_x, 0.0), 1.0);
+        }
+    }
+
+    #[test]
+    fn volume_ramp_does_not_depend_on_the_frame_rate() {
+        for fps in [30, 60, 144] {
+            let dt = 1.0 / fps as f64;
+            let volume = (0..fps).fold(0.2, |volume, _| ramp_volume(volume, 1.0, dt));
+            assert!((volume - (0.2 + VOLUME_RATE)).abs() < 1e-9, "{} FPS: {}", fps, volume);
+            let volume = (0..fps / 2).fold(0.6, |volume, _| ramp_volume(volume, -1.0, dt));
+            assert!((volume - (0.6 - VOLUME_RATE / 2.0)).abs() < 1e-9, "{} FPS: {}", fps, volume);
+        }
+    }
+
+    #[test]
+    fn volume_is_clamped() {
+        assert_eq!(ramp_volume(0.9, 1.0, 1.0), 1.0);
+        assert_eq!(ramp_volume(1.0, 1.0, 0.016), 1.0);
+        assert_eq!(ramp_volume(0.1, -1.0, 1.0), 0.0);
+        assert_eq!(ramp_volume(0.0, -1.0, 0.016), 0.0);
+        // Un cuadro muy largo tampoco pasa de los límites
+        assert_eq!(ramp_volume(0.5, 1.0, 60.0), 1.0);
+    }
+
+    #[test]
+    fn volume_bar_fills_with_the_volume() {
+        let mut framebuffer = Framebuffer::new(200, 100).unwrap();
+        draw_volume_bar(&mut framebuffer, 0.25, &PALETTES[0], 1);
+        let y = 100 - HUD_MARGIN - 1;
+        let filled = VOLUME_BAR_WIDTH / 4;
+        assert_eq!(framebuffer.pixel(HUD_MARGIN, y), PALETTES[0].hud_text);
+        assert_eq!(framebuffer.pixel(HUD_MARGIN + filled - 1, y), PALETTES[0].hud_text);
+        assert_eq!(framebuffer.pixel(HUD_MARGIN + filled, y), PALETTES[0].minimap_floor.drawable());
+        assert_eq!(framebuffer.pixel(HUD_MARGIN + VOLUME_BAR_WIDTH - 1, y), PALETTES[0].minimap_floor.drawable());
+        assert!(!framebuffer.is_point_set(HUD_MARGIN + VOLUME_BAR_WIDTH, y));
+    }
+
+    #[test]
+    fn toasts_wait_their_turn() {
+        let mut toasts = Toasts::default();
+        assert_eq!(toasts.current(), None);
+        toasts.push("now playing".to_string());
+        toasts.update(1.0);
+        toasts.push("recording saved".to_string());
+        assert_eq!(toasts.current(), Some("now playing"));
+
+        toasts.update(TOAST_TIME - 1.0);
+        assert_eq!(toasts.current(), Some("recording saved"));
+        // El segundo aviso tiene su tiempo completo
+        toasts.update(TOAST_TIME - 0.5);
+        assert_eq!(toasts.current(), Some("recording saved"));
+        toasts.update(0.5);
+        assert_eq!(toasts.current(), None);
+    }
+
+    #[test]
+    fn full_toast_queue_drops_the_oldest_waiting() {
+        let mut toasts = Toasts::default();
+        for index in 0..=MAX_TOASTS {
+            toasts.push(index.to_string());
+        }
+        assert_eq!(toasts.queue.len(), MAX_TOASTS);
+        assert_eq!(toasts.current(), Some("0"));
+        toasts.update(TOAST_TIME);
+        assert_eq!(toasts.current(), Some("2"));
+    }
+
+    #[test]
+    fn draw_2d_map_fills_cells() {
+        let (map, _) = map::parse_map("11\n1P\n").unwrap();
+        let mut framebuffer = Framebuffer::new(4, 4).unwrap();
+        draw_2d_map(&map, &mut framebuffer, &PALETTES[0]);
+        assert_eq!(framebuffer.pixel(1, 1), PALETTES[0].minimap_wall);
+        assert_eq!(framebuffer.pixel(3, 3), PALETTES[0].minimap_floor);
+    }
+
+    #[test]
+    fn draw_2d_map_handles_tiny_framebuffer() {
+        let map = initialize_map();
+        let mut framebuffer = Framebuffer::new(1, 1).unwrap();
+        draw_2d_map(&map, &mut framebuffer, &PALETTES[0]);
+        assert_eq!(framebuffer.pixel(0, 0), PALETTES[0].minimap_wall);
+    }
+
+    /// Minimapa de celdas de una sala de 9x9 con una pared dos celdas al este del jugador, que
+    /// está en el centro mirando en `direction`. Devuelve el framebuffer y la posición en
+    /// pantalla del jugador.
+    fn minimap_with_wall_east(direction: f64, mode: MinimapMode) -> (Framebuffer, (isize, isize)) {
+        let text = "111111111\n100000001\n100000001\n100000001\n1000P0101\n100000001\n100000001\n100000001\n111111111\n";
+        let (map, _) = map::parse_map(text).unwrap();
+        let player = Player::new(4.5, 4.5, direction);
+        let layer = render_minimap_layer(&map, &wall_segments(&map), &PALETTES[0], 1, MinimapStyle::Cells);
+        let mut framebuffer = Framebuffer::new(200, 200).unwrap();
+        draw_minimap(&layer, &[&player], &Markers::default(), &mut framebuffer, &PALETTES[0], 1, mode);
+
+        let start = (200 - layer.width - HUD_MARGIN) as isize;
+        let center = match mode {
+            MinimapMode::NorthUp => start + (4.5 * MINIMAP_SCALE as f64) as isize,
+            MinimapMode::PlayerUp => start + (layer.width / 2) as isize,
+        };
+        (framebuffer, (center, center))
+    }
+
+    #[test]
+    fn minimap_modes_agree_when_facing_north() {
+        let facing_north = 3.0 * FRAC_PI_2;
+        let (north_up, north_center) = minimap_with_wall_east(facing_north, MinimapMode::NorthUp);
+        let (player_up, player_center) = minimap_with_wall_east(facing_north, MinimapMode::PlayerUp);
+        let cell = MINIMAP_SCALE as isize;
+        for (dx, dy) in [(2, 0), (-2, 0), (0, 2), (0, -2), (3, 1), (-1, -3)] {
+            let north = north_up.pixel((north_center.0 + dx * cell) as usize, (north_center.1 + dy * cell) as usize);
+            let player = player_up.pixel((player_center.0 + dx * cell) as usize, (player_center.1 + dy * cell) as usize);
+            assert_eq!(north, player, "offset ({}, {})", dx, dy);
+        }
+        assert_eq!(north_up.pixel((north_center.0 + 2 * cell) as usize, north_center.1 as usize), PALETTES[0].minimap_wall);
+    }
+
+    #[test]
+    fn player_up_minimap_turns_the_view_ahead_up() {
+        let cell = MINIMAP_SCALE as isize;
+        // Mirando al este, en North-up la pared está a la derecha del jugador
+        let (north_up, (x, y)) = minimap_with_wall_east(0.0, MinimapMode::NorthUp);
+        assert_eq!(north_up.pixel((x + 2 * cell) as usize, y as usize), PALETTES[0].minimap_wall);
+        assert_eq!(north_up.pixel(x as usize, (y - 2 * cell) as usize), PALETTES[0].minimap_floor);
+
+        // y en Player-up queda encima de él
+        let (player_up, (x, y)) = minimap_with_wall_east(0.0, MinimapMode::PlayerUp);
+        assert_eq!(player_up.pixel(x as usize, (y - 2 * cell) as usize), PALETTES[0].minimap_wall);
+        assert_eq!(player_up.pixel((x + 2 * cell) as usize, y as usize), PALETTES[0].minimap_floor);
+
+        // Mirando al sur la pared del este queda a la izquierda
+        let (player_up, (x, y)) = minimap_with_wall_east(FRAC_PI_2, MinimapMode::PlayerUp);
+        assert_eq!(player_up.pixel((x - 2 * cell) as usize, y as usize), PALETTES[0].minimap_wall);
+    }
+
+    #[test]
+    fn player_up_minimap_is_clipped_to_a_circle() {
+        let (player_up, (x, y)) = minimap_with_wall_east(0.0, MinimapMode::PlayerUp);
+        let radius = (9 * MINIMAP_SCALE / 2) as isize;
+        // Las esquinas de la caja quedan fuera del círculo y no se dibujan
+        assert!(!player_up.is_point_set((x - radius) as usize, (y - radius) as usize));
+        assert!(!player_up.is_point_set((x + radius - 1) as usize, (y + radius - 1) as usize));
+        assert!(player_up.is_point_set(x as usize, (y - radius + 1) as usize));
+    }
+
+    /// Compone un cuadro completo en una ventana de `width` x `height`, como el bucle principal:
+    /// escena con letterbox, efecto submarino, minimapa, retrovisor y HUD; después el automapa.
+    fn compose_frame(width: usize, height: usize, hud_scale: usize) -> Framebuffer {
+        let text = "11111\n1P0W1\n10001\n11111\n\n[floor]\n.....\n...W.\n.....\n.....\n";
+        let map = map::parse_map(text).unwrap().0;
+        let segments = wall_segments(&map);
+        let palette = &PALETTES[0];
+        let player = Player::new(1.5, 1.5, 0.3);
+        let swimmer = Player::new(3.5, 1.5, 2.0);
+        let players: &[&Player] = &[&player, &swimmer];
+        let mut markers = Markers::default();
+        markers.place(2, 2, 1);
+
+        let mut framebuffer = Framebuffer::new(width, height).unwrap();
+        let view = Viewport::letterbox(WIDTH, HEIGHT, width, height);
+        let mut scene = Framebuffer::new(width, height).unwrap();
+        render_scene(&map, &player, &mut scene, Viewport::new(0, 0, width, height), palette, &test_options());
+        framebuffer.upscale_into(&scene, width, height, view);
+        underwater::apply(&mut framebuffer, view, 1.0);
+
+        for style in [MinimapStyle::Cells, MinimapStyle::Lines] {
+            let layer = render_minimap_layer(&map, &segments, palette, hud_scale, style);
+            for mode in [MinimapMode::NorthUp, MinimapMode::PlayerUp] {
+                draw_minimap(&layer, players, &markers, &mut framebuffer, palette, hud_scale, mode);
+            }
+        }
+        let mut mirror = Framebuffer::new(MIRROR_WIDTH, MIRROR_HEIGHT).unwrap();
+        render_mirror(&map, &player, &mut mirror, palette, &test_options());
+        draw_mirror(&mut framebuffer, &mirror, palette, hud_scale);
+
+        let (text_width, text_height) = measure_text("60FPS", hud_scale);
+        draw_text(&mut framebuffer, width.saturating_sub(text_width), height.saturating_sub(text_height), "60FPS", palette.hud_text, hud_scale);
+        draw_volume_bar(&mut framebuffer, 0.7, palette, hud_scale);
+        let mut timer = SpeedrunTimer::new(1.0 / 60.0);
+        timer.advance(90, false);
+        timer.split("first");
+        draw_speedrun(&mut framebuffer, &timer, &PersonalBest::default(), INPUT_FORWARD, palette, hud_scale);
+
+        let mut automap_view = AutomapView::new(AUTOMAP_ZOOM, AUTOMAP_ZOOM_RANGE);
+        automap_view.center = (player.x, player.y);
+        let mut automap = Framebuffer::new(width, height).unwrap();
+        draw_automap(&segments, players, &markers, &mut automap, palette, &automap_view);
+        framebuffer
+    }
+
+    #[test]
+    fn full_frame_renders_at_any_size() {
+        for (width, height) in [(1, 1), (50, 40), (120, 90), (640, 480)] {
+            for hud_scale in 1..=MAX_HUD_SCALE {
+                let frame = compose_frame(width, height, hud_scale);
+                assert_eq!(frame.buffer.len(), width * height);
+            }
+        }
+        // En la ventana grande la escena llena el centro
+        assert!(compose_frame(640, 480, 1).is_point_set(320, 240));
+    }
+
+    /// Sala interior a la izquierda y patio exterior a la derecha, separados por una pared con
+    /// una puerta en (4, 2). La puerta es interior.
+    fn doorway_map() -> Map {
+        let text = "111111111\n100010001\n1P0000001\n100010001\n111111111\n\n[outdoor]\n\
+                    .........\n.....OOOO\n.....OOOO\n.....OOOO\n.........\n";
+        map::parse_map(text).unwrap().0
+    }
+
+    /// Colores de la columna central de una vista de 2x40 de `player`, sin niebla ni efectos.
+    fn center_column(map: &Map, player: &Player) -> Vec<Color> {
+        let options = RenderOptions { ao_strength: 0.0, ..test_options() };
+        let mut framebuffer = Framebuffer::new(2, 40).unwrap();
+        render_scene(map, player, &mut framebuffer, Viewport::new(0, 0, 2, 40), &PALETTES[0], &options);
+        (0..framebuffer.height).map(|y| framebuffer.pixel(1, y)).collect()
+    }
+
+    #[test]
+    fn each_face_of_a_wall_type_has_its_colour() {
+        // Columna de tipo 2 en el centro de una sala, vista desde los cuatro lados
+        let text = "1111111\n1P00001\n1000001\n1002001\n1000001\n1000001\n1111111\n\n\
+                    [walls]\n2 808080 N=FF0000 E=00FF00 S=0000FF W=FFFF00\n";
+        let map = map::parse_map(text).unwrap().0;
+        let views = [
+            (Player::new(3.5, 1.5, FRAC_PI_2), 0xFF0000),      // Desde el norte, mirando al sur
+            (Player::new(5.5, 3.5, PI), 0x00FF00),             // Desde el este, mirando al oeste
+            (Player::new(3.5, 5.5, 3.0 * FRAC_PI_2), 0x0000FF), // Desde el sur, mirando al norte
+            (Player::new(1.5, 3.5, 0.0), 0xFFFF00),            // Desde el oeste, mirando al este
+        ];
+        for (player, expected) in views {
+            let column = center_column(&map, &player);
+            assert_eq!(column[20], Color::from_u32(expected), "from ({}, {})", player.x, player.y);
+        }
+    }
+
+    #[test]
+    fn mirror_shows_the_view_behind_flipped() {
+        // Pasillo este-oeste: rojo al este, azul al oeste, verde al norte y amarillo al sur
+        let text = "444444444\n3P0000002\n555555555\n\n[walls]\n\
+                    2 FF0000\n3 0000FF\n4 00FF00\n5 FFFF00\n";
+        let map = map::parse_map(text).unwrap().0;
+        let player = Player::new(4.5, 1.5, 0.0);
+        let options = RenderOptions { ao_strength: 0.0, ..test_options() };
+
+        let mut front = Framebuffer::new(MIRROR_WIDTH, MIRROR_HEIGHT).unwrap();
+        render_scene(&map, &player, &mut front, Viewport::new(0, 0, MIRROR_WIDTH, MIRROR_HEIGHT), &PALETTES[0], &options);
+        let mut mirror = Framebuffer::new(MIRROR_WIDTH, MIRROR_HEIGHT).unwrap();
+        render_mirror(&map, &player, &mut mirror, &PALETTES[0], &options);
+
+        let (center, middle) = (MIRROR_WIDTH / 2, MIRROR_HEIGHT / 2);
+        assert_eq!(front.pixel(center, middle), Color::from_u32(0xFF0000));
+        assert_eq!(mirror.pixel(center, middle), Color::from_u32(0x0000FF));
+        // Como en un espejo, lo que está a la izquierda del jugador queda a la izquierda
+        assert_eq!(front.pixel(0, middle), Color::from_u32(0x00FF00));
+        assert_eq!(mirror.pixel(0, middle), Color::from_u32(0x00FF00));
+        assert_eq!(front.pixel(MIRROR_WIDTH - 1, middle), Color::from_u32(0xFFFF00));
+        assert_eq!(mirror.pixel(MIRROR_WIDTH - 1, middle), Color::from_u32(0xFFFF00));
+    }
+
+    #[test]
+    fn mirror_is_drawn_top_center_with_a_border() {
+        let mut mirror = Framebuffer::new(MIRROR_WIDTH, MIRROR_HEIGHT).unwrap();
+        mirror.buffer.fill(0x123456);
+        let mut framebuffer = Framebuffer::new(640, 480).unwrap();
+        draw_mirror(&mut framebuffer, &mirror, &PALETTES[0], 1);
+        let (x, y) = ((640 - MIRROR_WIDTH) / 2, HUD_MARGIN);
+        assert_eq!(framebuffer.pixel(x - 1, y - 1), PALETTES[0].hud_text);
+        assert_eq!(framebuffer.pixel(x + MIRROR_WIDTH, y + MIRROR_HEIGHT), PALETTES[0].hud_text);
+        assert_eq!(framebuffer.pixel(x, y), Color::from_u32(0x123456));
+        assert_eq!(framebuffer.pixel(x + MIRROR_WIDTH - 1, y + MIRROR_HEIGHT - 1), Color::from_u32(0x123456));
+
+        // Si no cabe con su borde no se dibuja
+        let mut small = Framebuffer::new(MIRROR_WIDTH + 1, 480).unwrap();
+        draw_mirror(&mut small, &mirror, &PALETTES[0], 1);
+        assert!(small.buffer.iter().all(|&pixel| pixel == 0));
+    }
+
+    #[test]
+    fn faces_without_override_use_the_type_colour() {
+        let text = "1111111\n1P00001\n1000001\n1002001\n1000001\n1000001\n1111111\n\n[walls]\n2 808080 N=FF0000\n";
+        let map = map::parse_map(text).unwrap().0;
+        assert_eq!(center_column(&map, &Player::new(3.5, 1.5, FRAC_PI_2))[20], Color::from_u32(0xFF0000));
+        assert_eq!(center_column(&map, &Player::new(5.5, 3.5, PI))[20], Color::from_u32(0x808080));
+        // Las paredes de tipo 1 siguen con la paleta
+        assert_eq!(center_column(&map, &Player::new(3.5, 2.5, 3.0 * FRAC_PI_2))[20], PALETTES[0].wall_light);
+    }
+
+    /// Color sobre la pared de la columna, comprobando que toda esa parte de la columna lo tiene.
+    fn ceiling_of(column: &[Color]) -> Color {
+        let top = column[0];
+        let wall_start = column.iter().position(|&color| color != top).unwrap();
+        assert!(wall_start > 0 && wall_start < column.len() / 2);
+        top
+    }
+
+    #[test]
+    fn doorway_shows_sky_from_inside() {
+        // Desde la sala, a través de la puerta se ve el fondo del patio
+        let column = center_column(&doorway_map(), &Player::new(1.5, 2.5, 0.0));
+        assert_eq!(ceiling_of(&column), PALETTES[0].sky.drawable());
+    }
+
+    #[cfg(feature = "ray-stats")]
+    #[test]
+    fn cost_overlay_is_drawn_only_when_enabled() {
+        let map = doorway_map();
+        let player = Player::new(1.5, 2.5, 0.0);
+        let render = |cost_overlay| {
+            let options = RenderOptions { cost_overlay, ..test_options() };
+            let mut framebuffer = Framebuffer::new(8, 40).unwrap();
+            render_scene(&map, &player, &mut framebuffer, Viewport::new(0, 0, 8, 40), &PALETTES[0], &options);
+            framebuffer
+        };
+        let (plain, tinted) = (render(false), render(true));
+        let rows = raystats::OVERLAY_ROWS * 8;
+        assert!(plain.buffer[..rows].iter().zip(&tinted.buffer[..rows]).all(|(a, b)| a != b));
+        assert_eq!(plain.buffer[rows..], tinted.buffer[rows..]);
+    }
+
+    /// Código de salida de `check_map` para un mapa con el texto `text`.
+    fn check_map_text(name: &str, text: &str) -> i32 {
+        let dir = TestDir::new("check-map", name);
+        let path = dir.join("level.txt");
+        std::fs::write(&path, text).unwrap();
+        check_map(path.to_str().unwrap())
+    }
+
+    #[test]
+    fn check_map_passes_clean_and_warning_only_maps() {
+        assert_eq!(check_map_text("clean", "1111\n1P01\n1111\n"), 0);
+        // La fila corta de la capa exterior solo es un aviso
+        let warned = "1111\n1P01\n1111\n\n[outdoor]\n....\n..\n....\n";
+        let (_, issues) = map::parse_map(warned).unwrap();
+        assert!(!issues.is_empty() && !has_errors(&issues));
+        assert_eq!(check_map_text("warnings", warned), 0);
+    }
+
+    #[test]
+    fn check_map_fails_on_errors_and_unreadable_files() {
+        assert_eq!(check_map_text("no-spawn", "1111\n1001\n1111\n"), 1);
+        assert_eq!(check_map_text("open-border", "1111\n1P00\n1111\n"), 1);
+        let dir = TestDir::new("check-map", "missing");
+        assert_eq!(check_map(dir.join("missing.txt").to_str().unwrap()), 1);
+    }
+
+    #[test]
+    fn doorway_shows_ceiling_from_outside() {
+        // Desde el patio, a través de la puerta se ve el fondo de la sala
+        let column = center_column(&doorway_map(), &Player::new(7.5, 2.5, std::f64::consts::PI));
+        assert_eq!(ceiling_of(&column), PALETTES[0].ceiling.drawable());
+    }
+
+    #[test]
+    fn doorway_wall_uses_the_side_it_is_seen_from() {
+        let map = doorway_map();
+        let inside = center_column(&map, &Player::new(2.5, 1.5, 0.0));
+        assert_eq!(ceiling_of(&inside), PALETTES[0].ceiling.drawable());
+        let outside = center_column(&map, &Player::new(6.5, 1.5, std::f64::consts::PI));
+        assert_eq!(ceiling_of(&outside), PALETTES[0].sky.drawable());
+    }
+
+    fn test_options() -> RenderOptions {
+        RenderOptions {
+            ao_strength: 0.3,
+            light_tint: Color::WHITE,
+            light_intensity: 1.0,
+            fog: Fog { density: 0.0, color: Color::BLACK, max_distance: f64::INFINITY },
+            reflections: false,
+            #[cfg(feature = "ray-stats")]
+            cost_overlay: false,
+        }
+    }
+
+    fn scene_key(players: &[Player], options: &RenderOptions) -> SceneKey {
+        let views: Vec<&Player> = players.iter().collect();
+        SceneKey::new(&views, (WIDTH, HEIGHT), &PALETTES[0], options, false)
+    }
+
+    #[test]
+    fn scene_key_matches_identical_inputs() {
+        let players = [Player::new(2.5, 3.5, 1.0), Player::new(4.5, 1.5, 2.0)];
+        assert!(scene_key(&players, &test_options()) == scene_key(&players, &test_options()));
+    }
+
+    #[test]
+    fn scene_key_changes_with_any_pose() {
+        let base = [Player::new(2.5, 3.5, 1.0), Player::new(4.5, 1.5, 2.0)];
+        let key = scene_key(&base, &test_options());
+        let changes: [fn(&mut Player); 4] = [
+            |p| p.x += 1e-9,
+            |p| p.y -= 1e-9,
+            |p| p.rotate(1e-9),
+            |p| p.set_fov(91.0),
+        ];
+        for change in changes {
+            for index in 0..base.len() {
+                let mut players = [Player::new(2.5, 3.5, 1.0), Player::new(4.5, 1.5, 2.0)];
+                change(&mut players[index]);
+                assert!(scene_key(&players, &test_options()) != key);
+            }
+        }
+        assert!(scene_key(&base[..1], &test_options()) != key);
+    }
+
+    #[test]
+    fn scene_key_changes_with_view_settings() {
+        let players = [Player::new(2.5, 3.5, 1.0)];
+        let views = [&players[0]];
+        let options = test_options();
+        let key = SceneKey::new(&views, (WIDTH, HEIGHT), &PALETTES[0], &options, false);
+
+        assert!(SceneKey::new(&views, (WIDTH / 2, HEIGHT / 2), &PALETTES[0], &options, false) != key);
+        assert!(SceneKey::new(&views, (WIDTH, HEIGHT), &PALETTES[1], &options, false) != key);
+        assert!(SceneKey::new(&views, (WIDTH, HEIGHT), &PALETTES[0], &options, true) != key);
+
+        let mut tinted = PALETTES[0];
+        tinted.sky = tinted.sky.scale(0.99);
+        assert!(SceneKey::new(&views, (WIDTH, HEIGHT), &tinted, &options, false) != key);
+
+        let option_changes: [fn(&mut RenderOptions); 5] = [
+            |o| o.ao_strength = 0.0,
+            |o| o.light_tint = Color::from_u32(0xFFFFFE),
+            |o| o.light_intensity = 0.5,
+            |o| o.fog.density = 0.1,
+            |o| o.reflections = true,
+        ];
+        for change in option_changes {
+            let mut changed = options;
+            change(&mut changed);
+            assert!(SceneKey::new(&views, (WIDTH, HEIGHT), &PALETTES[0], &changed, false) != key);
+        }
+    }
+}
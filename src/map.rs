@@ -1,34 +1,272 @@
+use crate::color::Color;
+use crate::portal::{Portal, PortalFace, PortalTransform, Side};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Dimensiones máximas soportadas para un mapa (en celdas)
+pub const MAX_MAP_SIZE: usize = 256;
+
+const WATER_SPEED_FACTOR: f64 = 0.5; // Velocidad de movimiento dentro del agua (fracción de la normal)
+const SWIM_SPEED_FACTOR: f64 = 0.35; // Velocidad nadando en agua profunda
+const METAL_REFLECTIVITY: f64 = 0.3; // Reflejo de las paredes en el suelo de metal pulido
+const WATER_REFLECTIVITY: f64 = 0.2; // Reflejo de las paredes en el agua
+const FENCE_BARS: f64 = 4.0;          // Barrotes por celda en una reja
+const FENCE_BAR_WIDTH: f64 = 0.3;     // Ancho de cada barrote (fracción del espacio entre barrotes)
+
+/// Material del suelo de una celda.
+#[derive(Clone, Copy, PartialEq)]
+pub enum FloorMaterial {
+    Stone,
+    Wood,
+    Metal,
+    Water,
+    DeepWater, // El jugador nada bajo el agua
+}
+
+impl FloorMaterial {
+    /// Índice del material, en el mismo orden que los colores de suelo de la paleta.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Factor que se aplica a la velocidad de movimiento sobre este material.
+    pub fn speed_factor(self) -> f64 {
+        match self {
+            FloorMaterial::Water => WATER_SPEED_FACTOR,
+            FloorMaterial::DeepWater => SWIM_SPEED_FACTOR,
+            _ => 1.0,
+        }
+    }
+
+    /// Cuánto refleja el suelo las paredes que tiene delante (0.0 nada, 1.0 como un espejo).
+    pub fn reflectivity(self) -> f64 {
+        match self {
+            FloorMaterial::Metal => METAL_REFLECTIVITY,
+            FloorMaterial::Water => WATER_REFLECTIVITY,
+            _ => 0.0,
+        }
+    }
+
+    /// Indica si el jugador queda sumergido sobre este material.
+    pub fn is_submerged(self) -> bool {
+        self == FloorMaterial::DeepWater
+    }
+}
+
+/// Pared delgada que cruza una celda por el medio, en lugar de ocupar la celda entera.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ThinWall {
+    pub runs_east_west: bool, // true: el plano está en y = celda + 0.5; false: en x = celda + 0.5
+    pub fence: bool,          // Reja: entre los barrotes se ve lo que hay detrás (pero no se pasa)
+}
+
+impl ThinWall {
+    /// Indica si la posición `offset` (0.0 a 1.0) a lo largo de la pared es sólida. Una pared
+    /// lisa lo es entera; una reja solo en sus barrotes.
+    pub fn is_solid_at(self, offset: f64) -> bool {
+        !self.fence || (offset * FENCE_BARS).fract() < FENCE_BAR_WIDTH
+    }
+}
+
 pub struct Map {
     pub width: usize,
     pub height: usize,
-    pub grid: Vec<Vec<i32>>, // 1 es pared, 0 es espacio vacío
+    // Las capas guardan todas las filas seguidas en un solo Vec (índice `y * width + x`), para
+    // que el recorrido de los rayos no salte entre reservas de memoria separadas por fila
+    pub cells: Vec<u8>, // 0 es espacio vacío, 1 a 9 son paredes
+    pub spawn: Option<(usize, usize)>, // Celda inicial del jugador, si el mapa la declara
+    pub outdoor: Vec<bool>, // true si la celda es exterior (se ve el cielo en lugar del techo)
+    pub floor: Vec<FloorMaterial>, // Material del suelo de cada celda
+    pub scale: f64, // Metros que mide el lado de una celda
+    pub portals: Vec<Portal>, // Pares de caras de pared conectadas
+    pub wall_types: [Option<WallType>; 9], // Colores de las paredes 1 a 9, si el mapa los define
+    pub thin_walls: Vec<Option<ThinWall>>, // Pared delgada de cada celda vacía, si tiene
+}
+
+/// Colores de las caras de un tipo de pared, indexados por cara (N, E, S, W).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WallType {
+    pub faces: [Color; 4],
 }
 
 impl Map {
-    pub fn new(width: usize, height: usize) -> Self {
-        let grid = vec![vec![0; width]; height];
-        Self { width, height, grid }
+    /// Crea un mapa vacío. Falla si el número de celdas no cabe en un `u32`.
+    pub fn new(width: usize, height: usize) -> Result<Self, String> {
+        let count = width
+            .checked_mul(height)
+            .filter(|&count| count <= u32::MAX as usize)
+            .ok_or_else(|| format!("map of {}x{} cells is too large", width, height))?;
+        let cells = vec![0; count];
+        let outdoor = vec![true; count];
+        let floor = vec![FloorMaterial::Stone; count];
+        let thin_walls = vec![None; count];
+        Ok(Self { width, height, cells, spawn: None, outdoor, floor, scale: 1.0, portals: Vec::new(), wall_types: [None; 9], thin_walls })
+    }
+
+    /// Convierte una distancia en celdas (como la de los rayos) a metros.
+    #[inline]
+    pub fn to_meters(&self, cells: f64) -> f64 {
+        cells * self.scale
+    }
+
+    /// Convierte una distancia en metros (como las velocidades o la niebla) a celdas.
+    #[inline]
+    pub fn to_cells(&self, meters: f64) -> f64 {
+        meters / self.scale
+    }
+
+    /// Posición de la celda (x, y) en las capas del mapa.
+    #[inline]
+    pub fn index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    /// Valor de la celda (x, y): 0 si está vacía, 1 a 9 si es pared.
+    #[inline]
+    pub fn cell(&self, x: usize, y: usize) -> u8 {
+        self.cells[self.index(x, y)]
+    }
+
+    /// Valor de la celda (x, y), o 0 si está fuera del mapa.
+    pub fn cell_at(&self, x: isize, y: isize) -> u8 {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            self.cell(x as usize, y as usize)
+        } else {
+            0
+        }
+    }
+
+    /// Celdas de la fila `y`.
+    pub fn row(&self, y: usize) -> &[u8] {
+        &self.cells[y * self.width..(y + 1) * self.width]
     }
 
     pub fn is_wall(&self, x: f64, y: f64) -> bool {
         let xi = x as usize;
         let yi = y as usize;
         if xi < self.width && yi < self.height {
-            self.grid[yi][xi] > 0
+            self.cell(xi, yi) > 0
         } else {
             false
         }
     }
 
+    /// Indica si la celda es exterior. Fuera del mapa siempre se considera exterior.
+    pub fn is_outdoor(&self, x: f64, y: f64) -> bool {
+        let xi = x as usize;
+        let yi = y as usize;
+        if x >= 0.0 && y >= 0.0 && xi < self.width && yi < self.height {
+            self.outdoor[self.index(xi, yi)]
+        } else {
+            true
+        }
+    }
+
+    /// Material del suelo de la celda. Fuera del mapa se considera piedra.
+    pub fn floor_at(&self, x: f64, y: f64) -> FloorMaterial {
+        let xi = x as usize;
+        let yi = y as usize;
+        if x >= 0.0 && y >= 0.0 && xi < self.width && yi < self.height {
+            self.floor[self.index(xi, yi)]
+        } else {
+            FloorMaterial::Stone
+        }
+    }
+
+    /// Color de la cara `side` de una pared de tipo `cell` (1 a 9), si el mapa define ese tipo.
+    pub fn wall_color(&self, cell: u8, side: Side) -> Option<Color> {
+        let wall_type = self.wall_types.get(usize::from(cell).checked_sub(1)?)?.as_ref()?;
+        Some(wall_type.faces[side.index()])
+    }
+
+    /// Pared delgada de la celda (x, y), si tiene. Fuera del mapa no hay ninguna.
+    #[inline]
+    pub fn thin_wall(&self, x: isize, y: isize) -> Option<ThinWall> {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            self.thin_walls[self.index(x as usize, y as usize)]
+        } else {
+            None
+        }
+    }
+
+    /// Indica si una pared delgada impide moverse de `from` a `to`: si el movimiento la
+    /// atraviesa en cualquiera de las celdas que recorre (un paso rápido en diagonal puede
+    /// cruzar el plano sin empezar ni terminar en su celda), o si termina a menos de `margin`
+    /// de ella acercándose.
+    pub fn thin_wall_blocks(&self, from: (f64, f64), to: (f64, f64), margin: f64) -> bool {
+        let (min_x, max_x) = (from.0.min(to.0).floor() as isize, from.0.max(to.0).floor() as isize);
+        let (min_y, max_y) = (from.1.min(to.1).floor() as isize, from.1.max(to.1).floor() as isize);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                if self.thin_wall(x, y).is_some_and(|wall| crosses_thin_wall(wall, (x, y), from, to)) {
+                    return true;
+                }
+            }
+        }
+
+        let (cell_x, cell_y) = (to.0.floor(), to.1.floor());
+        let Some(wall) = self.thin_wall(cell_x as isize, cell_y as isize) else {
+            return false;
+        };
+        let (before, after) = plane_offsets(wall, (cell_x as isize, cell_y as isize), from, to);
+        let same_cell = from.0.floor() == cell_x && from.1.floor() == cell_y;
+        after.abs() < margin && (!same_cell || after.abs() < before.abs())
+    }
+
+    /// Si la cara `side` de la celda (`x`, `y`) es un portal, la transformación hacia su pareja.
+    pub fn portal_exit(&self, x: isize, y: isize, side: Side) -> Option<PortalTransform> {
+        let face = PortalFace { x: usize::try_from(x).ok()?, y: usize::try_from(y).ok()?, side };
+        self.portals.iter().find_map(|portal| {
+            if portal.a == face {
+                Some(PortalTransform::new(portal.a, portal.b))
+            } else if portal.b == face {
+                Some(PortalTransform::new(portal.b, portal.a))
+            } else {
+                None
+            }
+        })
+    }
+
     pub fn set_wall(&mut self, x: usize, y: usize) {
         if x < self.width && y < self.height {
-            self.grid[y][x] = 1; // 1 representa una pared
+            let i = self.index(x, y);
+            self.cells[i] = 1; // 1 representa una pared
         }
     }
 }
 
+/// Posición de `from` y de `to` a lo largo del eje que cruza el plano de la pared delgada de
+/// `cell`, relativa al plano.
+fn plane_offsets(wall: ThinWall, cell: (isize, isize), from: (f64, f64), to: (f64, f64)) -> (f64, f64) {
+    if wall.runs_east_west {
+        let plane = cell.1 as f64 + 0.5;
+        (from.1 - plane, to.1 - plane)
+    } else {
+        let plane = cell.0 as f64 + 0.5;
+        (from.0 - plane, to.0 - plane)
+    }
+}
+
+/// Indica si el tramo de `from` a `to` cruza el plano de la pared delgada dentro de su celda.
+fn crosses_thin_wall(wall: ThinWall, cell: (isize, isize), from: (f64, f64), to: (f64, f64)) -> bool {
+    let (before, after) = plane_offsets(wall, cell, from, to);
+    if before.signum() == after.signum() {
+        return false;
+    }
+    // Punto del cruce a lo largo de la pared, que debe caer dentro de la celda
+    let t = before / (before - after);
+    let (along, cell_along) = if wall.runs_east_west {
+        (from.0 + (to.0 - from.0) * t, cell.0)
+    } else {
+        (from.1 + (to.1 - from.1) * t, cell.1)
+    };
+    along.floor() as isize == cell_along
+}
+
 pub fn initialize_map() -> Map {
-    let mut map = Map::new(24, 24);
+    let mut map = Map::new(24, 24).unwrap(); // Tamaño fijo, siempre válido
 
     let grid = vec![
         vec![1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
@@ -56,9 +294,9 @@ pub fn initialize_map() -> Map {
         vec![1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
     ];
 
-    for y in 0..grid.len() {
-        for x in 0..grid[y].len() {
-            if grid[y][x] == 1 {
+    for (y, row) in grid.iter().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            if cell == 1 {
                 map.set_wall(x, y);
             }
         }
@@ -66,3 +304,806 @@ pub fn initialize_map() -> Map {
 
     map
 }
+
+/// Problema encontrado al cargar o validar un archivo de mapa.
+/// La línea y la columna empiezan en 1 y corresponden a la fila y celda del archivo.
+/// Los errores hacen que el mapa no se pueda jugar; el resto son avisos.
+pub struct Issue {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    pub error: bool,
+}
+
+impl Issue {
+    fn new(line: usize, column: usize, message: String) -> Self {
+        Self { line, column, message, error: false }
+    }
+
+    /// Problema en una celda de la rejilla principal, que empieza en la primera línea del archivo.
+    fn at_cell(x: usize, y: usize, message: String) -> Self {
+        Self::new(y + 1, x + 1, message)
+    }
+
+    /// Convierte el aviso en un error.
+    fn into_error(self) -> Self {
+        Self { error: true, ..self }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = if self.error { "error" } else { "warning" };
+        write!(f, "{}:{}: {}: {}", self.line, self.column, severity, self.message)
+    }
+}
+
+/// Indica si alguno de los problemas impide jugar el mapa.
+pub fn has_errors(issues: &[Issue]) -> bool {
+    issues.iter().any(|issue| issue.error)
+}
+
+/// Interpreta un mapa en formato de texto: una fila por línea, `0` es espacio vacío,
+/// `1`-`9` son paredes y `P` marca la posición inicial del jugador. `-` y `|` son paredes
+/// delgadas que cruzan la celda por el medio de oeste a este y de norte a sur, y `=` y `:`
+/// son rejas en las mismas direcciones: se ve entre sus barrotes.
+///
+/// Después de la rejilla, separadas por una línea vacía, pueden venir capas opcionales
+/// con un encabezado `[nombre]` y una fila por línea:
+/// * `[outdoor]` - `O` marca celdas exteriores y `.` interiores. Sin esta capa todo el
+///   mapa es exterior.
+/// * `[floor]` - Material del suelo: `.` piedra, `w` madera, `m` metal, `~` agua y `W` agua
+///   profunda. Sin esta capa todo el suelo es de piedra.
+/// * `[scale]` - Una sola línea con los metros que mide una celda. Sin esta sección cada
+///   celda mide 1 metro. Las paredes son siempre tan altas como ancha es una celda.
+/// * `[portals]` - Un portal por línea: `x y cara x y cara`, dos caras de pared conectadas.
+///   La cara es `N`, `E`, `S` u `W` de la celda de pared (`x`, `y`), y la celda frente a ella
+///   debe estar vacía. Lo que entra por una cara sale por la otra.
+/// * `[walls]` - Colores de un tipo de pared por línea: `tipo RRGGBB`, con el dígito de la
+///   pared y el color de todas sus caras, seguidos si hace falta de `cara=RRGGBB` (`N`, `E`,
+///   `S` u `W`) para las caras que llevan otro color. Los tipos sin línea usan los colores
+///   de la paleta.
+///
+/// El análisis es tolerante: los caracteres desconocidos y las filas cortas se
+/// convierten en espacio vacío y se reportan como problemas.
+///
+/// # Returns
+/// * `Result<(Map, Vec<Issue>), String>` - El mapa resultante y los problemas encontrados al
+///   leerlo, o un error si la rejilla es más grande que `MAX_MAP_SIZE` (se rechaza antes de
+///   reservar memoria para ella).
+pub fn parse_map(text: &str) -> Result<(Map, Vec<Issue>), String> {
+    let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+
+    // La rejilla principal termina en la primera línea vacía o encabezado de capa
+    let grid_end = lines
+        .iter()
+        .position(|line| line.is_empty() || line.starts_with('['))
+        .unwrap_or(lines.len());
+    let height = grid_end;
+    let width = lines.first().filter(|_| height > 0).map_or(0, |line| line.chars().count());
+    if width > MAX_MAP_SIZE || height > MAX_MAP_SIZE {
+        return Err(format!(
+            "map is {}x{}, supported sizes are up to {}x{}",
+            width, height, MAX_MAP_SIZE, MAX_MAP_SIZE
+        ));
+    }
+    // De las filas más largas que la primera solo se guardan las celdas que caben
+    let rows: Vec<Vec<char>> = lines[..grid_end].iter().map(|line| line.chars().take(width).collect()).collect();
+
+    let mut map = Map::new(width, height)?;
+    let mut issues = Vec::new();
+
+    for (y, row) in rows.iter().enumerate() {
+        let cells = lines[y].chars().count();
+        if cells != width {
+            issues.push(Issue::at_cell(cells.min(width), y, format!("row has {} cells, expected {}", cells, width)));
+        }
+
+        for (x, &ch) in row.iter().enumerate() {
+            match ch {
+                '0' => {}
+                '1'..='9' => {
+                    let i = map.index(x, y);
+                    map.cells[i] = ch as u8 - b'0';
+                }
+                '-' | '|' | '=' | ':' => {
+                    let i = map.index(x, y);
+                    map.thin_walls[i] = Some(ThinWall { runs_east_west: matches!(ch, '-' | '='), fence: matches!(ch, '=' | ':') });
+                }
+                'P' => {
+                    if map.spawn.is_some() {
+                        issues.push(Issue::at_cell(x, y, "duplicate spawn 'P'".to_string()));
+                    } else {
+                        map.spawn = Some((x, y));
+                    }
+                }
+                _ => issues.push(Issue::at_cell(x, y, format!("unknown cell '{}'", ch))),
+            }
+        }
+    }
+
+    // Capas adicionales: un encabezado "[nombre]" seguido de sus filas
+    let mut i = grid_end;
+    while i < lines.len() {
+        let header = lines[i];
+        i += 1;
+        if header.is_empty() {
+            continue;
+        }
+
+        let first_row = i;
+        while i < lines.len() && !lines[i].is_empty() && !lines[i].starts_with('[') {
+            i += 1;
+        }
+        let layer = &lines[first_row..i];
+        let map_size = (map.width, map.height);
+
+        match header {
+            "[outdoor]" => {
+                // Con la capa presente, solo las celdas marcadas con 'O' son exteriores
+                map.outdoor.fill(false);
+                parse_layer(map_size, "outdoor", layer, first_row, &mut issues, |x, y, ch| match ch {
+                    'O' => {
+                        let i = map.index(x, y);
+                        map.outdoor[i] = true;
+                        true
+                    }
+                    '.' => true,
+                    _ => false,
+                });
+            }
+            "[floor]" => parse_layer(map_size, "floor", layer, first_row, &mut issues, |x, y, ch| {
+                let material = match ch {
+                    '.' => FloorMaterial::Stone,
+                    'w' => FloorMaterial::Wood,
+                    'm' => FloorMaterial::Metal,
+                    '~' => FloorMaterial::Water,
+                    'W' => FloorMaterial::DeepWater,
+                    _ => return false,
+                };
+                let i = map.index(x, y);
+                map.floor[i] = material;
+                true
+            }),
+            "[scale]" => match layer.first().and_then(|value| value.trim().parse::<f64>().ok()) {
+                Some(scale) if layer.len() == 1 && scale.is_finite() && scale > 0.0 => map.scale = scale,
+                _ => issues.push(Issue::new(first_row, 1, "scale section must be one positive number".to_string())),
+            },
+            "[walls]" => {
+                for (j, line) in layer.iter().enumerate() {
+                    if let Err(message) = parse_wall_type(&mut map, line) {
+                        issues.push(Issue::new(first_row + j + 1, 1, message));
+                    }
+                }
+            }
+            "[portals]" => {
+                for (j, line) in layer.iter().enumerate() {
+                    match parse_portal(&map, line) {
+                        Ok(portal) => map.portals.push(portal),
+                        Err(message) => issues.push(Issue::new(first_row + j + 1, 1, message)),
+                    }
+                }
+            }
+            _ => issues.push(Issue::new(first_row, 1, format!("unknown section '{}'", header))),
+        }
+    }
+
+    Ok((map, issues))
+}
+
+/// Lee una línea de la sección `[walls]`: `tipo RRGGBB [cara=RRGGBB]...`.
+fn parse_wall_type(map: &mut Map, line: &str) -> Result<(), String> {
+    let mut fields = line.split_whitespace();
+    let digit = fields.next().unwrap_or_default();
+    let index = match digit.parse::<usize>() {
+        Ok(cell @ 1..=9) => cell - 1,
+        _ => return Err(format!("wall type must be 1 to 9, got '{}'", digit)),
+    };
+    if map.wall_types[index].is_some() {
+        return Err(format!("wall type {} is defined twice", digit));
+    }
+
+    let default = parse_hex_color(fields.next().ok_or_else(|| "wall type must be 'type RRGGBB [side=RRGGBB]...'".to_string())?)?;
+    let mut faces = [default; 4];
+    for field in fields {
+        let invalid = || format!("invalid face color '{}', expected N, E, S or W=RRGGBB", field);
+        let (side, color) = field.split_once('=').ok_or_else(invalid)?;
+        let mut chars = side.chars();
+        let side = match (chars.next().and_then(Side::from_char), chars.next()) {
+            (Some(side), None) => side,
+            _ => return Err(invalid()),
+        };
+        faces[side.index()] = parse_hex_color(color)?;
+    }
+    map.wall_types[index] = Some(WallType { faces });
+    Ok(())
+}
+
+/// Lee un color escrito como `RRGGBB` (con `#` delante opcional).
+fn parse_hex_color(text: &str) -> Result<Color, String> {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    if hex.len() != 6 {
+        return Err(format!("invalid color '{}', expected RRGGBB", text));
+    }
+    u32::from_str_radix(hex, 16)
+        .map(Color::from_u32)
+        .map_err(|_| format!("invalid color '{}', expected RRGGBB", text))
+}
+
+/// Lee una línea de la sección `[portals]`: `x y cara x y cara`.
+fn parse_portal(map: &Map, line: &str) -> Result<Portal, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() != 6 {
+        return Err("portal must be 'x y side x y side'".to_string());
+    }
+    let a = parse_portal_face(map, &fields[..3])?;
+    let b = parse_portal_face(map, &fields[3..])?;
+    let used = |face: PortalFace| map.portals.iter().any(|portal| portal.a == face || portal.b == face);
+    if a == b || used(a) || used(b) {
+        return Err("a wall face can only belong to one portal".to_string());
+    }
+    Ok(Portal { a, b })
+}
+
+/// Lee una cara de portal (`x y cara`) y comprueba que sea la cara de una pared frente a una celda vacía.
+fn parse_portal_face(map: &Map, fields: &[&str]) -> Result<PortalFace, String> {
+    let coordinate = |text: &str| text.parse::<usize>().map_err(|_| format!("invalid portal coordinate '{}'", text));
+    let (x, y) = (coordinate(fields[0])?, coordinate(fields[1])?);
+    let mut chars = fields[2].chars();
+    let side = match (chars.next().and_then(Side::from_char), chars.next()) {
+        (Some(side), None) => side,
+        _ => return Err(format!("invalid portal side '{}', expected N, E, S or W", fields[2])),
+    };
+
+    if x >= map.width || y >= map.height || map.cell(x, y) == 0 {
+        return Err(format!("portal cell {} {} is not a wall", x, y));
+    }
+    let face = PortalFace { x, y, side };
+    let (open_x, open_y) = face.open_cell();
+    let open = open_x >= 0
+        && open_y >= 0
+        && (open_x as usize) < map.width
+        && (open_y as usize) < map.height
+        && map.cell(open_x as usize, open_y as usize) == 0;
+    if !open {
+        return Err(format!("portal face {} {} {} does not face an empty cell", x, y, fields[2]));
+    }
+    Ok(face)
+}
+
+/// Recorre las filas de una capa y llama a `set(x, y, carácter)` por cada celda. `set` devuelve
+/// `false` si el carácter no es válido para la capa. `first_row` es el índice (desde 0) de la
+/// línea de la primera fila.
+fn parse_layer(
+    map_size: (usize, usize),
+    name: &str,
+    rows: &[&str],
+    first_row: usize,
+    issues: &mut Vec<Issue>,
+    mut set: impl FnMut(usize, usize, char) -> bool,
+) {
+    let (width, height) = map_size;
+    if rows.len() != height {
+        issues.push(Issue::new(
+            first_row,
+            1,
+            format!("{} layer has {} rows, expected {}", name, rows.len(), height),
+        ));
+    }
+
+    for (y, row) in rows.iter().enumerate().take(height) {
+        let line = first_row + y + 1;
+        // Como en la rejilla principal, las filas de otro ancho se reportan y las celdas que
+        // faltan conservan el valor por defecto de la capa
+        let cells = row.chars().count();
+        if cells != width {
+            issues.push(Issue::new(
+                line,
+                cells.min(width) + 1,
+                format!("{} layer row has {} cells, expected {}", name, cells, width),
+            ));
+        }
+        for (x, ch) in row.chars().enumerate().take(width) {
+            if !set(x, y, ch) {
+                issues.push(Issue::new(line, x + 1, format!("unknown {} cell '{}'", name, ch)));
+            }
+        }
+    }
+}
+
+/// Revisa que el mapa se pueda jugar: dimensiones soportadas, borde cerrado por paredes
+/// y una posición inicial declarada sobre una celda libre. Todos sus problemas son errores.
+pub fn validate(map: &Map) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    if map.width == 0 || map.height == 0 || map.width > MAX_MAP_SIZE || map.height > MAX_MAP_SIZE {
+        issues.push(Issue::at_cell(
+            0,
+            0,
+            format!(
+                "map is {}x{}, supported sizes are 1x1 to {}x{}",
+                map.width, map.height, MAX_MAP_SIZE, MAX_MAP_SIZE
+            ),
+        )
+        .into_error());
+        return issues;
+    }
+
+    // El borde debe ser de paredes para que los rayos siempre encuentren una
+    for y in 0..map.height {
+        for x in 0..map.width {
+            let on_border = x == 0 || y == 0 || x == map.width - 1 || y == map.height - 1;
+            if on_border && map.cell(x, y) == 0 {
+                issues.push(Issue::at_cell(x, y, "border is not sealed".to_string()).into_error());
+            }
+        }
+    }
+
+    match map.spawn {
+        None => issues.push(Issue::at_cell(0, 0, "no spawn 'P' found".to_string()).into_error()),
+        Some((x, y)) if map.cell(x, y) > 0 => {
+            issues.push(Issue::at_cell(x, y, "spawn is inside a wall".to_string()).into_error())
+        }
+        Some(_) => {}
+    }
+
+    issues
+}
+
+/// Carga un mapa desde un archivo de texto o una imagen `.png` (ver `image_to_text`) y lo valida.
+///
+/// # Returns
+/// * `io::Result<(Map, Vec<Issue>)>` - El mapa junto con todos los problemas de lectura y validación.
+pub fn load_map(path: &str) -> io::Result<(Map, Vec<Issue>)> {
+    let is_image = Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+    let mut image_issues = Vec::new();
+    let text = if is_image {
+        image_to_text(Path::new(path), &mut image_issues)?
+    } else {
+        fs::read_to_string(path)?
+    };
+    let (map, mut issues) = parse_map(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    issues.splice(0..0, image_issues);
+    issues.extend(validate(&map));
+    Ok((map, issues))
+}
+
+/// Colores de la imagen de un mapa y la celda que representan en el formato de texto.
+const IMAGE_LEGEND: [(u32, char); 3] = [
+    (0x000000, '1'), // Negro: pared
+    (0xFFFFFF, '0'), // Blanco: espacio vacío
+    (0xFF0000, 'P'), // Rojo: posición inicial del jugador
+];
+
+/// Colores de la imagen opcional del suelo y el material que representan.
+const FLOOR_LEGEND: [(u32, char); 5] = [
+    (0x808080, '.'), // Gris: piedra
+    (0x8B5A2B, 'w'), // Marrón: madera
+    (0xC0C0C0, 'm'), // Gris claro: metal
+    (0x3A6EA5, '~'), // Azul: agua
+    (0x1F3F66, 'W'), // Azul oscuro: agua profunda
+];
+
+/// Convierte una imagen en un mapa en formato de texto: cada píxel es una celda, según
+/// `IMAGE_LEGEND`. Si junto a `mapa.png` hay un `mapa.floor.png` del mismo tamaño, se añade
+/// como capa `[floor]` según `FLOOR_LEGEND`. Los colores que no están en la leyenda se
+/// cambian por el más parecido y se reportan en `issues`, una vez por color.
+fn image_to_text(path: &Path, issues: &mut Vec<Issue>) -> io::Result<String> {
+    let (rows, size) = image_rows(path, &IMAGE_LEGEND, "map", issues)?;
+    let mut text = rows.join("\n");
+
+    let floor_path = path.with_extension("floor.png");
+    if floor_path.exists() {
+        let (floor_rows, floor_size) = image_rows(&floor_path, &FLOOR_LEGEND, "floor", issues)?;
+        if floor_size == size {
+            text.push_str("\n\n[floor]\n");
+            text.push_str(&floor_rows.join("\n"));
+        } else {
+            issues.push(Issue::at_cell(
+                0,
+                0,
+                format!("floor image is {}x{}, expected {}x{}; ignoring it", floor_size.0, floor_size.1, size.0, size.1),
+            ));
+        }
+    }
+    Ok(text)
+}
+
+/// Lee una imagen y devuelve sus filas como caracteres de `legend`, junto con su tamaño.
+/// Las imágenes más grandes que `MAX_MAP_SIZE` se rechazan antes de decodificarlas.
+fn image_rows(path: &Path, legend: &[(u32, char)], name: &str, issues: &mut Vec<Issue>) -> io::Result<(Vec<String>, (usize, usize))> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    let (width, height) = image::image_dimensions(path).map_err(|err| invalid(err.to_string()))?;
+    let (width, height) = (width as usize, height as usize);
+    if width > MAX_MAP_SIZE || height > MAX_MAP_SIZE {
+        return Err(invalid(format!(
+            "{} image is {}x{}, supported sizes are up to {}x{}",
+            name, width, height, MAX_MAP_SIZE, MAX_MAP_SIZE
+        )));
+    }
+    let image = image::open(path).map_err(|err| invalid(err.to_string()))?.to_rgb8();
+
+    // Colores fuera de la leyenda: primera celda donde aparece, cuántas veces y a qué se cambió
+    let mut unknown: Vec<(u32, (usize, usize), usize, u32)> = Vec::new();
+    let mut rows = Vec::with_capacity(height);
+    for y in 0..height {
+        let mut row = String::with_capacity(width);
+        for x in 0..width {
+            let [r, g, b] = image.get_pixel(x as u32, y as u32).0;
+            let color = Color::from_rgb(r, g, b);
+            let &(legend_color, ch) = legend
+                .iter()
+                .min_by_key(|(legend_color, _)| color_distance(color, Color::from_u32(*legend_color)))
+                .unwrap();
+            if legend_color != color.to_u32() {
+                match unknown.iter_mut().find(|entry| entry.0 == color.to_u32()) {
+                    Some(entry) => entry.2 += 1,
+                    None => unknown.push((color.to_u32(), (x, y), 1, legend_color)),
+                }
+            }
+            row.push(ch);
+        }
+        rows.push(row);
+    }
+
+    for (color, (x, y), count, legend_color) in unknown {
+        issues.push(Issue::at_cell(
+            x,
+            y,
+            format!("{} color #{:06X} is not in the legend, used #{:06X} for {} pixel(s)", name, color, legend_color, count),
+        ));
+    }
+    Ok((rows, (width, height)))
+}
+
+/// Distancia al cuadrado entre dos colores, canal por canal.
+fn color_distance(a: Color, b: Color) -> u32 {
+    let channel = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    channel(a.r(), b.r()) + channel(a.g(), b.g()) + channel(a.b(), b.b())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn map_new_rejects_cell_count_overflow() {
+        assert!(Map::new(usize::MAX, 2).is_err());
+        assert!(Map::new(1 << 16, (1 << 16) + 1).is_err());
+    }
+
+    #[test]
+    fn map_new_accepts_regular_size() {
+        let map = Map::new(3, 2).unwrap();
+        assert_eq!((map.width, map.height), (3, 2));
+        assert!(!map.is_wall(1.5, 1.5));
+    }
+
+    #[test]
+    fn parse_map_rejects_too_wide_grid() {
+        let text = format!("{}\n", "1".repeat(MAX_MAP_SIZE + 1));
+        assert!(parse_map(&text).is_err());
+    }
+
+    #[test]
+    fn parse_map_rejects_too_tall_grid() {
+        let text = "1\n".repeat(MAX_MAP_SIZE + 1);
+        assert!(parse_map(&text).is_err());
+    }
+
+    #[test]
+    fn parse_map_accepts_largest_grid() {
+        let row = "1".repeat(MAX_MAP_SIZE);
+        let text = format!("{}\n", row).repeat(MAX_MAP_SIZE);
+        let (map, issues) = parse_map(&text).unwrap();
+        assert_eq!((map.width, map.height), (MAX_MAP_SIZE, MAX_MAP_SIZE));
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn parse_map_reports_long_row_length() {
+        let (_, issues) = parse_map("111\n11111\n111\n").unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].message, "row has 5 cells, expected 3");
+    }
+
+    /// Mensajes de los errores de validación del mapa escrito en texto.
+    fn validation_errors(text: &str) -> Vec<String> {
+        let (map, _) = parse_map(text).unwrap();
+        let issues = validate(&map);
+        assert!(issues.iter().all(|issue| issue.error));
+        issues.into_iter().map(|issue| issue.message).collect()
+    }
+
+    #[test]
+    fn validate_accepts_playable_map() {
+        assert!(validation_errors("111\n1P1\n111\n").is_empty());
+    }
+
+    #[test]
+    fn validate_rejects_empty_map() {
+        let errors = validation_errors("");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("map is 0x0"));
+    }
+
+    #[test]
+    fn validate_rejects_open_border() {
+        assert_eq!(validation_errors("111\n1P0\n111\n"), ["border is not sealed"]);
+    }
+
+    #[test]
+    fn validate_rejects_missing_spawn() {
+        assert_eq!(validation_errors("111\n101\n111\n"), ["no spawn 'P' found"]);
+    }
+
+    #[test]
+    fn validate_rejects_spawn_inside_wall() {
+        let (mut map, _) = parse_map("111\n1P1\n111\n").unwrap();
+        map.set_wall(1, 1);
+        let issues = validate(&map);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].error);
+        assert_eq!(issues[0].message, "spawn is inside a wall");
+    }
+
+    #[test]
+    fn parse_issues_are_warnings() {
+        let (map, issues) = parse_map("111\n1P1x\n111\n").unwrap();
+        assert!(!issues.is_empty());
+        assert!(!has_errors(&issues));
+        assert!(validate(&map).is_empty());
+    }
+
+    /// Sala de 5x4 con una pared delgada de norte a sur en la celda (2, 1), en x = 2.5.
+    fn thin_wall_room() -> Map {
+        parse_map("11111\n1P|01\n10001\n11111\n").unwrap().0
+    }
+
+    #[test]
+    fn thin_wall_blocks_crossing_inside_its_cell() {
+        let map = thin_wall_room();
+        assert!(map.thin_wall_blocks((2.3, 1.5), (2.7, 1.5), 0.1));
+        assert!(map.thin_wall_blocks((2.7, 1.5), (2.3, 1.5), 0.1));
+    }
+
+    #[test]
+    fn thin_wall_blocks_diagonal_crossing_from_neighbour_cell() {
+        // Empieza en (1, 2) y termina en (2, 1) lejos del plano, pero lo cruza en y = 1.45
+        let map = thin_wall_room();
+        assert!(map.thin_wall_blocks((1.9, 2.05), (2.7, 1.25), 0.1));
+    }
+
+    #[test]
+    fn thin_wall_blocks_move_skipping_its_cell() {
+        let map = thin_wall_room();
+        assert!(map.thin_wall_blocks((1.9, 1.5), (3.1, 1.5), 0.1));
+    }
+
+    #[test]
+    fn thin_wall_allows_crossing_beside_its_cell() {
+        // Cruza x = 2.5 en la fila 2, donde no hay pared
+        let map = thin_wall_room();
+        assert!(!map.thin_wall_blocks((1.9, 2.9), (2.7, 2.1), 0.1));
+        assert!(!map.thin_wall_blocks((2.1, 1.5), (2.3, 1.5), 0.1));
+    }
+
+    #[test]
+    fn thin_wall_keeps_margin() {
+        let map = thin_wall_room();
+        assert!(map.thin_wall_blocks((2.3, 1.5), (2.45, 1.5), 0.1));
+        // Alejarse del plano siempre se permite
+        assert!(!map.thin_wall_blocks((2.45, 1.5), (2.3, 1.5), 0.1));
+    }
+
+    /// Guarda una imagen cuyas filas son los colores 0xRRGGBB de `pixels`.
+    fn write_png(path: &Path, pixels: &[&[u32]]) {
+        let image = image::RgbImage::from_fn(pixels[0].len() as u32, pixels.len() as u32, |x, y| {
+            let color = Color::from_u32(pixels[y as usize][x as usize]);
+            image::Rgb([color.r(), color.g(), color.b()])
+        });
+        image.save(path).unwrap();
+    }
+
+    #[test]
+    fn image_legend_colors_become_cells() {
+        let dir = TestDir::new("map", "legend");
+        let path = dir.join("map.png");
+        write_png(&path, &[&[0x000000, 0x000000, 0x000000], &[0x000000, 0xFF0000, 0xFFFFFF], &[0x000000, 0x000000, 0x000000]]);
+
+        let mut issues = Vec::new();
+        assert_eq!(image_to_text(&path, &mut issues).unwrap(), "111\n1P0\n111");
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn floor_legend_colors_become_layer() {
+        let dir = TestDir::new("map", "floor");
+        let path = dir.join("map.png");
+        write_png(&path, &[&[0xFF0000, 0xFFFFFF, 0xFFFFFF, 0xFFFFFF, 0xFFFFFF]]);
+        write_png(&dir.join("map.floor.png"), &[&[0x808080, 0x8B5A2B, 0xC0C0C0, 0x3A6EA5, 0x1F3F66]]);
+
+        let mut issues = Vec::new();
+        assert_eq!(image_to_text(&path, &mut issues).unwrap(), "P0000\n\n[floor]\n.wm~W");
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn unknown_colors_snap_to_nearest_and_are_reported_once() {
+        let dir = TestDir::new("map", "snap");
+        let path = dir.join("map.png");
+        // Gris oscuro se acerca al negro, rosa al blanco y rojo oscuro al rojo
+        write_png(&path, &[&[0x202020, 0xFFF0F0, 0x202020], &[0xC00000, 0xFFFFFF, 0x000000]]);
+
+        let mut issues = Vec::new();
+        assert_eq!(image_to_text(&path, &mut issues).unwrap(), "101\nP01");
+        let messages: Vec<String> = issues.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "1:1: warning: map color #202020 is not in the legend, used #000000 for 2 pixel(s)",
+                "1:2: warning: map color #FFF0F0 is not in the legend, used #FFFFFF for 1 pixel(s)",
+                "2:1: warning: map color #C00000 is not in the legend, used #FF0000 for 1 pixel(s)",
+            ]
+        );
+    }
+
+    #[test]
+    fn mismatched_floor_image_is_ignored() {
+        let dir = TestDir::new("map", "floor-size");
+        let path = dir.join("map.png");
+        write_png(&path, &[&[0xFF0000, 0xFFFFFF]]);
+        write_png(&dir.join("map.floor.png"), &[&[0x808080]]);
+
+        let mut issues = Vec::new();
+        assert_eq!(image_to_text(&path, &mut issues).unwrap(), "P0");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].message, "floor image is 1x1, expected 2x1; ignoring it");
+    }
+
+    #[test]
+    fn layer_rows_of_wrong_width_are_reported() {
+        let (map, issues) = parse_map("1111\n1P01\n1111\n\n[outdoor]\n....\n.O\n....O\n").unwrap();
+        let messages: Vec<String> = issues.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "7:3: warning: outdoor layer row has 2 cells, expected 4",
+                "8:5: warning: outdoor layer row has 5 cells, expected 4",
+            ]
+        );
+        // Las celdas que faltan quedan interiores, el valor por defecto con la capa presente
+        assert!(map.is_outdoor(1.5, 1.5));
+        assert!(!map.is_outdoor(2.5, 1.5));
+    }
+
+    /// Índices de material del suelo de la fila `y`.
+    fn floor_row(map: &Map, y: usize) -> Vec<usize> {
+        (0..map.width).map(|x| map.floor_at(x as f64 + 0.5, y as f64 + 0.5).index()).collect()
+    }
+
+    #[test]
+    fn floor_layer_sets_each_material() {
+        let (map, issues) = parse_map("111111\n1P0001\n111111\n\n[floor]\n......\n.wm~W.\n......\n").unwrap();
+        assert!(issues.is_empty());
+        let [stone, wood, metal, water, deep] =
+            [FloorMaterial::Stone, FloorMaterial::Wood, FloorMaterial::Metal, FloorMaterial::Water, FloorMaterial::DeepWater].map(FloorMaterial::index);
+        assert_eq!(floor_row(&map, 1), [stone, wood, metal, water, deep, stone]);
+        assert_eq!(floor_row(&map, 0), [stone; 6]);
+    }
+
+    #[test]
+    fn floor_defaults_to_stone() {
+        let (map, _) = parse_map("111\n1P1\n111\n").unwrap();
+        assert!((0..3).all(|y| floor_row(&map, y) == [FloorMaterial::Stone.index(); 3]));
+        // Fuera del mapa también es piedra
+        assert!(map.floor_at(-1.0, 1.5) == FloorMaterial::Stone);
+        assert!(map.floor_at(1.5, 7.0) == FloorMaterial::Stone);
+    }
+
+    #[test]
+    fn unknown_floor_cells_are_reported_as_stone() {
+        let (map, issues) = parse_map("1111\n1P01\n1111\n\n[floor]\n....\n.x~.\n....\n").unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].to_string(), "7:2: warning: unknown floor cell 'x'");
+        assert_eq!(floor_row(&map, 1), [0, 0, FloorMaterial::Water.index(), 0]);
+    }
+
+    #[test]
+    fn only_water_slows_movement() {
+        assert_eq!(FloorMaterial::Stone.speed_factor(), 1.0);
+        assert_eq!(FloorMaterial::Wood.speed_factor(), 1.0);
+        assert_eq!(FloorMaterial::Metal.speed_factor(), 1.0);
+        assert_eq!(FloorMaterial::Water.speed_factor(), WATER_SPEED_FACTOR);
+        assert_eq!(FloorMaterial::DeepWater.speed_factor(), SWIM_SPEED_FACTOR);
+    }
+
+    #[test]
+    fn wall_faces_override_the_default_colour() {
+        let text = "1111\n1P21\n1111\n\n[walls]\n2 808080 N=FF0000 e=00FF00 S=#0000FF\n";
+        let (map, issues) = parse_map(text).unwrap();
+        assert!(issues.is_empty());
+        assert_eq!(map.wall_color(2, Side::North), Some(Color::from_u32(0xFF0000)));
+        assert_eq!(map.wall_color(2, Side::East), Some(Color::from_u32(0x00FF00)));
+        assert_eq!(map.wall_color(2, Side::South), Some(Color::from_u32(0x0000FF)));
+        assert_eq!(map.wall_color(2, Side::West), Some(Color::from_u32(0x808080)));
+        // Los tipos sin línea usan la paleta
+        assert_eq!(map.wall_color(1, Side::North), None);
+        assert_eq!(map.wall_color(0, Side::North), None);
+    }
+
+    #[test]
+    fn malformed_wall_types_are_reported() {
+        let lines = [
+            "0 808080",
+            "2",
+            "2 80808",
+            "2 808080 X=FF0000",
+            "2 808080 NE=FF0000",
+            "2 808080 N:FF0000",
+            "2 808080 N=red",
+        ];
+        let text = format!("111\n1P1\n111\n\n[walls]\n{}\n", lines.join("\n"));
+        let (map, issues) = parse_map(&text).unwrap();
+        let messages: Vec<String> = issues.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "6:1: warning: wall type must be 1 to 9, got '0'",
+                "7:1: warning: wall type must be 'type RRGGBB [side=RRGGBB]...'",
+                "8:1: warning: invalid color '80808', expected RRGGBB",
+                "9:1: warning: invalid face color 'X=FF0000', expected N, E, S or W=RRGGBB",
+                "10:1: warning: invalid face color 'NE=FF0000', expected N, E, S or W=RRGGBB",
+                "11:1: warning: invalid face color 'N:FF0000', expected N, E, S or W=RRGGBB",
+                "12:1: warning: invalid color 'red', expected RRGGBB",
+            ]
+        );
+        assert_eq!(map.wall_color(2, Side::North), None);
+    }
+
+    #[test]
+    fn wall_type_defined_twice_keeps_the_first() {
+        let (map, issues) = parse_map("111\n1P1\n111\n\n[walls]\n3 FF0000\n3 00FF00\n").unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].message, "wall type 3 is defined twice");
+        assert_eq!(map.wall_color(3, Side::East), Some(Color::from_u32(0xFF0000)));
+    }
+
+    #[test]
+    fn scale_section_sets_meters_per_cell() {
+        let (map, issues) = parse_map("111\n1P1\n111\n\n[scale]\n2.5\n").unwrap();
+        assert!(issues.is_empty());
+        assert_eq!(map.scale, 2.5);
+        assert_eq!(map.to_meters(4.0), 10.0);
+        assert_eq!(map.to_cells(10.0), 4.0);
+        for cells in [0.0, 0.3, 7.25] {
+            assert!((map.to_cells(map.to_meters(cells)) - cells).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn scale_defaults_to_one_meter() {
+        let (map, _) = parse_map("111\n1P1\n111\n").unwrap();
+        assert_eq!(map.scale, 1.0);
+        assert_eq!(map.to_meters(3.5), 3.5);
+        assert_eq!(map.to_cells(3.5), 3.5);
+    }
+
+    #[test]
+    fn invalid_scale_is_reported_and_ignored() {
+        for scale in ["0", "-2", "nan", "inf", "big", "1\n2"] {
+            let (map, issues) = parse_map(&format!("111\n1P1\n111\n\n[scale]\n{}\n", scale)).unwrap();
+            assert_eq!(map.scale, 1.0, "{}", scale);
+            let messages: Vec<String> = issues.iter().map(ToString::to_string).collect();
+            assert_eq!(messages, ["5:1: warning: scale section must be one positive number"], "{}", scale);
+        }
+    }
+
+    #[test]
+    fn missing_layer_rows_are_reported() {
+        let (_, issues) = parse_map("111\n1P1\n111\n\n[floor]\n...\n").unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].message, "floor layer has 1 rows, expected 3");
+    }
+}
@@ -0,0 +1,113 @@
+pub const MAX_MARKERS: usize = 32; // Marcadores como máximo en un nivel
+pub const MARKER_KINDS: usize = 4; // Tipos de marcador, uno por color (teclas 1 a 4)
+
+/// Marca que el jugador deja en una celda del automapa.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Marker {
+    pub x: usize,
+    pub y: usize,
+    pub kind: usize, // 0 a MARKER_KINDS - 1, elige el color
+}
+
+/// Marcadores del nivel actual.
+#[derive(Default)]
+pub struct Markers {
+    list: Vec<Marker>,
+}
+
+impl Markers {
+    /// Pone un marcador en la celda (`x`, `y`). Si la celda ya tenía uno, se cambia su tipo.
+    /// Devuelve `false` si ya hay `MAX_MARKERS` y no se pudo añadir.
+    pub fn place(&mut self, x: usize, y: usize, kind: usize) -> bool {
+        if let Some(marker) = self.list.iter_mut().find(|marker| (marker.x, marker.y) == (x, y)) {
+            marker.kind = kind;
+            return true;
+        }
+        if self.list.len() == MAX_MARKERS {
+            return false;
+        }
+        self.list.push(Marker { x, y, kind });
+        true
+    }
+
+    /// Quita el marcador más cercano al punto (`x`, `y`), medido al centro de su celda.
+    /// Devuelve `false` si no había ninguno.
+    pub fn remove_nearest(&mut self, x: f64, y: f64) -> bool {
+        let distance = |marker: &Marker| (marker.x as f64 + 0.5 - x).powi(2) + (marker.y as f64 + 0.5 - y).powi(2);
+        let nearest = self
+            .list
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+            .map(|(i, _)| i);
+        match nearest {
+            Some(i) => {
+                self.list.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Quita todos los marcadores (al cambiar de nivel).
+    pub fn clear(&mut self) {
+        self.list.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Marker> {
+        self.list.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(markers: &Markers) -> Vec<(usize, usize, usize)> {
+        markers.iter().map(|marker| (marker.x, marker.y, marker.kind)).collect()
+    }
+
+    #[test]
+    fn placing_on_a_marked_cell_changes_its_kind() {
+        let mut markers = Markers::default();
+        assert!(markers.place(2, 3, 0));
+        assert!(markers.place(5, 1, 1));
+        assert!(markers.place(2, 3, 3));
+        assert_eq!(cells(&markers), [(2, 3, 3), (5, 1, 1)]);
+    }
+
+    #[test]
+    fn placing_stops_at_the_limit() {
+        let mut markers = Markers::default();
+        for x in 0..MAX_MARKERS {
+            assert!(markers.place(x, 0, x % MARKER_KINDS));
+        }
+        assert!(!markers.place(0, 1, 0));
+        assert_eq!(markers.iter().count(), MAX_MARKERS);
+        // Cambiar el tipo de uno que ya existe sigue funcionando con la lista llena
+        assert!(markers.place(4, 0, 2));
+        assert_eq!(markers.iter().find(|marker| marker.x == 4).unwrap().kind, 2);
+    }
+
+    #[test]
+    fn removal_picks_the_nearest_cell_centre() {
+        let mut markers = Markers::default();
+        markers.place(1, 1, 0);
+        markers.place(4, 1, 1);
+        markers.place(4, 6, 2);
+        // (3.2, 2.0) queda más cerca del centro de (4, 1) que del de (1, 1)
+        assert!(markers.remove_nearest(3.2, 2.0));
+        assert_eq!(cells(&markers), [(1, 1, 0), (4, 6, 2)]);
+        assert!(markers.remove_nearest(100.0, 100.0));
+        assert_eq!(cells(&markers), [(1, 1, 0)]);
+    }
+
+    #[test]
+    fn removal_from_an_empty_list_fails() {
+        let mut markers = Markers::default();
+        assert!(!markers.remove_nearest(1.0, 1.0));
+        markers.place(0, 0, 0);
+        markers.clear();
+        assert!(!markers.remove_nearest(0.5, 0.5));
+    }
+}
@@ -0,0 +1,453 @@
+use rodio::buffer::SamplesBuffer;
+use rodio::{Decoder, OutputStreamHandle, Sink, Source};
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+const CROSSFADE: f64 = 3.0; // Segundos que dura el fundido entre dos pistas
+const PRELOAD: f64 = 10.0;  // Segundos antes del fundido en que se empieza a decodificar la siguiente pista
+const EXTENSIONS: [&str; 4] = ["wav", "mp3", "ogg", "flac"];
+
+/// Función que decodifica una pista completa; las pruebas la cambian por una que no lee el disco.
+type DecodeFn = fn(&Path) -> Result<SamplesBuffer<i16>, String>;
+
+/// Decodifica un archivo de música en un hilo aparte para que la ventana se abra sin esperar.
+/// El bucle principal consulta `poll` cada cuadro y conecta la música al sink cuando está lista.
+pub struct MusicLoader {
+    receiver: Receiver<Result<SamplesBuffer<i16>, String>>,
+}
+
+impl MusicLoader {
+    /// Empieza a decodificar `path` con `decode` en segundo plano.
+    pub fn start(path: &Path, decode: DecodeFn) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let path = path.to_path_buf();
+        thread::spawn(move || {
+            // Si el juego ya terminó nadie recibe el resultado, y no pasa nada
+            let _ = sender.send(decode(&path).map_err(|err| format!("{}: {}", path.display(), err)));
+        });
+        Self { receiver }
+    }
+
+    /// Resultado de la decodificación si ya terminó, o `None` si sigue en curso.
+    pub fn poll(&self) -> Option<Result<SamplesBuffer<i16>, String>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("music decoder stopped unexpectedly".to_string())),
+        }
+    }
+}
+
+/// Decodifica el archivo completo en memoria, así se repite sin volver a leer el disco.
+fn decode(path: &Path) -> Result<SamplesBuffer<i16>, String> {
+    let file = File::open(path).map_err(|err| err.to_string())?;
+    let source = Decoder::new(BufReader::new(file)).map_err(|err| err.to_string())?;
+    let (channels, sample_rate) = (source.channels(), source.sample_rate());
+    Ok(SamplesBuffer::new(channels, sample_rate, source.collect::<Vec<i16>>()))
+}
+
+/// Lista de pistas que se reproducen en orden, volviendo a la primera después de la última.
+pub struct Playlist {
+    tracks: Vec<PathBuf>,
+    current: usize,
+}
+
+impl Playlist {
+    /// Lista con una sola pista, que se repite sin fin.
+    pub fn single(path: &Path) -> Self {
+        Self { tracks: vec![path.to_path_buf()], current: 0 }
+    }
+
+    /// Todas las pistas de audio de un directorio, ordenadas por nombre de archivo.
+    pub fn from_dir(dir: &Path) -> io::Result<Self> {
+        let mut tracks: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| EXTENSIONS.iter().any(|known| known.eq_ignore_ascii_case(ext)))
+            })
+            .collect();
+        if tracks.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no audio files in directory"));
+        }
+        tracks.sort();
+        Ok(Self { tracks, current: 0 })
+    }
+
+    /// Mezcla las pistas con un orden que solo depende de `seed`, así una misma semilla repite
+    /// siempre la misma lista. Vuelve a empezar por la primera del nuevo orden.
+    pub fn shuffle(&mut self, seed: u64) {
+        let mut rng = XorShift::new(seed);
+        // Fisher-Yates
+        for i in (1..self.tracks.len()).rev() {
+            let j = (rng.next() % (i as u64 + 1)) as usize;
+            self.tracks.swap(i, j);
+        }
+        self.current = 0;
+    }
+
+    fn current(&self) -> &Path {
+        &self.tracks[self.current]
+    }
+
+    fn advance(&mut self) {
+        self.current = (self.current + 1) % self.tracks.len();
+    }
+}
+
+/// Generador xorshift64: no sirve para nada serio, pero es determinista y no necesita crates.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // El estado 0 se queda en 0 para siempre
+        Self(if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed })
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// Nombre de una pista para mostrarlo: el nombre del archivo sin la extensión.
+fn track_name(path: &Path) -> String {
+    path.file_stem().map_or_else(|| path.display().to_string(), |stem| stem.to_string_lossy().into_owned())
+}
+
+/// Reproduce una lista de pistas con fundido cruzado entre ellas. Cada pista suena en su propio
+/// sink: durante el fundido la nueva sube mientras la anterior baja, y después la anterior se
+/// descarta. Con una sola pista, esta se repite sin fundidos.
+pub struct MusicPlayer {
+    output: Option<OutputStreamHandle>,   // None: las pistas van a un sink sin salida (pruebas)
+    decode: DecodeFn,
+    playlist: Playlist,
+    volume: f64,
+    loader: Option<MusicLoader>,          // Siguiente pista, mientras se decodifica
+    ready: Option<SamplesBuffer<i16>>,    // Siguiente pista decodificada, esperando a que toque
+    current: Option<Sink>,
+    fading_out: Option<Sink>,             // Pista anterior durante el fundido
+    fade: Option<f64>,                    // Segundos desde que empezó el fundido
+    elapsed: f64,                         // Segundos que lleva sonando la pista actual
+    duration: Option<f64>,                // Duración de la pista actual; None si se repite sin fin
+    failures: usize,                      // Pistas seguidas que no se pudieron decodificar
+}
+
+impl MusicPlayer {
+    /// Empieza a decodificar la primera pista; sonará en cuanto esté lista.
+    pub fn new(stream_handle: OutputStreamHandle, playlist: Playlist, volume: f64) -> Self {
+        Self::with_output(Some(stream_handle), decode, playlist, volume)
+    }
+
+    fn with_output(output: Option<OutputStreamHandle>, decode: DecodeFn, playlist: Playlist, volume: f64) -> Self {
+        let loader = Some(MusicLoader::start(playlist.current(), decode));
+        Self {
+            output,
+            decode,
+            playlist,
+            volume,
+            loader,
+            ready: None,
+            current: None,
+            fading_out: None,
+            fade: None,
+            elapsed: 0.0,
+            duration: None,
+            failures: 0,
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: f64) {
+        self.volume = volume;
+        self.apply_volume();
+    }
+
+    /// Pasa a la siguiente pista (con fundido) en cuanto esté decodificada.
+    pub fn skip(&mut self) {
+        if self.playlist.tracks.len() < 2 {
+            return;
+        }
+        if self.loader.is_none() && self.ready.is_none() {
+            self.load_next();
+        }
+        // Lo que ya se está cargando empieza apenas termine
+        self.duration = Some(self.elapsed);
+    }
+
+    /// Avanza `dt` segundos de reproducción. Devuelve el nombre de la pista si una empezó a sonar.
+    pub fn update(&mut self, dt: f64) -> Option<String> {
+        self.elapsed += dt;
+        if let Some(fade) = &mut self.fade {
+            *fade += dt;
+            if *fade >= CROSSFADE {
+                self.fade = None;
+                self.fading_out = None;
+            }
+            self.apply_volume();
+        }
+
+        // Con varias pistas, la siguiente se decodifica un poco antes de que haga falta
+        let remaining = self.duration.map_or(f64::INFINITY, |duration| duration - self.elapsed);
+        if remaining <= CROSSFADE + PRELOAD && self.loader.is_none() && self.ready.is_none() {
+            self.load_next();
+        }
+
+        if let Some(result) = self.loader.as_ref().and_then(MusicLoader::poll) {
+            self.loader = None;
+            match result {
+                Ok(music) => {
+                    self.failures = 0;
+                    self.ready = Some(music);
+                }
+                Err(err) => {
+                    eprintln!("warning: {}", err);
+                    // Se prueba la siguiente, salvo que ya hayan fallado todas
+                    self.failures += 1;
+                    if self.failures < self.playlist.tracks.len() {
+                        self.load_next();
+                    }
+                }
+            }
+        }
+
+        // La pista lista empieza al llegar al fundido, o de inmediato si no suena nada
+        if self.ready.is_some() && (self.current.is_none() || remaining <= CROSSFADE) {
+            let music = self.ready.take().unwrap();
+            return self.start_track(music);
+        }
+        None
+    }
+
+    /// Pasa a la siguiente pista de la lista y empieza a decodificarla.
+    fn load_next(&mut self) {
+        self.playlist.advance();
+        self.loader = Some(MusicLoader::start(self.playlist.current(), self.decode));
+    }
+
+    fn start_track(&mut self, music: SamplesBuffer<i16>) -> Option<String> {
+        let sink = match &self.output {
+            Some(handle) => Sink::try_new(handle),
+            None => Ok(Sink::new_idle().0),
+        };
+        let sink = match sink {
+            Ok(sink) => sink,
+            Err(err) => {
+                eprintln!("warning: cannot play music: {}", err);
+                return None;
+            }
+        };
+
+        if self.playlist.tracks.len() == 1 {
+            self.duration = None;
+            sink.append(music.repeat_infinite());
+        } else {
+            self.duration = music.total_duration().map(|duration| duration.as_secs_f64());
+            sink.append(music);
+        }
+        self.elapsed = 0.0;
+        self.fading_out = self.current.replace(sink);
+        self.fade = self.fading_out.as_ref().map(|_| 0.0);
+        self.apply_volume();
+        Some(track_name(self.playlist.current()))
+    }
+
+    fn apply_volume(&self) {
+        let t = self.fade.map_or(1.0, |fade| (fade / CROSSFADE).min(1.0));
+        if let Some(sink) = &self.current {
+            sink.set_volume((self.volume * t) as f32);
+        }
+        if let Some(sink) = &self.fading_out {
+            sink.set_volume((self.volume * (1.0 - t)) as f32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Condvar, Mutex};
+    use std::time::{Duration, Instant};
+
+    const SAMPLE_RATE: u32 = 1000;
+    const TRACK_SECONDS: usize = 60;
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    // Pistas que el decodificador de prueba ya puede terminar; hasta entonces se queda esperando
+    static RELEASED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+    static RELEASE: Condvar = Condvar::new();
+
+    /// Decodificador lento: no termina hasta que la prueba libera la pista con `release`.
+    fn slow_decode(path: &Path) -> Result<SamplesBuffer<i16>, String> {
+        let mut released = RELEASED.lock().unwrap();
+        while !released.iter().any(|done| done == path) {
+            released = RELEASE.wait(released).unwrap();
+        }
+        Ok(SamplesBuffer::new(1, SAMPLE_RATE, vec![0; SAMPLE_RATE as usize * TRACK_SECONDS]))
+    }
+
+    fn failing_decode(path: &Path) -> Result<SamplesBuffer<i16>, String> {
+        Err(format!("{}: cannot decode", path.display()))
+    }
+
+    fn release(path: &Path) {
+        RELEASED.lock().unwrap().push(path.to_path_buf());
+        RELEASE.notify_all();
+    }
+
+    /// Pistas con nombres propios de cada prueba, para que no se liberen entre pruebas.
+    fn playlist(test: &str, names: &[&str]) -> Playlist {
+        let tracks = names.iter().map(|name| PathBuf::from(format!("{}-{}.wav", test, name))).collect();
+        Playlist { tracks, current: 0 }
+    }
+
+    fn player(playlist: Playlist, decode: DecodeFn) -> MusicPlayer {
+        MusicPlayer::with_output(None, decode, playlist, 0.5)
+    }
+
+    fn names(playlist: &Playlist) -> Vec<String> {
+        playlist.tracks.iter().map(|track| track_name(track)).collect()
+    }
+
+    #[test]
+    fn shuffle_depends_only_on_the_seed() {
+        let tracks = ["a", "b", "c", "d", "e", "f", "g", "h"];
+        let shuffled = |seed| {
+            let mut list = playlist("shuffle", &tracks);
+            list.shuffle(seed);
+            names(&list)
+        };
+        assert_eq!(shuffled(42), shuffled(42));
+        assert_ne!(shuffled(42), shuffled(43));
+        assert_ne!(shuffled(42), names(&playlist("shuffle", &tracks)));
+
+        // Sigue siendo la misma lista de pistas, incluso con la semilla 0
+        for seed in [0, 42] {
+            let mut sorted = shuffled(seed);
+            sorted.sort();
+            assert_eq!(sorted, names(&playlist("shuffle", &tracks)));
+        }
+    }
+
+    #[test]
+    fn shuffle_restarts_from_the_first_track() {
+        let mut list = playlist("shuffle-restart", &["a", "b", "c"]);
+        list.advance();
+        list.shuffle(7);
+        assert_eq!(list.current(), list.tracks[0]);
+    }
+
+    /// Llama a `update` hasta que una pista empieza a sonar y devuelve su nombre.
+    fn wait_for_track(music: &mut MusicPlayer, dt: f64) -> String {
+        let start = Instant::now();
+        loop {
+            if let Some(track) = music.update(dt) {
+                return track;
+            }
+            assert!(start.elapsed() < TIMEOUT, "no track started");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Llama a `update` hasta que la siguiente pista termina de decodificarse.
+    fn wait_until_ready(music: &mut MusicPlayer) {
+        let start = Instant::now();
+        while music.ready.is_none() {
+            assert_eq!(music.update(0.0), None);
+            assert!(start.elapsed() < TIMEOUT, "next track never decoded");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn music_waits_for_the_decoder_and_attaches_late() {
+        let playlist = playlist("late", &["theme"]);
+        let path = playlist.current().to_path_buf();
+        let mut music = player(playlist, slow_decode);
+
+        // Mientras se decodifica el juego sigue corriendo sin música
+        for _ in 0..20 {
+            assert_eq!(music.update(0.016), None);
+        }
+        assert!(music.current.is_none());
+        assert!(music.loader.is_some());
+
+        release(&path);
+        assert_eq!(wait_for_track(&mut music, 0.016), "late-theme");
+        assert!(music.current.is_some());
+        assert!(music.loader.is_none());
+        // Una sola pista se repite sin fin y sin fundido
+        assert_eq!(music.duration, None);
+        assert!(music.fading_out.is_none());
+    }
+
+    #[test]
+    fn skip_before_decode_finishes_is_queued() {
+        let playlist = playlist("queued", &["a", "b"]);
+        let (a, b) = (playlist.tracks[0].clone(), playlist.tracks[1].clone());
+        let mut music = player(playlist, slow_decode);
+        release(&a);
+        assert_eq!(wait_for_track(&mut music, 0.016), "queued-a");
+
+        // El cambio se pide antes de que la siguiente esté lista: la actual sigue sonando
+        music.skip();
+        for _ in 0..20 {
+            assert_eq!(music.update(0.016), None);
+        }
+        assert!(music.loader.is_some());
+        assert!(music.fading_out.is_none());
+
+        release(&b);
+        assert_eq!(wait_for_track(&mut music, 0.016), "queued-b");
+        assert!(music.fading_out.is_some());
+        assert_eq!(music.fade, Some(0.0));
+    }
+
+    #[test]
+    fn decoded_track_waits_for_the_crossfade() {
+        let playlist = playlist("preload", &["a", "b"]);
+        let (a, b) = (playlist.tracks[0].clone(), playlist.tracks[1].clone());
+        release(&a);
+        release(&b);
+        let mut music = player(playlist, slow_decode);
+        assert_eq!(wait_for_track(&mut music, 0.0), "preload-a");
+        assert_eq!(music.duration, Some(TRACK_SECONDS as f64));
+
+        // Antes de la precarga no se decodifica nada
+        assert_eq!(music.update(TRACK_SECONDS as f64 - CROSSFADE - PRELOAD - 1.0), None);
+        assert!(music.loader.is_none());
+
+        // Dentro de la precarga se decodifica, pero la pista espera al fundido
+        assert_eq!(music.update(2.0), None);
+        wait_until_ready(&mut music);
+        assert!(music.fading_out.is_none());
+        assert_eq!(music.update(PRELOAD - 1.0), Some("preload-b".to_string()));
+        assert!(music.fading_out.is_some());
+    }
+
+    #[test]
+    fn failed_tracks_are_skipped_until_all_fail() {
+        let mut music = player(playlist("broken", &["a", "b", "c"]), failing_decode);
+        let start = Instant::now();
+        while music.loader.is_some() {
+            assert_eq!(music.update(0.016), None);
+            assert!(start.elapsed() < TIMEOUT, "decoder never gave up");
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(music.failures, 3);
+        assert!(music.current.is_none());
+    }
+
+    #[test]
+    fn stopped_decoder_is_reported() {
+        let (sender, receiver) = mpsc::channel();
+        drop(sender);
+        let loader = MusicLoader { receiver };
+        assert!(matches!(loader.poll(), Some(Err(_))));
+    }
+}
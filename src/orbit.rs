@@ -0,0 +1,130 @@
+use crate::map::Map;
+use crate::player::Player;
+use crate::raycaster::cast_ray;
+use std::f64::consts::PI;
+
+const ORBIT_SPEED: f64 = 0.4;        // Radianes por segundo que gira la cámara alrededor del jugador
+const RADIUS_RANGE: (f64, f64) = (0.5, 8.0); // Distancia al jugador (en celdas)
+const RADIUS_STEP: f64 = 0.5;        // Cambio de distancia por cada paso de la rueda del ratón
+const WALL_CLEARANCE: f64 = 0.2;     // Distancia mínima entre la cámara y una pared detrás de ella
+
+/// Cámara de depuración que gira alrededor del jugador y lo mira desde fuera. Solo cambia desde
+/// dónde se dibuja la escena: el jugador, sus controles y la simulación no se enteran.
+pub struct OrbitCamera {
+    angle: f64,  // Dirección desde el jugador hacia la cámara
+    radius: f64,
+}
+
+impl OrbitCamera {
+    /// Empieza detrás del jugador, a `radius` celdas.
+    pub fn new(player: &Player, radius: f64) -> Self {
+        Self { angle: player.direction + PI, radius: radius.clamp(RADIUS_RANGE.0, RADIUS_RANGE.1) }
+    }
+
+    /// Avanza el giro `dt` segundos y acerca o aleja la cámara `wheel` pasos de la rueda.
+    pub fn update(&mut self, dt: f64, wheel: f64) {
+        self.angle += ORBIT_SPEED * dt;
+        self.radius = (self.radius - wheel * RADIUS_STEP).clamp(RADIUS_RANGE.0, RADIUS_RANGE.1);
+    }
+
+    /// Pose de la cámara: a `radius` del jugador y mirando hacia él. Si hay una pared entre
+    /// ambos, la cámara se acerca para quedar delante de ella.
+    pub fn pose(&self, map: &Map, player: &Player) -> Player {
+        let outward = Player::new(player.x, player.y, self.angle);
+        let hit = cast_ray(map, &outward, 0.0, self.radius + WALL_CLEARANCE);
+        let distance = if hit.fogged { self.radius } else { (hit.distance - WALL_CLEARANCE).max(0.0) };
+
+        let mut camera = Player::new(
+            player.x + self.angle.cos() * distance,
+            player.y + self.angle.sin() * distance,
+            self.angle + PI,
+        );
+        camera.fov = player.fov;
+        camera
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::parse_map;
+    use std::f64::consts::{FRAC_PI_2, TAU};
+
+    /// Sala vacía de 19x19 celdas con el jugador en el centro.
+    fn open_room() -> Map {
+        let wall = "1".repeat(21);
+        let row = format!("1{}1", "0".repeat(19));
+        let mut rows = vec![wall.clone()];
+        rows.extend(std::iter::repeat_n(row, 19));
+        rows.push(wall);
+        parse_map(&(rows.join("\n") + "\n")).unwrap().0
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    /// Diferencia entre dos ángulos, entre -PI y PI.
+    fn angle_between(a: f64, b: f64) -> f64 {
+        (a - b + PI).rem_euclid(TAU) - PI
+    }
+
+    #[test]
+    fn starts_behind_the_player_looking_at_them() {
+        let map = open_room();
+        let player = Player::new(10.5, 10.5, FRAC_PI_2);
+        let camera = OrbitCamera::new(&player, 3.0).pose(&map, &player);
+        // El jugador mira hacia +y, así que la cámara queda 3 celdas hacia -y
+        assert_close(camera.x, 10.5);
+        assert_close(camera.y, 7.5);
+        assert_close(angle_between(camera.direction, FRAC_PI_2), 0.0);
+        assert_close(camera.fov, player.fov);
+    }
+
+    #[test]
+    fn orbit_turns_at_a_fixed_rate() {
+        let map = open_room();
+        let player = Player::new(10.5, 10.5, 0.0);
+        let mut orbit = OrbitCamera::new(&player, 2.0);
+        orbit.update(2.5, 0.0);
+        let angle = PI + ORBIT_SPEED * 2.5;
+        assert_close(angle_between(orbit.angle, angle), 0.0);
+
+        let camera = orbit.pose(&map, &player);
+        assert_close(camera.x, 10.5 + angle.cos() * 2.0);
+        assert_close(camera.y, 10.5 + angle.sin() * 2.0);
+        // Sigue mirando hacia el jugador
+        let towards_player = (player.y - camera.y).atan2(player.x - camera.x);
+        assert_close(angle_between(camera.direction, towards_player), 0.0);
+    }
+
+    #[test]
+    fn radius_is_clamped() {
+        let player = Player::new(10.5, 10.5, 0.0);
+        assert_close(OrbitCamera::new(&player, 0.0).radius, RADIUS_RANGE.0);
+        assert_close(OrbitCamera::new(&player, 100.0).radius, RADIUS_RANGE.1);
+
+        let mut orbit = OrbitCamera::new(&player, 3.0);
+        orbit.update(0.0, 2.0);
+        assert_close(orbit.radius, 3.0 - 2.0 * RADIUS_STEP);
+        orbit.update(0.0, 50.0);
+        assert_close(orbit.radius, RADIUS_RANGE.0);
+        orbit.update(0.0, -50.0);
+        assert_close(orbit.radius, RADIUS_RANGE.1);
+    }
+
+    #[test]
+    fn walls_pull_the_camera_in() {
+        let map = open_room();
+        // Dos celdas entre el jugador y la pared oeste (su cara está en x = 1)
+        let player = Player::new(3.0, 10.5, 0.0);
+        let camera = OrbitCamera::new(&player, 6.0).pose(&map, &player);
+        assert_close(camera.x, 1.0 + WALL_CLEARANCE);
+        assert_close(camera.y, 10.5);
+
+        // Pegado a la pared la cámara queda sobre el jugador en lugar de atravesarla
+        let player = Player::new(1.1, 10.5, 0.0);
+        let camera = OrbitCamera::new(&player, 6.0).pose(&map, &player);
+        assert_close(camera.x, 1.1);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::color::Color;
+
+/// Colores usados por el renderer y el HUD.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub name: &'static str,
+    pub background: Color,     // Fondo (suelo y píxeles sin dibujar)
+    pub letterbox: Color,      // Barras alrededor de la imagen cuando la ventana tiene otra proporción
+    pub sky: Color,            // Cielo sobre las zonas exteriores
+    pub ceiling: Color,        // Techo de las zonas interiores
+    pub wall_light: Color,     // Paredes horizontales
+    pub wall_dark: Color,      // Paredes verticales
+    pub floor: [Color; 5],     // Suelo según el material: piedra, madera, metal, agua y agua profunda
+    pub minimap_wall: Color,   // Paredes en el minimapa
+    pub minimap_floor: Color,  // Espacio vacío en el minimapa
+    pub players: [Color; 2],   // Marcador de cada jugador en el minimapa
+    pub hud_text: Color,       // Texto del HUD
+    pub hud_alert: Color,      // Indicadores de alerta del HUD (p. ej. grabación)
+    pub markers: [Color; 4],   // Marcadores del automapa, uno por tipo
+    pub splits: [Color; 3],    // Tiempos parciales del speedrun: mejor tramo, por delante y por detrás del récord
+}
+
+pub const DEFAULT: Palette = Palette {
+    name: "default",
+    background: Color::from_u32(0xADD8E6),
+    letterbox: Color::from_u32(0x000000),
+    sky: Color::from_u32(0xADD8E6),
+    ceiling: Color::from_u32(0x505050),
+    wall_light: Color::from_u32(0xCCCCCC),
+    wall_dark: Color::from_u32(0xAAAAAA),
+    floor: [Color::from_u32(0xADD8E6), Color::from_u32(0x8B5A2B), Color::from_u32(0x70787F), Color::from_u32(0x3A6EA5), Color::from_u32(0x1F3F66)],
+    minimap_wall: Color::from_u32(0xFFFFFF),
+    minimap_floor: Color::from_u32(0x000000),
+    players: [Color::from_u32(0xFF0000), Color::from_u32(0x0000FF)], // Rojo para el jugador 1, azul para el jugador 2
+    hud_text: Color::from_u32(0xFFFFFF),
+    hud_alert: Color::from_u32(0xFF0000),
+    markers: [Color::from_u32(0xFFD700), Color::from_u32(0x00C000), Color::from_u32(0xFF00FF), Color::from_u32(0xFF8000)],
+    splits: [Color::from_u32(0xFFD700), Color::from_u32(0x00C000), Color::from_u32(0xFF0000)],
+};
+
+pub const HIGH_CONTRAST: Palette = Palette {
+    name: "high-contrast",
+    background: Color::from_u32(0x000000),
+    letterbox: Color::from_u32(0x000000),
+    sky: Color::from_u32(0x202060),
+    ceiling: Color::from_u32(0x000000),
+    wall_light: Color::from_u32(0xFFFFFF),
+    wall_dark: Color::from_u32(0xA0A0A0),
+    floor: [Color::from_u32(0x000000), Color::from_u32(0x804000), Color::from_u32(0x606060), Color::from_u32(0x0000FF), Color::from_u32(0x000080)],
+    minimap_wall: Color::from_u32(0xFFFFFF),
+    minimap_floor: Color::from_u32(0x000000),
+    players: [Color::from_u32(0xFFFF00), Color::from_u32(0x00FFFF)],
+    hud_text: Color::from_u32(0xFFFF00),
+    hud_alert: Color::from_u32(0xFF0000),
+    markers: [Color::from_u32(0xFFFF00), Color::from_u32(0x00FF00), Color::from_u32(0xFF00FF), Color::from_u32(0x00FFFF)],
+    splits: [Color::from_u32(0xFFFF00), Color::from_u32(0x00FF00), Color::from_u32(0xFF0000)],
+};
+
+// Evita distinguir estados solo por rojo/verde: naranja y azul se distinguen con deuteranopía
+pub const DEUTERANOPIA: Palette = Palette {
+    name: "deuteranopia",
+    background: Color::from_u32(0xADD8E6),
+    letterbox: Color::from_u32(0x000000),
+    sky: Color::from_u32(0xADD8E6),
+    ceiling: Color::from_u32(0x505050),
+    wall_light: Color::from_u32(0xCCCCCC),
+    wall_dark: Color::from_u32(0x999999),
+    floor: [Color::from_u32(0xADD8E6), Color::from_u32(0x8B5A2B), Color::from_u32(0x70787F), Color::from_u32(0x0072B2), Color::from_u32(0x00466E)],
+    minimap_wall: Color::from_u32(0xFFFFFF),
+    minimap_floor: Color::from_u32(0x000000),
+    players: [Color::from_u32(0xE69F00), Color::from_u32(0x0072B2)],
+    hud_text: Color::from_u32(0xFFFFFF),
+    hud_alert: Color::from_u32(0xE69F00),
+    markers: [Color::from_u32(0xF0E442), Color::from_u32(0x56B4E9), Color::from_u32(0xCC79A7), Color::from_u32(0xD55E00)],
+    splits: [Color::from_u32(0xF0E442), Color::from_u32(0x56B4E9), Color::from_u32(0xD55E00)],
+};
+
+/// Esquemas disponibles, en el orden en que se recorren.
+pub const PALETTES: [Palette; 3] = [DEFAULT, HIGH_CONTRAST, DEUTERANOPIA];
+
+/// Busca un esquema por nombre y devuelve su índice en `PALETTES`.
+pub fn find_palette(name: &str) -> Option<usize> {
+    PALETTES.iter().position(|palette| palette.name == name)
+}
@@ -0,0 +1,90 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const APP_DIR: &str = env!("CARGO_PKG_NAME"); // Carpeta del juego dentro de las del sistema
+
+/// Carpeta de datos del usuario según la plataforma, sin la del juego: `%APPDATA%` en Windows,
+/// `~/Library/Application Support` en macOS y `$XDG_DATA_HOME` o `~/.local/share` en el resto.
+fn platform_data_dir() -> Option<PathBuf> {
+    let from_env = |name: &str| env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    if cfg!(windows) {
+        from_env("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        from_env("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        from_env("XDG_DATA_HOME").or_else(|| from_env("HOME").map(|home| home.join(".local").join("share")))
+    }
+}
+
+/// Carpeta donde el juego escribe los archivos de tipo `kind` (p. ej. `recordings`), creada si
+/// no existía. Nunca se escribe junto al ejecutable, que puede estar en una carpeta de solo
+/// lectura: se usa la carpeta de datos del usuario y, si no se puede crear, la carpeta temporal
+/// del sistema, avisando del cambio. Si tampoco esa se puede crear, se devuelve igualmente y
+/// el error aparece al escribir el archivo.
+pub fn output_dir(kind: &str) -> PathBuf {
+    output_dir_in(platform_data_dir(), &env::temp_dir(), kind)
+}
+
+/// `output_dir` con la carpeta de datos del usuario (`data_dir`) y la temporal (`temp_dir`)
+/// dadas, en lugar de las del sistema.
+fn output_dir_in(data_dir: Option<PathBuf>, temp_dir: &Path, kind: &str) -> PathBuf {
+    let fallback = temp_dir.join(APP_DIR).join(kind);
+    match data_dir.map(|dir| dir.join(APP_DIR).join(kind)) {
+        Some(dir) => match fs::create_dir_all(&dir) {
+            Ok(()) => return dir,
+            Err(err) => eprintln!("warning: cannot create {}: {}, using {}", dir.display(), err, fallback.display()),
+        },
+        None => eprintln!("warning: no user data directory, using {}", fallback.display()),
+    }
+    if let Err(err) = fs::create_dir_all(&fallback) {
+        eprintln!("warning: cannot create {}: {}", fallback.display(), err);
+    }
+    fallback
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn uses_data_dir_when_it_can_be_created() {
+        let temp = TestDir::new("paths", "data");
+        let root = temp.path();
+        let dir = output_dir_in(Some(root.join("data")), &root.join("temp"), "recordings");
+        assert_eq!(dir, root.join("data").join(APP_DIR).join("recordings"));
+        assert!(dir.is_dir());
+        assert!(!root.join("temp").exists());
+    }
+
+    #[test]
+    fn falls_back_to_temp_when_data_dir_fails() {
+        // Un archivo donde debería ir una carpeta hace fallar create_dir_all, incluso como root
+        let temp = TestDir::new("paths", "blocked");
+        let root = temp.path();
+        fs::write(root.join("data"), "").unwrap();
+        let dir = output_dir_in(Some(root.join("data")), &root.join("temp"), "crashes");
+        assert_eq!(dir, root.join("temp").join(APP_DIR).join("crashes"));
+        assert!(dir.is_dir());
+    }
+
+    #[test]
+    fn falls_back_to_temp_without_data_dir() {
+        let temp = TestDir::new("paths", "none");
+        let root = temp.path();
+        let dir = output_dir_in(None, root, "recordings");
+        assert_eq!(dir, root.join(APP_DIR).join("recordings"));
+        assert!(dir.is_dir());
+    }
+
+    #[test]
+    fn returns_fallback_even_if_it_cannot_be_created() {
+        let temp = TestDir::new("paths", "nowhere");
+        let root = temp.path();
+        fs::write(root.join("file"), "").unwrap();
+        let dir = output_dir_in(None, &root.join("file"), "recordings");
+        assert_eq!(dir, root.join("file").join(APP_DIR).join("recordings"));
+        assert!(!dir.exists());
+    }
+}
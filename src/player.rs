@@ -1,4 +1,8 @@
 use crate::map::Map;
+use crate::portal::Side;
+use std::f64::consts::TAU;
+
+const WALL_MARGIN: f64 = 0.1; // Distancia mínima entre el jugador y la cara de una pared
 
 /// Representa a un jugador en el mapa del juego.
 /// El jugador tiene una posición (x, y), una dirección en la que mira (en radianes)
@@ -8,6 +12,7 @@ pub struct Player {
     pub y: f64,         // Coordenada y de la posición del jugador en el mapa.
     pub direction: f64, // Dirección en la que está mirando el jugador (en radianes).
     pub fov: f64,       // Campo de visión del jugador (en radianes).
+    pub noclip: bool,   // Si es true, el jugador atraviesa las paredes.
 }
 
 impl Player {
@@ -23,12 +28,15 @@ impl Player {
     ///
     /// Una nueva instancia de `Player`.
     pub fn new(x: f64, y: f64, direction: f64) -> Self {
-        Self {
+        let mut player = Self {
             x,
             y,
-            direction,
+            direction: 0.0,
             fov: 90.0_f64.to_radians(), // Campo de visión predeterminado de 90 grados.
-        }
+            noclip: false,
+        };
+        player.set_direction(direction);
+        player
     }
 
     /// Mueve al jugador hacia adelante en la dirección que está mirando.
@@ -38,18 +46,7 @@ impl Player {
     /// * `distance` - La distancia que el jugador debe moverse.
     /// * `map` - Una referencia al mapa para verificar colisiones con paredes.
     pub fn move_forward(&mut self, distance: f64, map: &Map) {
-        let new_x = self.x + self.direction.cos() * distance;
-        let new_y = self.y + self.direction.sin() * distance;
-
-        // Verifica si la nueva posición en el eje x no es una pared
-        if !map.is_wall(new_x, self.y) {
-            self.x = new_x;
-        }
-
-        // Verifica si la nueva posición en el eje y no es una pared
-        if !map.is_wall(self.x, new_y) {
-            self.y = new_y;
-        }
+        self.try_move(self.direction.cos() * distance, self.direction.sin() * distance, map);
     }
 
     /// Mueve al jugador hacia atrás en la dirección opuesta a la que está mirando.
@@ -59,18 +56,102 @@ impl Player {
     /// * `distance` - La distancia que el jugador debe moverse hacia atrás.
     /// * `map` - Una referencia al mapa para verificar colisiones con paredes.
     pub fn move_backward(&mut self, distance: f64, map: &Map) {
-        let new_x = self.x - self.direction.cos() * distance;
-        let new_y = self.y - self.direction.sin() * distance;
+        self.try_move(-self.direction.cos() * distance, -self.direction.sin() * distance, map);
+    }
+
+    /// Desplaza al jugador (`dx`, `dy`) eje por eje, para que se deslice junto a las paredes en
+    /// lugar de quedarse quieto. Después lo pasa por los portales que toque y lo separa de las
+    /// paredes. Con noclip se mueve sin más.
+    fn try_move(&mut self, dx: f64, dy: f64, map: &Map) {
+        let new_x = self.x + dx;
+        let new_y = self.y + dy;
 
-        // Verifica si la nueva posición en el eje x no es una pared
-        if !map.is_wall(new_x, self.y) {
+        // Verifica si la nueva posición en el eje x no es una pared (ni queda pegada a una delgada)
+        if self.noclip || !(map.is_wall(new_x, self.y) || map.thin_wall_blocks((self.x, self.y), (new_x, self.y), WALL_MARGIN)) {
             self.x = new_x;
         }
 
-        // Verifica si la nueva posición en el eje y no es una pared
-        if !map.is_wall(self.x, new_y) {
+        // Verifica si la nueva posición en el eje y no es una pared (ni queda pegada a una delgada)
+        if self.noclip || !(map.is_wall(self.x, new_y) || map.thin_wall_blocks((self.x, self.y), (self.x, new_y), WALL_MARGIN)) {
             self.y = new_y;
         }
+
+        if !self.noclip {
+            self.pass_through_portals(map);
+            self.keep_off_walls(map);
+        }
+    }
+
+    /// Si el jugador llegó a menos de `WALL_MARGIN` de la cara de un portal, lo pasa al otro
+    /// lado: aparece frente a la cara conectada, en el punto que corresponde a lo largo de la
+    /// cara, y girado como el portal.
+    fn pass_through_portals(&mut self, map: &Map) {
+        let (cell_x, cell_y) = (self.x.floor(), self.y.floor());
+        // Caras de las celdas vecinas que miran hacia la del jugador, y la distancia a cada una
+        let faces = [
+            (cell_x - 1.0, cell_y, Side::East, self.x - cell_x),
+            (cell_x + 1.0, cell_y, Side::West, cell_x + 1.0 - self.x),
+            (cell_x, cell_y - 1.0, Side::South, self.y - cell_y),
+            (cell_x, cell_y + 1.0, Side::North, cell_y + 1.0 - self.y),
+        ];
+        for (x, y, side, distance) in faces {
+            if distance >= WALL_MARGIN {
+                continue;
+            }
+            let Some(portal) = map.portal_exit(x as isize, y as isize, side) else {
+                continue;
+            };
+            let (normal_x, normal_y) = side.normal();
+            let on_face = (self.x - normal_x as f64 * distance, self.y - normal_y as f64 * distance);
+            let (exit_x, exit_y) = portal.point(on_face);
+            self.x = exit_x + portal.exit_normal.0 * WALL_MARGIN;
+            self.y = exit_y + portal.exit_normal.1 * WALL_MARGIN;
+            self.set_direction(self.direction + portal.rotation);
+            return;
+        }
+    }
+
+    /// Aleja al jugador de las paredes vecinas si quedó a menos de `WALL_MARGIN` de su cara,
+    /// empujándolo en la dirección normal a la pared, o de la esquina de una pared en diagonal,
+    /// empujándolo en dirección contraria a la esquina. Así la cámara nunca queda tan pegada a
+    /// una pared que la proyección la vea desde dentro de la celda.
+    fn keep_off_walls(&mut self, map: &Map) {
+        let (cell_x, cell_y) = (self.x.floor(), self.y.floor());
+        if map.is_wall(cell_x - 0.5, self.y) {
+            self.x = self.x.max(cell_x + WALL_MARGIN);
+        }
+        if map.is_wall(cell_x + 1.5, self.y) {
+            self.x = self.x.min(cell_x + 1.0 - WALL_MARGIN);
+        }
+        if map.is_wall(self.x, cell_y - 0.5) {
+            self.y = self.y.max(cell_y + WALL_MARGIN);
+        }
+        if map.is_wall(self.x, cell_y + 1.5) {
+            self.y = self.y.min(cell_y + 1.0 - WALL_MARGIN);
+        }
+
+        // Esquinas salientes: solo las paredes en diagonal, sin pared a los lados, dejan al
+        // jugador acercarse a la esquina más de lo que permiten las caras
+        for (dx, dy) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            if !map.is_wall(cell_x + 0.5 + dx, cell_y + 0.5 + dy) {
+                continue;
+            }
+            let corner_x = cell_x + if dx > 0.0 { 1.0 } else { 0.0 };
+            let corner_y = cell_y + if dy > 0.0 { 1.0 } else { 0.0 };
+            let (away_x, away_y) = (self.x - corner_x, self.y - corner_y);
+            let distance = away_x.hypot(away_y);
+            if distance >= WALL_MARGIN {
+                continue;
+            }
+            // Justo sobre la esquina no hay dirección: se sale en diagonal, lejos de la pared
+            let (normal_x, normal_y) = if distance > 0.0 {
+                (away_x / distance, away_y / distance)
+            } else {
+                (-dx * std::f64::consts::FRAC_1_SQRT_2, -dy * std::f64::consts::FRAC_1_SQRT_2)
+            };
+            self.x = corner_x + normal_x * WALL_MARGIN;
+            self.y = corner_y + normal_y * WALL_MARGIN;
+        }
     }
 
     /// Gira al jugador hacia la izquierda (contra las agujas del reloj).
@@ -79,7 +160,7 @@ impl Player {
     ///
     /// * `angle` - El ángulo en radianes que se debe girar a la izquierda.
     pub fn turn_left(&mut self, angle: f64) {
-        self.direction -= angle;
+        self.rotate(-angle);
     }
 
     /// Gira al jugador hacia la derecha (en el sentido de las agujas del reloj).
@@ -88,7 +169,7 @@ impl Player {
     ///
     /// * `angle` - El ángulo en radianes que se debe girar a la derecha.
     pub fn turn_right(&mut self, angle: f64) {
-        self.direction += angle;
+        self.rotate(angle);
     }
 
     /// Permite al jugador girar en cualquier dirección con un ajuste más fino.
@@ -96,9 +177,9 @@ impl Player {
     /// # Argumentos
     ///
     /// * `angle` - El ángulo en radianes que se debe girar. Un valor positivo
-    /// girará a la derecha y un valor negativo girará a la izquierda.
+    ///   girará a la derecha y un valor negativo girará a la izquierda.
     pub fn rotate(&mut self, angle: f64) {
-        self.direction += angle;
+        self.set_direction(self.direction + angle);
     }
 
     /// Ajusta el campo de visión del jugador.
@@ -109,4 +190,169 @@ impl Player {
     pub fn set_fov(&mut self, fov: f64) {
         self.fov = fov.to_radians();
     }
+
+    /// Cambia la dirección del jugador, normalizada al rango [0, 2π) para que no crezca
+    /// sin límite al girar y `cos`/`sin` no pierdan precisión.
+    ///
+    /// # Argumentos
+    ///
+    /// * `angle` - La nueva dirección en radianes (puede estar fuera del rango).
+    pub fn set_direction(&mut self, angle: f64) {
+        // rem_euclid puede devolver exactamente TAU con ángulos negativos muy pequeños
+        let direction = angle.rem_euclid(TAU);
+        self.direction = if direction < TAU { direction } else { 0.0 };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::parse_map;
+
+    const TURNS: usize = 10_000;
+
+    /// Sala con un pilar de una celda en (2, 3): sus cuatro esquinas son salientes.
+    fn pillar_room() -> Map {
+        parse_map("111111\n1P0001\n100001\n101001\n100001\n111111\n").unwrap().0
+    }
+
+    fn corner_distance(player: &Player, (x, y): (f64, f64)) -> f64 {
+        (player.x - x).hypot(player.y - y)
+    }
+
+    fn assert_in_range(player: &Player) {
+        assert!((0.0..TAU).contains(&player.direction), "direction {} out of range", player.direction);
+    }
+
+    #[test]
+    fn full_turns_return_to_start() {
+        let mut player = Player::new(1.5, 1.5, 0.0);
+        let steps_per_turn = 64;
+        for _ in 0..TURNS * steps_per_turn {
+            player.turn_right(TAU / steps_per_turn as f64);
+            assert_in_range(&player);
+        }
+        assert!(player.direction.cos() > 1.0 - 1e-9 && player.direction.sin().abs() < 1e-6);
+    }
+
+    #[test]
+    fn uneven_steps_keep_forward_vector_accurate() {
+        let step = 0.03;
+        let steps = (TURNS as f64 * TAU / step) as usize;
+        let mut left = Player::new(1.5, 1.5, 0.0);
+        let mut right = Player::new(1.5, 1.5, 0.0);
+        for _ in 0..steps {
+            left.turn_left(step);
+            right.turn_right(step);
+        }
+        assert_in_range(&left);
+        assert_in_range(&right);
+
+        let expected = (steps as f64 * step).rem_euclid(TAU);
+        assert!((right.direction.cos() - expected.cos()).abs() < 1e-6);
+        assert!((right.direction.sin() - expected.sin()).abs() < 1e-6);
+        assert!((left.direction.cos() - expected.cos()).abs() < 1e-6);
+        assert!((left.direction.sin() + expected.sin()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn moves_along_direction_after_spinning() {
+        let map = Map::new(8, 8).unwrap();
+        let mut player = Player::new(4.0, 4.0, 0.0);
+        for _ in 0..TURNS {
+            player.rotate(TAU);
+        }
+        player.rotate(TAU / 4.0);
+        player.move_forward(1.0, &map);
+        assert!((player.x - 4.0).abs() < 1e-9 && (player.y - 5.0).abs() < 1e-9, "at ({}, {})", player.x, player.y);
+    }
+
+    #[test]
+    fn backward_matches_forward_facing_the_other_way() {
+        let map = pillar_room();
+        // Por un espacio abierto y contra la cara del pilar, donde el eje bloqueado se desliza
+        for (start, direction) in [((4.5, 1.5), 2.0), ((1.5, 3.2), 0.4), ((2.8, 2.4), 1.2)] {
+            let mut backward = Player::new(start.0, start.1, direction);
+            let mut forward = Player::new(start.0, start.1, direction + TAU / 2.0);
+            for _ in 0..40 {
+                backward.move_backward(0.05, &map);
+                forward.move_forward(0.05, &map);
+            }
+            assert!((backward.x - forward.x).abs() < 1e-9 && (backward.y - forward.y).abs() < 1e-9, "from {:?}", start);
+            assert!(!map.is_wall(backward.x, backward.y));
+        }
+    }
+
+    #[test]
+    fn noclip_moves_through_walls() {
+        let map = pillar_room();
+        let mut player = Player::new(1.5, 3.5, 0.0);
+        player.noclip = true;
+        player.move_forward(1.0, &map);
+        assert_eq!((player.x, player.y), (2.5, 3.5));
+        player.move_backward(2.0, &map);
+        assert_eq!((player.x, player.y), (0.5, 3.5));
+    }
+
+    #[test]
+    fn set_direction_normalizes_negative_and_large_angles() {
+        let mut player = Player::new(0.0, 0.0, -TAU / 4.0);
+        assert!((player.direction - TAU * 0.75).abs() < 1e-12);
+        player.set_direction(-1e-20);
+        assert_in_range(&player);
+        player.set_direction(1e6);
+        assert_in_range(&player);
+    }
+
+    #[test]
+    fn walking_into_a_convex_corner_keeps_the_margin() {
+        let map = pillar_room();
+        // Hacia cada esquina del pilar, desde la celda en diagonal
+        let approaches = [
+            ((2.0, 3.0), (1.7, 2.7)),
+            ((3.0, 3.0), (3.3, 2.7)),
+            ((2.0, 4.0), (1.7, 4.3)),
+            ((3.0, 4.0), (3.3, 4.3)),
+        ];
+        for (corner, start) in approaches {
+            let mut player = Player::new(start.0, start.1, (corner.1 - start.1).atan2(corner.0 - start.0));
+            for _ in 0..50 {
+                player.move_forward(0.01, &map);
+                let distance = corner_distance(&player, corner);
+                assert!(distance >= WALL_MARGIN - 1e-9, "{:?}: {} from the corner at ({}, {})", corner, distance, player.x, player.y);
+                assert!(!map.is_wall(player.x, player.y));
+            }
+        }
+    }
+
+    #[test]
+    fn sliding_past_a_corner_keeps_the_margin() {
+        let map = pillar_room();
+        // Pegado a la cara oeste del pilar, caminando hacia el norte más allá de la esquina
+        let mut player = Player::new(1.95, 3.5, -TAU / 4.0);
+        player.move_forward(0.0, &map);
+        assert!((player.x - (2.0 - WALL_MARGIN)).abs() < 1e-9);
+        for _ in 0..100 {
+            player.move_forward(0.01, &map);
+            assert!(corner_distance(&player, (2.0, 3.0)) >= WALL_MARGIN - 1e-9, "at ({}, {})", player.x, player.y);
+        }
+    }
+
+    #[test]
+    fn standing_on_a_corner_is_pushed_away_from_the_wall() {
+        let map = pillar_room();
+        // Justo sobre la esquina sureste del pilar, ya en la celda (3, 4)
+        let mut player = Player::new(3.0, 4.0, 0.0);
+        player.move_forward(0.0, &map);
+        assert!((corner_distance(&player, (3.0, 4.0)) - WALL_MARGIN).abs() < 1e-9);
+        assert!(player.x > 3.0 && player.y > 4.0, "at ({}, {})", player.x, player.y);
+    }
+
+    #[test]
+    fn concave_corners_use_the_faces() {
+        let map = pillar_room();
+        let mut player = Player::new(1.05, 1.05, 0.0);
+        player.move_forward(0.0, &map);
+        assert!((player.x - (1.0 + WALL_MARGIN)).abs() < 1e-9 && (player.y - (1.0 + WALL_MARGIN)).abs() < 1e-9);
+    }
 }
@@ -0,0 +1,181 @@
+/// Cara de una celda. El eje y del mapa crece hacia abajo, así que el norte es y negativo.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Side {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Side {
+    /// Lee una cara escrita como `N`, `E`, `S` u `W`.
+    pub fn from_char(ch: char) -> Option<Side> {
+        match ch.to_ascii_uppercase() {
+            'N' => Some(Side::North),
+            'E' => Some(Side::East),
+            'S' => Some(Side::South),
+            'W' => Some(Side::West),
+            _ => None,
+        }
+    }
+
+    /// Posición de la cara en las tablas indexadas por cara (N, E, S, W).
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Vector unitario que sale de la celda a través de esta cara.
+    pub fn normal(self) -> (isize, isize) {
+        match self {
+            Side::North => (0, -1),
+            Side::East => (1, 0),
+            Side::South => (0, 1),
+            Side::West => (-1, 0),
+        }
+    }
+}
+
+/// Cara `side` de la celda de pared (`x`, `y`) usada como portal.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PortalFace {
+    pub x: usize,
+    pub y: usize,
+    pub side: Side,
+}
+
+impl PortalFace {
+    /// Celda frente a la cara, desde la que se ve el portal y por la que se sale de él.
+    pub fn open_cell(self) -> (isize, isize) {
+        let (nx, ny) = self.side.normal();
+        (self.x as isize + nx, self.y as isize + ny)
+    }
+
+    /// Punto medio de la cara.
+    fn center(self) -> (f64, f64) {
+        let (nx, ny) = self.side.normal();
+        (self.x as f64 + 0.5 + nx as f64 * 0.5, self.y as f64 + 0.5 + ny as f64 * 0.5)
+    }
+}
+
+/// Par de caras conectadas: lo que entra por una sale por la otra, en ambos sentidos.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Portal {
+    pub a: PortalFace,
+    pub b: PortalFace,
+}
+
+/// Transformación de un portal, de su cara de entrada a la de salida: lo que entra contra la
+/// normal de la entrada sale a lo largo de la normal de la salida, y el punto de la cara por
+/// el que entra se corresponde con el mismo punto de la cara de salida.
+pub struct PortalTransform {
+    pub rotation: f64,            // Giro de la dirección al atravesar el portal (en radianes)
+    pub exit_cell: (isize, isize), // Celda en la que se sale
+    pub exit_normal: (f64, f64),  // Normal de la cara de salida, hacia la celda de salida
+    entry_center: (f64, f64),
+    exit_center: (f64, f64),
+    cos: f64,
+    sin: f64,
+}
+
+impl PortalTransform {
+    pub fn new(entry: PortalFace, exit: PortalFace) -> Self {
+        let (entry_x, entry_y) = entry.side.normal();
+        let (exit_x, exit_y) = exit.side.normal();
+        let rotation = (exit_y as f64).atan2(exit_x as f64) - (-entry_y as f64).atan2(-entry_x as f64);
+        Self {
+            rotation,
+            exit_cell: exit.open_cell(),
+            exit_normal: (exit_x as f64, exit_y as f64),
+            entry_center: entry.center(),
+            exit_center: exit.center(),
+            cos: rotation.cos(),
+            sin: rotation.sin(),
+        }
+    }
+
+    /// Dirección con la que sale lo que entró con dirección (`x`, `y`).
+    pub fn direction(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (x * self.cos - y * self.sin, x * self.sin + y * self.cos)
+    }
+
+    /// Punto de la cara de salida que corresponde al punto (`x`, `y`) de la cara de entrada.
+    pub fn point(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let (dx, dy) = self.direction((x - self.entry_center.0, y - self.entry_center.1));
+        (self.exit_center.0 + dx, self.exit_center.1 + dy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIDES: [Side; 4] = [Side::North, Side::East, Side::South, Side::West];
+    const EPSILON: f64 = 1e-9;
+
+    fn close(a: (f64, f64), b: (f64, f64)) -> bool {
+        (a.0 - b.0).abs() < EPSILON && (a.1 - b.1).abs() < EPSILON
+    }
+
+    fn normal(side: Side) -> (f64, f64) {
+        let (x, y) = side.normal();
+        (x as f64, y as f64)
+    }
+
+    fn cross(a: (f64, f64), b: (f64, f64)) -> f64 {
+        a.0 * b.1 - a.1 * b.0
+    }
+
+    /// Las dieciséis combinaciones de cara de entrada y de salida, en celdas distintas.
+    fn pairings() -> impl Iterator<Item = (PortalFace, PortalFace)> {
+        SIDES.into_iter().flat_map(|entry| {
+            SIDES.into_iter().map(move |exit| (PortalFace { x: 2, y: 3, side: entry }, PortalFace { x: 7, y: 5, side: exit }))
+        })
+    }
+
+    #[test]
+    fn entering_against_normal_exits_along_normal() {
+        for (entry, exit) in pairings() {
+            let transform = PortalTransform::new(entry, exit);
+            let (x, y) = normal(entry.side);
+            let out = transform.direction((-x, -y));
+            assert!(close(out, normal(exit.side)), "{:?} -> {:?}: {:?}", entry.side, exit.side, out);
+            assert!(close(transform.exit_normal, normal(exit.side)));
+            assert_eq!(transform.exit_cell, exit.open_cell());
+        }
+    }
+
+    #[test]
+    fn direction_keeps_its_length() {
+        for (entry, exit) in pairings() {
+            let (x, y) = PortalTransform::new(entry, exit).direction((0.6, -0.8));
+            assert!((x.hypot(y) - 1.0).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn face_points_map_onto_exit_face() {
+        for (entry, exit) in pairings() {
+            let transform = PortalTransform::new(entry, exit);
+            assert!(close(transform.point(entry.center()), exit.center()));
+
+            let (entry_x, entry_y) = normal(entry.side);
+            let exit_normal = normal(exit.side);
+            let inward = (-entry_x, -entry_y);
+            for t in [-0.4, -0.1, 0.25, 0.5] {
+                // Punto de la cara de entrada desplazado `t` a lo largo de ella
+                let offset = (-entry_y * t, entry_x * t);
+                let point = (entry.center().0 + offset.0, entry.center().1 + offset.1);
+                let mapped = transform.point(point);
+                let exit_offset = (mapped.0 - exit.center().0, mapped.1 - exit.center().1);
+
+                // Queda sobre la cara de salida, a la misma distancia de su centro...
+                assert!((exit_offset.0 * exit_normal.0 + exit_offset.1 * exit_normal.1).abs() < EPSILON);
+                assert!((exit_offset.0.hypot(exit_offset.1) - t.abs()).abs() < EPSILON);
+                // ...y del mismo lado respecto de la dirección de avance, sin reflejarse
+                let before = cross(inward, offset);
+                let after = cross(transform.direction(inward), exit_offset);
+                assert!((before - after).abs() < EPSILON, "{:?} -> {:?} at {}", entry.side, exit.side, t);
+            }
+        }
+    }
+}
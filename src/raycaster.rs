@@ -1,58 +1,197 @@
 use crate::player::Player;
-use crate::map::Map;
+use crate::map::{Map, ThinWall};
+use crate::portal::Side;
+use crate::raycore::{delta_dist, initial_side_dist, perp_distance, steps_along_x, wall_offset};
+
+const MAX_PORTAL_DEPTH: usize = 2; // Portales que un rayo puede cruzar seguidos (portales vistos a través de otros)
+
+/// Tramo recto de un rayo: empieza en `origin`, a `start` de distancia del jugador a lo largo del rayo.
+#[derive(Clone, Copy, Default)]
+struct RaySegment {
+    start: f64,
+    origin: (f64, f64),
+    dir: (f64, f64),
+}
+
+/// Resultado de lanzar un rayo contra el mapa.
+pub struct RayHit {
+    pub distance: f64,       // Distancia perpendicular a la pared
+    pub is_horizontal: bool, // Si la pared golpeada es horizontal
+    pub near_x: isize,       // Celda vacía justo antes de la pared (lado cercano)
+    pub near_y: isize,
+    pub wall_x: f64,         // Punto exacto del impacto a lo largo de la cara de la pared (0.0 a 1.0)
+    pub face: Side,          // Cara de la pared golpeada
+    pub wall_type: u8,       // Valor de la celda golpeada (1 a 9), o 0 fuera del mapa o en la niebla
+    pub fogged: bool,        // El rayo superó la distancia máxima de la niebla sin encontrar pared
+    #[cfg(feature = "ray-stats")]
+    pub steps: u32,          // Celdas que recorrió el DDA
+    segments: [RaySegment; MAX_PORTAL_DEPTH + 1], // Tramos del rayo, uno más por cada portal cruzado
+    segment_count: usize,
+}
+
+impl RayHit {
+    /// Punto del mapa a `distance` del jugador a lo largo del rayo, siguiéndolo a través de los
+    /// portales que cruzó.
+    pub fn point_at(&self, distance: f64) -> (f64, f64) {
+        let segment = self.segments[..self.segment_count]
+            .iter()
+            .rev()
+            .find(|segment| segment.start <= distance)
+            .unwrap_or(&self.segments[0]);
+        let t = distance - segment.start;
+        (segment.origin.0 + segment.dir.0 * t, segment.origin.1 + segment.dir.1 * t)
+    }
+}
+
+/// Resultado de recorrer un tramo del rayo hasta una pared o la niebla.
+struct Trace {
+    distance: f64, // Distancia desde el origen del tramo
+    side: usize,   // 0 para vertical, 1 para horizontal
+    wall: (isize, isize),
+    near: (isize, isize),
+    face: Side,    // Cara de la pared golpeada
+    wall_x: f64,
+    fogged: bool,
+    #[cfg(feature = "ray-stats")]
+    steps: u32,
+}
 
 /// Lanza un rayo desde la posición del jugador y calcula la distancia hasta la primera pared que encuentra.
+/// Si la pared es un portal, el rayo sigue desde la cara conectada (hasta `MAX_PORTAL_DEPTH`
+/// portales) y la distancia es la recorrida en total.
 ///
 /// # Arguments
 /// * `map` - Referencia al mapa del juego.
 /// * `player` - Referencia al jugador.
 /// * `angle_offset` - Desplazamiento angular para calcular la dirección del rayo.
+/// * `max_distance` - Distancia a partir de la cual se deja de buscar (todo es niebla).
+///   `f64::INFINITY` recorre el mapa hasta la pared.
 ///
 /// # Returns
-/// * `(f64, bool)` - Distancia perpendicular a la pared y un booleano indicando si la pared es horizontal.
-pub fn cast_ray(map: &Map, player: &Player, angle_offset: f64) -> (f64, bool) {
+/// * `RayHit` - Distancia perpendicular a la pared, orientación de la pared y la celda del lado cercano.
+pub fn cast_ray(map: &Map, player: &Player, angle_offset: f64, max_distance: f64) -> RayHit {
     let ray_angle = player.direction + angle_offset;
 
-    // Dirección del rayo
-    let ray_dir_x = ray_angle.cos();
-    let ray_dir_y = ray_angle.sin();
+    // Primer tramo: desde el jugador en la dirección del rayo
+    let mut segment = RaySegment { start: 0.0, origin: (player.x, player.y), dir: (ray_angle.cos(), ray_angle.sin()) };
+    let mut cell = (player.x.floor() as isize, player.y.floor() as isize);
+    let mut segments = [RaySegment::default(); MAX_PORTAL_DEPTH + 1];
+    let mut depth = 0;
+    #[cfg(feature = "ray-stats")]
+    let mut steps = 0;
+
+    loop {
+        segments[depth] = segment;
+        let trace = trace(map, segment, cell, max_distance - segment.start);
+        #[cfg(feature = "ray-stats")]
+        {
+            steps += trace.steps;
+        }
 
-    // Posiciones iniciales en la rejilla
-    let mut map_x = player.x.floor() as isize;
-    let mut map_y = player.y.floor() as isize;
+        // Un portal continúa el rayo desde su pareja, con la dirección girada como el portal
+        let portal = if trace.fogged || depth == MAX_PORTAL_DEPTH {
+            None
+        } else {
+            map.portal_exit(trace.wall.0, trace.wall.1, trace.face)
+        };
+        if let Some(portal) = portal {
+            let hit = (
+                segment.origin.0 + segment.dir.0 * trace.distance,
+                segment.origin.1 + segment.dir.1 * trace.distance,
+            );
+            segment = RaySegment {
+                start: segment.start + trace.distance,
+                origin: portal.point(hit),
+                dir: portal.direction(segment.dir),
+            };
+            cell = portal.exit_cell;
+            depth += 1;
+            continue;
+        }
+
+        return RayHit {
+            distance: if trace.fogged { max_distance } else { segment.start + trace.distance },
+            is_horizontal: trace.side == 1,
+            near_x: trace.near.0,
+            near_y: trace.near.1,
+            wall_x: trace.wall_x,
+            face: trace.face,
+            wall_type: if trace.fogged { 0 } else { map.cell_at(trace.wall.0, trace.wall.1) },
+            fogged: trace.fogged,
+            #[cfg(feature = "ray-stats")]
+            steps,
+            segments,
+            segment_count: depth + 1,
+        };
+    }
+}
+
+/// Recorre un tramo del rayo con DDA desde `cell`, la celda que contiene su origen, hasta la
+/// primera pared o hasta `max_distance`.
+fn trace(map: &Map, segment: RaySegment, cell: (isize, isize), max_distance: f64) -> Trace {
+    let (origin_x, origin_y) = segment.origin;
+    let (ray_dir_x, ray_dir_y) = segment.dir;
+    let (mut map_x, mut map_y) = cell;
 
     // Distancia recorrida por el rayo desde una pared a la siguiente
-    let delta_dist_x = if ray_dir_x != 0.0 {
-        (1.0 / ray_dir_x).abs()
-    } else {
-        f64::INFINITY
-    };
-    let delta_dist_y = if ray_dir_y != 0.0 {
-        (1.0 / ray_dir_y).abs()
-    } else {
-        f64::INFINITY
-    };
+    let delta_dist_x = delta_dist(ray_dir_x);
+    let delta_dist_y = delta_dist(ray_dir_y);
 
     // Variables de paso
-    let (step_x, mut side_dist_x) = if ray_dir_x < 0.0 {
-        (-1, (player.x - map_x as f64) * delta_dist_x)
-    } else {
-        (1, (map_x as f64 + 1.0 - player.x) * delta_dist_x)
-    };
-
-    let (step_y, mut side_dist_y) = if ray_dir_y < 0.0 {
-        (-1, (player.y - map_y as f64) * delta_dist_y)
-    } else {
-        (1, (map_y as f64 + 1.0 - player.y) * delta_dist_y)
-    };
+    let (step_x, mut side_dist_x) = initial_side_dist(origin_x, map_x, ray_dir_x, delta_dist_x);
+    let (step_y, mut side_dist_y) = initial_side_dist(origin_y, map_y, ray_dir_y, delta_dist_y);
 
     let mut hit = false; // Si el rayo ha golpeado una pared
     let mut side = 0; // 0 para vertical, 1 para horizontal
+    let mut enter = 0.0; // Distancia a la que el rayo entró en la celda actual
+    #[cfg(feature = "ray-stats")]
+    let mut steps = 0;
 
     // Bucle para recorrer el mapa
     while !hit {
+        // Una pared delgada detiene el rayo si la cruza dentro de la celda por una parte sólida;
+        // si no (o entre los barrotes de una reja), el rayo sigue
+        if let Some(wall) = map.thin_wall(map_x, map_y) {
+            let exit = side_dist_x.min(side_dist_y).min(max_distance);
+            if let Some((distance, offset)) = thin_wall_hit(wall, (map_x, map_y), segment, enter, exit) {
+                let face = match (wall.runs_east_west, ray_dir_x > 0.0, ray_dir_y > 0.0) {
+                    (true, _, true) => Side::North,
+                    (true, _, false) => Side::South,
+                    (false, true, _) => Side::West,
+                    (false, false, _) => Side::East,
+                };
+                return Trace {
+                    distance,
+                    side: if wall.runs_east_west { 1 } else { 0 },
+                    wall: (map_x, map_y),
+                    near: (map_x, map_y),
+                    face,
+                    wall_x: offset,
+                    fogged: false,
+                    #[cfg(feature = "ray-stats")]
+                    steps,
+                };
+            }
+        }
+
+        // La siguiente línea de la rejilla ya queda dentro de la niebla: no hace falta seguir
+        if side_dist_x.min(side_dist_y) > max_distance {
+            return Trace {
+                distance: max_distance,
+                side: 0,
+                wall: (map_x, map_y),
+                near: (map_x, map_y),
+                face: Side::West,
+                wall_x: 0.0,
+                fogged: true,
+                #[cfg(feature = "ray-stats")]
+                steps,
+            };
+        }
+
         // Saltar al siguiente cuadrado
-        if side_dist_x < side_dist_y {
+        enter = side_dist_x.min(side_dist_y);
+        if steps_along_x(side_dist_x, side_dist_y) {
             side_dist_x += delta_dist_x;
             map_x += step_x;
             side = 0;
@@ -61,19 +200,144 @@ pub fn cast_ray(map: &Map, player: &Player, angle_offset: f64) -> (f64, bool) {
             map_y += step_y;
             side = 1;
         }
+        #[cfg(feature = "ray-stats")]
+        {
+            steps += 1;
+        }
 
-        // Comprobar si el rayo ha golpeado una pared
-        if map.is_wall(map_x as f64, map_y as f64) {
+        // Comprobar si el rayo ha golpeado una pared (o ha salido del mapa,
+        // para que un mapa sin borde cerrado no deje el bucle sin fin)
+        let outside = map_x < 0 || map_y < 0 || map_x as usize >= map.width || map_y as usize >= map.height;
+        if outside || map.is_wall(map_x as f64, map_y as f64) {
             hit = true;
         }
     }
 
-    // Calcular distancia perpendicular a la pared para evitar distorsión
-    let perp_wall_dist = if side == 0 {
-        (map_x as f64 - player.x + (1 - step_x) as f64 / 2.0) / ray_dir_x
+    // Calcular distancia perpendicular a la pared para evitar distorsión,
+    // y la posición del impacto a lo largo de la cara de la pared
+    let (perp_wall_dist, wall_x) = if side == 0 {
+        let distance = perp_distance(map_x, origin_x, step_x, ray_dir_x);
+        (distance, wall_offset(origin_y, distance, ray_dir_y))
+    } else {
+        let distance = perp_distance(map_y, origin_y, step_y, ray_dir_y);
+        (distance, wall_offset(origin_x, distance, ray_dir_x))
+    };
+
+    // La celda vacía anterior a la pared es la que se cruzó en el último paso, y la cara
+    // golpeada es la que mira hacia ella
+    let (near, face) = if side == 0 {
+        ((map_x - step_x, map_y), if step_x > 0 { Side::West } else { Side::East })
+    } else {
+        ((map_x, map_y - step_y), if step_y > 0 { Side::North } else { Side::South })
+    };
+
+    Trace {
+        distance: perp_wall_dist,
+        side,
+        wall: (map_x, map_y),
+        near,
+        face,
+        wall_x,
+        fogged: false,
+        #[cfg(feature = "ray-stats")]
+        steps,
+    }
+}
+
+/// Distancia a la que el tramo cruza el plano de la pared delgada de `cell`, y posición del
+/// cruce a lo largo de la pared, si lo cruza entre `enter` y `exit` por una parte sólida.
+fn thin_wall_hit(wall: ThinWall, cell: (isize, isize), segment: RaySegment, enter: f64, exit: f64) -> Option<(f64, f64)> {
+    let (origin_x, origin_y) = segment.origin;
+    let (ray_dir_x, ray_dir_y) = segment.dir;
+    // Eje que cruza el plano y eje a lo largo de la pared
+    let (plane, origin_across, dir_across, cell_along, origin_along, dir_along) = if wall.runs_east_west {
+        (cell.1 as f64 + 0.5, origin_y, ray_dir_y, cell.0 as f64, origin_x, ray_dir_x)
     } else {
-        (map_y as f64 - player.y + (1 - step_y) as f64 / 2.0) / ray_dir_y
+        (cell.0 as f64 + 0.5, origin_x, ray_dir_x, cell.1 as f64, origin_y, ray_dir_y)
     };
+    if dir_across == 0.0 {
+        return None; // Paralelo al plano
+    }
+
+    let distance = (plane - origin_across) / dir_across;
+    if distance < enter || distance > exit {
+        return None;
+    }
+    let offset = (origin_along + distance * dir_along - cell_along).clamp(0.0, 1.0);
+    wall.is_solid_at(offset).then_some((distance, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::parse_map;
+
+    const EPSILON: f64 = 1e-9;
 
-    (perp_wall_dist, side == 1)
+    /// Pasillo de 5 celdas con una pared delgada de norte a sur en (4, 1), en x = 4.5, una reja
+    /// de norte a sur en (4, 3), y una fila libre entre ambas.
+    fn corridor() -> Map {
+        parse_map("1111111\n1P00|01\n1000001\n1000:01\n1111111\n").unwrap().0
+    }
+
+    fn cast(map: &Map, x: f64, y: f64, direction: f64) -> RayHit {
+        cast_ray(map, &Player::new(x, y, direction), 0.0, 100.0)
+    }
+
+    #[test]
+    fn dead_on_hit_stops_at_plane() {
+        let hit = cast(&corridor(), 1.5, 1.5, 0.0);
+        assert!((hit.distance - 3.0).abs() < EPSILON);
+        assert_eq!((hit.near_x, hit.near_y), (4, 1));
+        assert_eq!(hit.face, Side::West);
+        assert!((hit.wall_x - 0.5).abs() < EPSILON);
+        assert_eq!(hit.wall_type, 0);
+    }
+
+    #[test]
+    fn hit_from_behind_shows_other_face() {
+        let hit = cast(&corridor(), 5.5, 1.5, std::f64::consts::PI);
+        assert!((hit.distance - 1.0).abs() < EPSILON);
+        assert_eq!(hit.face, Side::East);
+    }
+
+    #[test]
+    fn ray_just_inside_cell_edge_hits() {
+        // Cruza x = 4.5 en y = 2 - 1e-6, todavía dentro de la celda de la pared
+        let angle = (0.5 - 1e-6_f64).atan2(3.0);
+        let hit = cast(&corridor(), 1.5, 1.5, angle);
+        assert_eq!(hit.wall_type, 0);
+        assert!((hit.distance - 3.0 / angle.cos()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn grazing_ray_past_cell_edge_misses() {
+        // Cruza x = 4.5 en y = 2 + 1e-6, ya en la fila libre, y sigue hasta el borde
+        let angle = (0.5 + 1e-6_f64).atan2(3.0);
+        let hit = cast(&corridor(), 1.5, 1.5, angle);
+        assert_eq!(hit.wall_type, 1);
+        assert_eq!(hit.face, Side::West);
+        assert!((hit.distance - 4.5 / angle.cos()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn ray_parallel_to_plane_misses() {
+        // Sube por la celda de la pared a la izquierda del plano sin cruzarlo
+        let hit = cast(&corridor(), 4.2, 2.5, -std::f64::consts::FRAC_PI_2);
+        assert_eq!(hit.wall_type, 1);
+        assert_eq!(hit.face, Side::South);
+        assert!((hit.distance - 1.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn fence_stops_rays_only_at_bars() {
+        let map = corridor();
+        // En la mitad de la celda hay un barrote; a 5/8 hay un hueco
+        let bar = cast(&map, 1.5, 3.5, 0.0);
+        assert_eq!(bar.wall_type, 0);
+        assert!((bar.distance - 3.0).abs() < EPSILON);
+        let gap = cast(&map, 1.5, 3.625, 0.0);
+        assert_eq!(gap.wall_type, 1);
+        assert!((gap.distance - 4.5).abs() < EPSILON);
+    }
 }
@@ -0,0 +1,179 @@
+//! Matemática pura del raycasting (DDA y proyección), sin dependencias del mapa ni del jugador.
+
+// Margen dentro del cual las distancias a la siguiente línea vertical y horizontal se
+// consideran empatadas. En un empate siempre se avanza en y, para que el ruido de punto
+// flotante no haga alternar la cara de la pared entre columnas vecinas en las esquinas.
+const TIE_EPSILON: f64 = 1e-9;
+
+/// Distancia que recorre el rayo entre dos líneas consecutivas de la rejilla en un eje.
+/// Un rayo paralelo al eje nunca las cruza, así que la distancia es infinita.
+pub fn delta_dist(ray_dir: f64) -> f64 {
+    if ray_dir != 0.0 {
+        (1.0 / ray_dir).abs()
+    } else {
+        f64::INFINITY
+    }
+}
+
+/// Paso en la rejilla (-1 o 1) y distancia inicial del rayo hasta la primera línea de la rejilla en un eje.
+///
+/// # Arguments
+/// * `pos` - Posición del origen del rayo en el eje.
+/// * `cell` - Celda que contiene el origen en el eje.
+/// * `ray_dir` - Componente de la dirección del rayo en el eje.
+/// * `delta` - Resultado de `delta_dist` para ese eje.
+pub fn initial_side_dist(pos: f64, cell: isize, ray_dir: f64, delta: f64) -> (isize, f64) {
+    if ray_dir < 0.0 {
+        (-1, (pos - cell as f64) * delta)
+    } else {
+        (1, (cell as f64 + 1.0 - pos) * delta)
+    }
+}
+
+/// Decide el eje del siguiente paso del DDA: `true` si la línea vertical (eje x) está más cerca.
+/// Los empates avanzan en y.
+pub fn steps_along_x(side_dist_x: f64, side_dist_y: f64) -> bool {
+    side_dist_x < side_dist_y - TIE_EPSILON
+}
+
+/// Distancia perpendicular al plano de la cámara hasta la cara de la celda golpeada en un eje.
+/// Usar la distancia perpendicular en lugar de la euclidiana evita el efecto ojo de pez.
+pub fn perp_distance(cell: isize, pos: f64, step: isize, ray_dir: f64) -> f64 {
+    (cell as f64 - pos + (1 - step) as f64 / 2.0) / ray_dir
+}
+
+/// Posición fraccionaria (0.0 a 1.0) del impacto a lo largo de la cara de la pared.
+///
+/// # Arguments
+/// * `pos` - Posición del origen en el eje paralelo a la cara.
+/// * `distance` - Distancia perpendicular a la pared.
+/// * `ray_dir` - Componente de la dirección del rayo en el eje paralelo a la cara.
+pub fn wall_offset(pos: f64, distance: f64, ray_dir: f64) -> f64 {
+    let hit = pos + distance * ray_dir;
+    hit - hit.floor()
+}
+
+/// Filas de inicio y fin (relativas al borde superior de la vista) de una franja de pared
+/// a `distance`, centrada verticalmente y recortada a `view_height`.
+pub fn wall_slice(distance: f64, view_height: usize) -> (usize, usize) {
+    let wall_height = ((view_height as f64 / distance) as usize).min(view_height);
+    let start = (view_height / 2).saturating_sub(wall_height / 2);
+    let end = view_height / 2 + wall_height / 2;
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 1e-12;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < EPSILON, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn delta_dist_is_inverse_of_component() {
+        assert_close(delta_dist(1.0), 1.0);
+        assert_close(delta_dist(-1.0), 1.0);
+        assert_close(delta_dist(0.5), 2.0);
+        assert_close(delta_dist(-0.25), 4.0);
+    }
+
+    #[test]
+    fn delta_dist_axis_aligned_ray_never_crosses() {
+        assert_eq!(delta_dist(0.0), f64::INFINITY);
+        assert_eq!(delta_dist(-0.0), f64::INFINITY);
+    }
+
+    #[test]
+    fn delta_dist_with_non_unit_direction() {
+        // Con una dirección de largo 2 las distancias se miden en unidades de ese vector
+        assert_close(delta_dist(2.0), 0.5);
+        assert_close(delta_dist(-4.0), 0.25);
+    }
+
+    #[test]
+    fn initial_side_dist_positive_and_negative_directions() {
+        assert_eq!(initial_side_dist(2.25, 2, 1.0, 1.0), (1, 0.75));
+        assert_eq!(initial_side_dist(2.25, 2, -1.0, 1.0), (-1, 0.25));
+        let (step, dist) = initial_side_dist(2.25, 2, 0.5, 2.0);
+        assert_eq!(step, 1);
+        assert_close(dist, 1.5);
+    }
+
+    #[test]
+    fn initial_side_dist_starting_on_grid_line() {
+        // Sobre la línea x = 3: hacia adelante falta una celda entera, hacia atrás nada
+        assert_eq!(initial_side_dist(3.0, 3, 1.0, 1.0), (1, 1.0));
+        assert_eq!(initial_side_dist(3.0, 3, -1.0, 1.0), (-1, 0.0));
+    }
+
+    #[test]
+    fn initial_side_dist_axis_aligned_is_infinite() {
+        // Un rayo sin componente en el eje avanza "hacia adelante" a distancia infinita
+        let (step, dist) = initial_side_dist(2.5, 2, 0.0, delta_dist(0.0));
+        assert_eq!(step, 1);
+        assert_eq!(dist, f64::INFINITY);
+    }
+
+    #[test]
+    fn steps_along_x_picks_nearest_line() {
+        assert!(steps_along_x(0.5, 1.0));
+        assert!(!steps_along_x(1.0, 0.5));
+        assert!(steps_along_x(0.0, f64::INFINITY));
+        assert!(!steps_along_x(f64::INFINITY, 0.0));
+    }
+
+    #[test]
+    fn steps_along_x_ties_advance_in_y() {
+        assert!(!steps_along_x(1.0, 1.0));
+        // Dentro del margen sigue siendo empate, aunque x sea un poco menor
+        assert!(!steps_along_x(1.0 - TIE_EPSILON / 2.0, 1.0));
+        // Fuera del margen x gana
+        assert!(steps_along_x(1.0 - TIE_EPSILON * 2.0, 1.0));
+    }
+
+    #[test]
+    fn perp_distance_both_directions() {
+        // Pared en la celda 5 vista desde 2.5: hacia +x la cara está en 5.0, hacia -x (celda 0) en 1.0
+        assert_close(perp_distance(5, 2.5, 1, 1.0), 2.5);
+        assert_close(perp_distance(0, 2.5, -1, -1.0), 1.5);
+    }
+
+    #[test]
+    fn perp_distance_with_non_unit_direction() {
+        // La distancia se mide en unidades del vector de dirección
+        assert_close(perp_distance(5, 2.5, 1, 2.0), 1.25);
+        assert_close(perp_distance(5, 2.5, 1, 0.5), 5.0);
+    }
+
+    #[test]
+    fn perp_distance_from_grid_line() {
+        assert_close(perp_distance(4, 3.0, 1, 1.0), 1.0);
+        assert_close(perp_distance(2, 3.0, -1, -1.0), 0.0);
+    }
+
+    #[test]
+    fn wall_offset_is_fractional_part() {
+        assert_close(wall_offset(1.25, 2.0, 0.5), 0.25);
+        assert_close(wall_offset(1.25, 1.0, -0.5), 0.75);
+        assert_close(wall_offset(3.0, 0.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn wall_slice_centered_and_clamped() {
+        assert_eq!(wall_slice(1.0, 100), (0, 100));
+        assert_eq!(wall_slice(2.0, 100), (25, 75));
+        assert_eq!(wall_slice(4.0, 100), (38, 62));
+        // Muy cerca la franja se recorta a la altura de la vista
+        assert_eq!(wall_slice(0.01, 100), (0, 100));
+        // Muy lejos la franja desaparece
+        assert_eq!(wall_slice(1000.0, 100), (50, 50));
+    }
+
+    #[test]
+    fn wall_slice_odd_height() {
+        assert_eq!(wall_slice(1.0, 101), (0, 100));
+    }
+}
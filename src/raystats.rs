@@ -0,0 +1,132 @@
+use crate::color::Color;
+
+pub const OVERLAY_ROWS: usize = 4; // Filas superiores de cada columna que se tiñen según su costo
+const OVERLAY_BLEND: f64 = 0.6;    // Cuánto del color del costo se mezcla con la imagen
+
+const FEW_STEPS: u32 = 16;  // Menos pasos que esto es barato (verde)
+const MANY_STEPS: u32 = 64; // Menos pasos que esto es aceptable (amarillo); más es caro (rojo)
+
+/// Pasos del DDA por columna en un cuadro: mínimo, máximo y total para el promedio.
+#[derive(Clone, Copy, Default)]
+pub struct StepStats {
+    min: u32,
+    max: u32,
+    total: u64,
+    columns: u64,
+}
+
+impl StepStats {
+    pub fn record(&mut self, steps: u32) {
+        self.min = if self.columns == 0 { steps } else { self.min.min(steps) };
+        self.max = self.max.max(steps);
+        self.total += steps as u64;
+        self.columns += 1;
+    }
+
+    /// Junta las estadísticas de dos vistas (pantalla dividida).
+    pub fn merge(self, other: StepStats) -> StepStats {
+        match (self.columns, other.columns) {
+            (0, _) => other,
+            (_, 0) => self,
+            _ => StepStats {
+                min: self.min.min(other.min),
+                max: self.max.max(other.max),
+                total: self.total + other.total,
+                columns: self.columns + other.columns,
+            },
+        }
+    }
+
+    /// Mínimo, promedio y máximo de pasos por columna.
+    pub fn summary(&self) -> (u32, f64, u32) {
+        let average = if self.columns == 0 { 0.0 } else { self.total as f64 / self.columns as f64 };
+        (self.min, average, self.max)
+    }
+}
+
+/// Color de la franja de costo de una columna: verde, amarillo o rojo según sus pasos.
+fn step_color(steps: u32) -> Color {
+    if steps < FEW_STEPS {
+        Color::from_rgb(0, 255, 0)
+    } else if steps < MANY_STEPS {
+        Color::from_rgb(255, 255, 0)
+    } else {
+        Color::from_rgb(255, 0, 0)
+    }
+}
+
+/// Color ya dibujado de la franja, mezclado con el color del costo de la columna.
+pub fn tint(pixel: Color, steps: u32) -> Color {
+    pixel.lerp(step_color(steps), OVERLAY_BLEND).drawable()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::parse_map;
+    use crate::player::Player;
+    use crate::raycaster::cast_ray;
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    /// Sala de 3x2 celdas libres, con el jugador en el centro de la celda (1, 1).
+    const ROOM: &str = "11111\n1P001\n10001\n11111\n";
+
+    fn steps(text: &str, direction: f64, max_distance: f64) -> u32 {
+        let map = parse_map(text).unwrap().0;
+        cast_ray(&map, &Player::new(1.5, 1.5, direction), 0.0, max_distance).steps
+    }
+
+    #[test]
+    fn straight_rays_count_one_step_per_cell_crossed() {
+        // Al este cruza a (2, 1) y (3, 1) y golpea (4, 1)
+        assert_eq!(steps(ROOM, 0.0, f64::INFINITY), 3);
+        // Al oeste golpea (0, 1) al primer paso
+        assert_eq!(steps(ROOM, PI, f64::INFINITY), 1);
+        // Al sur cruza a (1, 2) y golpea (1, 3)
+        assert_eq!(steps(ROOM, FRAC_PI_2, f64::INFINITY), 2);
+    }
+
+    #[test]
+    fn diagonal_ray_steps_through_both_axes() {
+        // A 45° las dos líneas quedan a la misma distancia: el empate avanza en y a (1, 2), luego
+        // x a (2, 2) y, en el siguiente empate, y a (2, 3), que es pared
+        assert_eq!(steps(ROOM, FRAC_PI_4, f64::INFINITY), 3);
+    }
+
+    #[test]
+    fn fog_stops_counting_steps() {
+        // La primera línea al este está a 0.5 y la segunda a 1.5, ya dentro de la niebla
+        assert_eq!(steps(ROOM, 0.0, 1.0), 1);
+        assert_eq!(steps(ROOM, 0.0, 0.25), 0);
+    }
+
+    #[test]
+    fn thin_wall_stops_the_ray_inside_its_cell() {
+        // La pared delgada de (2, 1) detiene el rayo sin llegar a (3, 1)
+        assert_eq!(steps("11111\n1P|01\n10001\n11111\n", 0.0, f64::INFINITY), 1);
+    }
+
+    #[test]
+    fn stats_summarize_and_merge() {
+        let mut left = StepStats::default();
+        assert_eq!(left.summary(), (0, 0.0, 0));
+        for steps in [3, 1, 8] {
+            left.record(steps);
+        }
+        assert_eq!(left.summary(), (1, 4.0, 8));
+
+        let mut right = StepStats::default();
+        right.record(20);
+        assert_eq!(left.merge(right).summary(), (1, 8.0, 20));
+        assert_eq!(left.merge(StepStats::default()).summary(), left.summary());
+        assert_eq!(StepStats::default().merge(right).summary(), (20, 20.0, 20));
+    }
+
+    #[test]
+    fn step_colors_follow_thresholds() {
+        assert_eq!(step_color(FEW_STEPS - 1), Color::from_rgb(0, 255, 0));
+        assert_eq!(step_color(FEW_STEPS), Color::from_rgb(255, 255, 0));
+        assert_eq!(step_color(MANY_STEPS - 1), Color::from_rgb(255, 255, 0));
+        assert_eq!(step_color(MANY_STEPS), Color::from_rgb(255, 0, 0));
+    }
+}
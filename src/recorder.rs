@@ -0,0 +1,247 @@
+use crate::color::Color;
+use crate::paths;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAX_FRAMES: usize = 300; // Cuadros guardados como máximo; al llenarse se descartan los más viejos
+const DOWNSCALE: usize = 2;    // Factor de reducción de cada cuadro capturado
+const FRAME_RATE: u32 = 60;    // Cuadros por segundo del juego, para calcular la duración de cada cuadro del GIF
+
+/// Graba los cuadros presentados en memoria y los guarda como un GIF animado.
+pub struct Recorder {
+    frames: VecDeque<RgbaImage>,
+    recording: bool,
+    frame_counter: usize,
+    every: usize, // Se captura uno de cada `every` cuadros
+    size: Option<(usize, usize)>, // Tamaño de los cuadros de la grabación actual, fijado por el primero
+    saved_sender: Sender<Result<PathBuf, String>>, // Lo clona cada hilo que guarda un GIF
+    saved: Receiver<Result<PathBuf, String>>,       // Resultados de los guardados terminados
+}
+
+impl Recorder {
+    pub fn new(every: usize) -> Self {
+        let (saved_sender, saved) = mpsc::channel();
+        Self {
+            frames: VecDeque::new(),
+            recording: false,
+            frame_counter: 0,
+            every: every.max(1),
+            size: None,
+            saved_sender,
+            saved,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// Empieza a grabar, o termina la grabación y guarda el GIF en un hilo aparte
+    /// para que el juego no se detenga mientras se codifica. Al terminar devuelve la ruta en
+    /// la que se guarda el GIF, si había cuadros que guardar; `poll_saved` avisa cuando está listo.
+    pub fn toggle(&mut self) -> Option<PathBuf> {
+        if !self.recording {
+            self.recording = true;
+            self.frame_counter = 0;
+            self.size = None;
+            return None;
+        }
+        self.stop(&paths::output_dir("recordings"))
+    }
+
+    /// Termina la grabación y guarda el GIF en `dir`.
+    fn stop(&mut self, dir: &Path) -> Option<PathBuf> {
+        self.recording = false;
+        let frames: Vec<RgbaImage> = self.frames.drain(..).collect();
+        if frames.is_empty() {
+            return None;
+        }
+
+        let delay = Delay::from_numer_denom_ms(self.every as u32 * 1000, FRAME_RATE);
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+        let path = dir.join(format!("recording_{}.gif", timestamp));
+        let saved_path = path.clone();
+        let sender = self.saved_sender.clone();
+        thread::spawn(move || {
+            let result = save_gif(&path, frames, delay)
+                .map(|()| path.clone())
+                .map_err(|err| format!("cannot save recording {}: {}", path.display(), err));
+            // Si el juego ya terminó nadie recibe el resultado, y no pasa nada
+            let _ = sender.send(result);
+        });
+        Some(saved_path)
+    }
+
+    /// Resultado de un guardado que terminó desde la última consulta: la ruta del GIF o el error.
+    /// El bucle principal lo consulta cada cuadro para avisar en pantalla.
+    pub fn poll_saved(&self) -> Option<Result<PathBuf, String>> {
+        self.saved.try_recv().ok()
+    }
+
+    /// Registra un cuadro presentado (colores 0xRRGGBB) y lo guarda reducido si toca capturarlo.
+    /// Si la ventana cambia de tamaño durante la grabación, los cuadros se siguen escalando al
+    /// tamaño del primero para que todos los del GIF midan lo mismo.
+    pub fn capture(&mut self, buffer: &[u32], width: usize, height: usize) {
+        if !self.recording {
+            return;
+        }
+
+        let capture = self.frame_counter.is_multiple_of(self.every);
+        self.frame_counter += 1;
+        if !capture {
+            return;
+        }
+
+        let (out_width, out_height) = *self
+            .size
+            .get_or_insert(((width / DOWNSCALE).max(1), (height / DOWNSCALE).max(1)));
+        let image = RgbaImage::from_fn(out_width as u32, out_height as u32, |x, y| {
+            let source_x = x as usize * width / out_width;
+            let source_y = y as usize * height / out_height;
+            let color = Color::from_u32(buffer[source_y * width + source_x]);
+            image::Rgba([color.r(), color.g(), color.b(), 255])
+        });
+
+        if self.frames.len() == MAX_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(image);
+    }
+}
+
+fn save_gif(path: &Path, frames: Vec<RgbaImage>, delay: Delay) -> Result<(), String> {
+    let file = File::create(path).map_err(|err| err.to_string())?;
+    let mut encoder = GifEncoder::new(file);
+    encoder.set_repeat(Repeat::Infinite).map_err(|err| err.to_string())?;
+    encoder
+        .encode_frames(frames.into_iter().map(|image| Frame::from_parts(image, 0, 0, delay)))
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use image::codecs::gif::GifDecoder;
+    use image::AnimationDecoder;
+    use std::time::{Duration, Instant};
+
+    const RED: u32 = 0xFF0000;
+    const GREEN: u32 = 0x00FF00;
+    const BLUE: u32 = 0x0000FF;
+
+    fn recording(every: usize) -> Recorder {
+        let mut recorder = Recorder::new(every);
+        assert_eq!(recorder.toggle(), None);
+        assert!(recorder.is_recording());
+        recorder
+    }
+
+    fn capture_filled(recorder: &mut Recorder, color: u32, width: usize, height: usize) {
+        recorder.capture(&vec![color; width * height], width, height);
+    }
+
+    /// Espera a que termine el hilo que guarda el GIF.
+    fn wait_saved(recorder: &Recorder) -> Result<PathBuf, String> {
+        let start = Instant::now();
+        loop {
+            if let Some(result) = recorder.poll_saved() {
+                return result;
+            }
+            assert!(start.elapsed() < Duration::from_secs(10), "the recording was never saved");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn nothing_is_captured_while_stopped() {
+        let mut recorder = Recorder::new(1);
+        capture_filled(&mut recorder, RED, 8, 6);
+        assert!(recorder.frames.is_empty());
+    }
+
+    #[test]
+    fn captures_one_in_every_frames_downscaled() {
+        let mut recorder = recording(3);
+        for _ in 0..7 {
+            capture_filled(&mut recorder, RED, 8, 6);
+        }
+        // Se capturan los cuadros 0, 3 y 6
+        assert_eq!(recorder.frames.len(), 3);
+        let frame = &recorder.frames[0];
+        assert_eq!(frame.dimensions(), (4, 3));
+        assert_eq!(frame.get_pixel(3, 2), &image::Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn frame_size_is_fixed_by_the_first_capture() {
+        let mut recorder = recording(1);
+        capture_filled(&mut recorder, RED, 8, 6);
+        capture_filled(&mut recorder, GREEN, 16, 12);
+        assert_eq!(recorder.frames[1].dimensions(), (4, 3));
+        assert_eq!(recorder.frames[1].get_pixel(0, 0), &image::Rgba([0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn oldest_frames_are_dropped_at_the_limit() {
+        let mut recorder = recording(1);
+        for index in 0..MAX_FRAMES + 5 {
+            capture_filled(&mut recorder, index as u32 & 0xFF, 2, 2);
+        }
+        assert_eq!(recorder.frames.len(), MAX_FRAMES);
+        // El primero que queda es el sexto capturado (azul = 5)
+        assert_eq!(recorder.frames[0].get_pixel(0, 0)[2], 5);
+    }
+
+    #[test]
+    fn stopping_without_frames_saves_nothing() {
+        let mut recorder = recording(1);
+        let temp = TestDir::new("recorder", "empty");
+        assert_eq!(recorder.stop(temp.path()), None);
+        assert!(!recorder.is_recording());
+        assert!(recorder.poll_saved().is_none());
+    }
+
+    #[test]
+    fn saved_gif_round_trips() {
+        let temp = TestDir::new("recorder", "round-trip");
+        let mut recorder = recording(1);
+        for color in [RED, GREEN, BLUE] {
+            capture_filled(&mut recorder, color, 8, 8);
+        }
+        let path = recorder.stop(temp.path()).unwrap();
+        assert_eq!(path.parent(), Some(temp.path()));
+        assert_eq!(wait_saved(&recorder), Ok(path.clone()));
+        assert!(recorder.frames.is_empty());
+
+        let frames = GifDecoder::new(File::open(&path).unwrap()).unwrap().into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 3);
+        for (frame, expected) in frames.iter().zip([[255, 0, 0], [0, 255, 0], [0, 0, 255]]) {
+            assert_eq!(frame.buffer().dimensions(), (4, 4));
+            let pixel = frame.buffer().get_pixel(2, 2);
+            // La paleta del GIF puede redondear un poco los colores
+            for channel in 0..3 {
+                assert!((pixel[channel] as i32 - expected[channel]).abs() <= 8, "{:?} != {:?}", pixel, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn failed_save_is_reported() {
+        let temp = TestDir::new("recorder", "failed");
+        // Un archivo en lugar de la carpeta hace que no se pueda crear el GIF
+        let not_a_dir = temp.join("file");
+        std::fs::write(&not_a_dir, "").unwrap();
+        let mut recorder = recording(1);
+        capture_filled(&mut recorder, RED, 4, 4);
+        let path = recorder.stop(&not_a_dir).unwrap();
+        let err = wait_saved(&recorder).unwrap_err();
+        assert!(err.contains(&path.display().to_string()), "{}", err);
+    }
+}
@@ -0,0 +1,137 @@
+/// Escalas de resolución interna disponibles, de mayor a menor calidad.
+const SCALES: [f64; 4] = [1.0, 0.85, 0.7, 0.5];
+
+const FRAME_BUDGET: f64 = 1.0 / 60.0; // Presupuesto de tiempo por cuadro (en segundos)
+const HEADROOM: f64 = 0.7;            // Fracción del presupuesto por debajo de la cual se puede subir la escala
+const DROP_AFTER: f64 = 0.5;          // Segundos sobre el presupuesto antes de bajar la escala
+const RAISE_AFTER: f64 = 2.0;         // Segundos con holgura antes de subir la escala
+const SMOOTHING: f64 = 0.1;           // Peso de cada cuadro en el promedio móvil
+
+/// Ajusta la resolución interna del render según el costo promedio de cada cuadro.
+///
+/// Baja un escalón cuando el promedio supera el presupuesto durante `DROP_AFTER` segundos,
+/// y sube uno cuando queda holgura durante `RAISE_AFTER` segundos. Entre ambos umbrales
+/// no cambia nada, lo que evita que la escala oscile.
+pub struct ResolutionController {
+    level: usize,
+    average_cost: f64,
+    over_budget: f64,
+    under_budget: f64,
+}
+
+impl ResolutionController {
+    pub fn new() -> Self {
+        Self {
+            level: 0,
+            average_cost: 0.0,
+            over_budget: 0.0,
+            under_budget: 0.0,
+        }
+    }
+
+    /// Escala actual de la resolución interna (1.0 es resolución completa).
+    pub fn scale(&self) -> f64 {
+        SCALES[self.level]
+    }
+
+    /// Registra un cuadro y decide si cambia la escala.
+    ///
+    /// # Argumentos
+    ///
+    /// * `cost` - Tiempo de trabajo del cuadro, sin contar la espera para limitar los FPS (en segundos).
+    /// * `dt` - Tiempo real transcurrido desde el cuadro anterior (en segundos).
+    pub fn update(&mut self, cost: f64, dt: f64) {
+        self.average_cost += (cost - self.average_cost) * SMOOTHING;
+
+        if self.average_cost > FRAME_BUDGET {
+            self.over_budget += dt;
+            self.under_budget = 0.0;
+        } else if self.average_cost < FRAME_BUDGET * HEADROOM {
+            self.under_budget += dt;
+            self.over_budget = 0.0;
+        } else {
+            self.over_budget = 0.0;
+            self.under_budget = 0.0;
+        }
+
+        if self.over_budget >= DROP_AFTER && self.level + 1 < SCALES.len() {
+            self.level += 1;
+            self.over_budget = 0.0;
+        } else if self.under_budget >= RAISE_AFTER && self.level > 0 {
+            self.level -= 1;
+            self.under_budget = 0.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f64 = 1.0 / 60.0;
+
+    /// Registra `seconds` segundos de cuadros de costo `cost`, a 60 cuadros por segundo.
+    fn run(controller: &mut ResolutionController, cost: f64, seconds: f64) {
+        for _ in 0..(seconds / DT).round() as usize {
+            controller.update(cost, DT);
+        }
+    }
+
+    #[test]
+    fn starts_at_full_resolution() {
+        assert_eq!(ResolutionController::new().scale(), 1.0);
+    }
+
+    #[test]
+    fn sustained_overload_drops_one_step_at_a_time() {
+        let mut controller = ResolutionController::new();
+        run(&mut controller, FRAME_BUDGET * 2.0, 0.4);
+        assert_eq!(controller.scale(), SCALES[0]);
+        run(&mut controller, FRAME_BUDGET * 2.0, 0.4);
+        assert_eq!(controller.scale(), SCALES[1]);
+        run(&mut controller, FRAME_BUDGET * 2.0, 10.0);
+        assert_eq!(controller.scale(), SCALES[SCALES.len() - 1]);
+    }
+
+    #[test]
+    fn single_spike_does_not_drop() {
+        let mut controller = ResolutionController::new();
+        run(&mut controller, FRAME_BUDGET * 0.5, 1.0);
+        controller.update(FRAME_BUDGET * 5.0, DT);
+        run(&mut controller, FRAME_BUDGET * 0.5, 1.0);
+        assert_eq!(controller.scale(), SCALES[0]);
+    }
+
+    #[test]
+    fn headroom_raises_after_delay() {
+        let mut controller = ResolutionController::new();
+        run(&mut controller, FRAME_BUDGET * 2.0, 1.2);
+        assert_eq!(controller.scale(), SCALES[2]);
+        run(&mut controller, FRAME_BUDGET * 0.3, 1.5);
+        assert_eq!(controller.scale(), SCALES[2]);
+        run(&mut controller, FRAME_BUDGET * 0.3, 1.0);
+        assert_eq!(controller.scale(), SCALES[1]);
+        run(&mut controller, FRAME_BUDGET * 0.3, 10.0);
+        assert_eq!(controller.scale(), SCALES[0]);
+    }
+
+    #[test]
+    fn cost_between_thresholds_holds_scale() {
+        let mut controller = ResolutionController::new();
+        run(&mut controller, FRAME_BUDGET * 2.0, 0.8);
+        assert_eq!(controller.scale(), SCALES[1]);
+        run(&mut controller, FRAME_BUDGET * 0.85, 10.0);
+        assert_eq!(controller.scale(), SCALES[1]);
+    }
+
+    #[test]
+    fn alternating_costs_do_not_oscillate() {
+        // Cuadros caros y baratos alternados: el promedio queda entre los umbrales
+        let mut controller = ResolutionController::new();
+        for frame in 0..600 {
+            let cost = if frame % 2 == 0 { FRAME_BUDGET * 1.2 } else { FRAME_BUDGET * 0.5 };
+            controller.update(cost, DT);
+        }
+        assert_eq!(controller.scale(), SCALES[0]);
+    }
+}
@@ -0,0 +1,411 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const BEST_FILE: &str = "best.txt"; // Récord personal, dentro de la carpeta de speedruns
+
+/// Cómo va un tramo comparado con el récord personal.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SplitStatus {
+    Gold,   // El tramo es el más rápido jamás hecho en ese nivel
+    Ahead,  // El tiempo acumulado va por delante del récord
+    Behind, // El tiempo acumulado va por detrás del récord
+}
+
+impl SplitStatus {
+    /// Posición en la tabla de colores de tiempos parciales de la paleta.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            SplitStatus::Gold => "gold",
+            SplitStatus::Ahead => "ahead",
+            SplitStatus::Behind => "behind",
+        }
+    }
+}
+
+/// Tiempo parcial: el nivel terminado y el tiempo acumulado de la partida al terminarlo.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Split {
+    pub level: String,
+    pub time: f64,
+}
+
+/// Reloj del modo speedrun. Cuenta pasos fijos de la simulación, no tiempo real, así que una
+/// repetición de la misma partida da exactamente el mismo tiempo; ralentizar la simulación
+/// alarga la partida en tiempo real pero no su tiempo. Empieza al terminar la introducción.
+pub struct SpeedrunTimer {
+    ticks: u64,
+    step: f64,
+    splits: Vec<Split>,
+    finished: bool,
+}
+
+impl SpeedrunTimer {
+    /// Reloj para una simulación que avanza en pasos de `step` segundos.
+    pub fn new(step: f64) -> Self {
+        Self { ticks: 0, step, splits: Vec::new(), finished: false }
+    }
+
+    /// Suma los `ticks` pasos de simulación de este cuadro, salvo durante la introducción o si
+    /// la partida ya terminó.
+    pub fn advance(&mut self, ticks: u32, intro_playing: bool) {
+        if !intro_playing && !self.finished {
+            self.ticks += ticks as u64;
+        }
+    }
+
+    pub fn seconds(&self) -> f64 {
+        self.ticks as f64 * self.step
+    }
+
+    /// Registra el final de `level` con el tiempo actual.
+    pub fn split(&mut self, level: &str) {
+        if !self.finished {
+            self.splits.push(Split { level: level.to_string(), time: self.seconds() });
+        }
+    }
+
+    /// Registra el último nivel y detiene el reloj.
+    pub fn finish(&mut self, level: &str) {
+        self.split(level);
+        self.finished = true;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn splits(&self) -> &[Split] {
+        &self.splits
+    }
+
+    /// Vuelve a empezar la partida desde cero, sin tiempos parciales.
+    pub fn reset(&mut self) {
+        self.ticks = 0;
+        self.splits.clear();
+        self.finished = false;
+    }
+}
+
+/// Duración del tramo `index`: lo que pasó entre el tiempo parcial anterior y este.
+fn segment(splits: &[Split], index: usize) -> f64 {
+    splits[index].time - index.checked_sub(1).map_or(0.0, |previous| splits[previous].time)
+}
+
+/// Récord personal: los tiempos parciales de la partida completa más rápida y el mejor tramo
+/// hecho en cada nivel, de cualquier partida del mismo recorrido.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct PersonalBest {
+    pub splits: Vec<Split>,
+    pub best_segments: Vec<f64>,
+}
+
+impl PersonalBest {
+    /// Lee el récord escrito por `to_text`: una línea por nivel con su nombre, el tiempo parcial
+    /// y el mejor tramo separados por tabuladores. Las líneas con `#` son comentarios.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut best = PersonalBest::default();
+        for (number, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [level, time, segment] = fields[..] else {
+                return Err(format!("line {}: expected 3 tab-separated fields, got {}", number + 1, fields.len()));
+            };
+            let seconds = |value: &str| {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+                    .ok_or_else(|| format!("line {}: invalid time '{}'", number + 1, value))
+            };
+            best.splits.push(Split { level: level.to_string(), time: seconds(time)? });
+            best.best_segments.push(seconds(segment)?);
+        }
+        Ok(best)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# level\tsplit\tbest segment (seconds)\n");
+        for (split, segment) in self.splits.iter().zip(&self.best_segments) {
+            text.push_str(&format!("{}\t{:.6}\t{:.6}\n", split.level, split.time, segment));
+        }
+        text
+    }
+
+    /// Récord guardado en `path`; si todavía no existe, uno vacío.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+
+    /// Si el tiempo parcial `index` de la partida es del mismo nivel que en el récord.
+    fn same_level(&self, splits: &[Split], index: usize) -> bool {
+        self.splits.get(index).is_some_and(|best| best.level == splits[index].level)
+    }
+
+    /// Diferencia entre el tiempo parcial `index` de la partida y el del récord (negativa si va
+    /// por delante), o `None` si el récord no llega a ese nivel.
+    pub fn delta(&self, splits: &[Split], index: usize) -> Option<f64> {
+        self.same_level(splits, index).then(|| splits[index].time - self.splits[index].time)
+    }
+
+    /// Cómo va el tiempo parcial `index` de la partida: oro si su tramo es el mejor hecho en ese
+    /// nivel y, si no, por delante o por detrás del récord. `None` si no hay con qué comparar.
+    pub fn status(&self, splits: &[Split], index: usize) -> Option<SplitStatus> {
+        if !self.same_level(splits, index) {
+            return None;
+        }
+        if segment(splits, index) < self.best_segments[index] {
+            Some(SplitStatus::Gold)
+        } else if splits[index].time < self.splits[index].time {
+            Some(SplitStatus::Ahead)
+        } else {
+            Some(SplitStatus::Behind)
+        }
+    }
+
+    /// Suma una partida terminada: guarda sus mejores tramos y, si es más rápida que el récord
+    /// (o no había), la toma como nuevo récord. Una partida por otro recorrido de niveles
+    /// reemplaza el récord entero. Devuelve `true` si hay nuevo récord.
+    pub fn record(&mut self, splits: &[Split]) -> bool {
+        let same_route = self.splits.len() == splits.len()
+            && self.splits.iter().zip(splits).all(|(best, split)| best.level == split.level);
+        if !same_route {
+            self.splits = splits.to_vec();
+            self.best_segments = (0..splits.len()).map(|index| segment(splits, index)).collect();
+            return true;
+        }
+
+        for (index, best) in self.best_segments.iter_mut().enumerate() {
+            *best = best.min(segment(splits, index));
+        }
+        let faster = match (splits.last(), self.splits.last()) {
+            (Some(run), Some(best)) => run.time < best.time,
+            _ => false,
+        };
+        if faster {
+            self.splits = splits.to_vec();
+        }
+        faster
+    }
+}
+
+/// Tiempo de una partida en modo speedrun como `m:ss.cc`.
+pub fn format_run_time(seconds: f64) -> String {
+    let centiseconds = (seconds * 100.0).floor() as u64;
+    format!("{}:{:02}.{:02}", centiseconds / 6000, centiseconds / 100 % 60, centiseconds % 100)
+}
+
+/// Diferencia con el récord como `+m:ss.cc` o `-m:ss.cc`.
+pub fn format_delta(seconds: f64) -> String {
+    format!("{}{}", if seconds < 0.0 { '-' } else { '+' }, format_run_time(seconds.abs()))
+}
+
+/// Resumen de una partida para exportar: una línea por nivel con el tiempo parcial, la duración
+/// del tramo, la diferencia con el récord y cómo iba, y al final el tiempo total.
+pub fn summary(splits: &[Split], best: &PersonalBest) -> String {
+    let mut text = String::from("level\tsplit\tsegment\tdelta\tstatus\n");
+    for (index, split) in splits.iter().enumerate() {
+        let delta = best.delta(splits, index).map_or_else(|| "-".to_string(), format_delta);
+        let status = best.status(splits, index).map_or("-", SplitStatus::name);
+        text.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\n",
+            split.level,
+            format_run_time(split.time),
+            format_run_time(segment(splits, index)),
+            delta,
+            status
+        ));
+    }
+    let total = splits.last().map_or(0.0, |split| split.time);
+    text.push_str(&format!("total\t{}\n", format_run_time(total)));
+    text
+}
+
+/// Escribe el resumen de la partida en `dir` con un nombre único y devuelve su ruta.
+pub fn export_summary(dir: &Path, splits: &[Split], best: &PersonalBest) -> io::Result<PathBuf> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+    let path = dir.join(format!("splits_{}.txt", timestamp));
+    fs::write(&path, summary(splits, best))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    const STEP: f64 = 1.0 / 60.0;
+
+    fn splits(times: &[(&str, f64)]) -> Vec<Split> {
+        times.iter().map(|&(level, time)| Split { level: level.to_string(), time }).collect()
+    }
+
+    /// Récord de tres niveles: 10 s, 25 s y 40 s, con mejores tramos de 9, 14 y 15 s.
+    fn best() -> PersonalBest {
+        PersonalBest { splits: splits(&[("a", 10.0), ("b", 25.0), ("c", 40.0)]), best_segments: vec![9.0, 14.0, 15.0] }
+    }
+
+    #[test]
+    fn timer_counts_simulation_ticks() {
+        let mut timer = SpeedrunTimer::new(STEP);
+        for _ in 0..120 {
+            timer.advance(1, false);
+        }
+        assert!((timer.seconds() - 2.0).abs() < 1e-9);
+
+        // A media velocidad cada cuadro da la mitad de pasos: el tiempo de la partida es el mismo
+        let mut slow = SpeedrunTimer::new(STEP);
+        for frame in 0..240 {
+            slow.advance(frame % 2, false);
+        }
+        assert_eq!(slow.seconds(), timer.seconds());
+    }
+
+    #[test]
+    fn timer_waits_for_intro_and_stops_when_finished() {
+        let mut timer = SpeedrunTimer::new(STEP);
+        timer.advance(300, true);
+        assert_eq!(timer.seconds(), 0.0);
+        timer.advance(60, false);
+        timer.finish("a");
+        timer.advance(60, false);
+        assert!((timer.seconds() - 1.0).abs() < 1e-9);
+        assert!(timer.is_finished());
+        timer.split("b");
+        assert_eq!(timer.splits().len(), 1);
+    }
+
+    #[test]
+    fn timer_records_splits_and_resets() {
+        let mut timer = SpeedrunTimer::new(STEP);
+        timer.advance(60, false);
+        timer.split("a");
+        timer.advance(30, false);
+        timer.finish("b");
+        assert_eq!(timer.splits().len(), 2);
+        assert!((timer.splits()[1].time - 1.5).abs() < 1e-9);
+        assert!((segment(timer.splits(), 1) - 0.5).abs() < 1e-9);
+
+        timer.reset();
+        assert_eq!(timer.seconds(), 0.0);
+        assert!(timer.splits().is_empty());
+        assert!(!timer.is_finished());
+    }
+
+    #[test]
+    fn split_status_colors() {
+        let best = best();
+        // Tramo de 8 s: mejor que el mejor tramo (9 s)
+        assert_eq!(best.status(&splits(&[("a", 8.0)]), 0), Some(SplitStatus::Gold));
+        // 9.5 s: peor que el mejor tramo, pero por delante de los 10 s del récord
+        assert_eq!(best.status(&splits(&[("a", 9.5)]), 0), Some(SplitStatus::Ahead));
+        // 10 s: igual que el récord cuenta como por detrás
+        assert_eq!(best.status(&splits(&[("a", 10.0)]), 0), Some(SplitStatus::Behind));
+        // Tramo de 13 s en b (oro) aunque el acumulado, 27 s, vaya por detrás de 25 s
+        assert_eq!(best.status(&splits(&[("a", 14.0), ("b", 27.0)]), 1), Some(SplitStatus::Gold));
+        assert_eq!(best.status(&splits(&[("a", 9.5), ("b", 24.0)]), 1), Some(SplitStatus::Ahead));
+        assert_eq!(best.status(&splits(&[("a", 9.5), ("b", 26.0)]), 1), Some(SplitStatus::Behind));
+    }
+
+    #[test]
+    fn other_levels_are_not_compared() {
+        let best = best();
+        assert_eq!(best.status(&splits(&[("x", 1.0)]), 0), None);
+        assert_eq!(best.delta(&splits(&[("x", 1.0)]), 0), None);
+        let longer = splits(&[("a", 9.0), ("b", 20.0), ("c", 30.0), ("d", 40.0)]);
+        assert_eq!(best.status(&longer, 3), None);
+        assert_eq!(PersonalBest::default().status(&splits(&[("a", 1.0)]), 0), None);
+        assert_eq!(best.delta(&longer, 1), Some(-5.0));
+    }
+
+    #[test]
+    fn faster_run_becomes_personal_best() {
+        let mut best = best();
+        assert!(best.record(&splits(&[("a", 8.0), ("b", 24.0), ("c", 39.0)])));
+        assert_eq!(best.splits[2].time, 39.0);
+        assert_eq!(best.best_segments, vec![8.0, 14.0, 15.0]);
+    }
+
+    #[test]
+    fn slower_run_keeps_record_but_saves_golds() {
+        let mut best = best();
+        assert!(!best.record(&splits(&[("a", 12.0), ("b", 24.0), ("c", 45.0)])));
+        assert_eq!(best.splits[2].time, 40.0);
+        assert_eq!(best.best_segments, vec![9.0, 12.0, 15.0]);
+    }
+
+    #[test]
+    fn other_route_replaces_record() {
+        let mut best = best();
+        assert!(best.record(&splits(&[("a", 50.0), ("d", 80.0)])));
+        assert_eq!(best.splits, splits(&[("a", 50.0), ("d", 80.0)]));
+        assert_eq!(best.best_segments, vec![50.0, 30.0]);
+
+        let mut empty = PersonalBest::default();
+        assert!(empty.record(&splits(&[("a", 10.0)])));
+        assert_eq!(empty.best_segments, vec![10.0]);
+    }
+
+    #[test]
+    fn personal_best_file_round_trips() {
+        let dir = TestDir::new("speedrun", "best");
+        let path = dir.join(BEST_FILE);
+        assert_eq!(PersonalBest::load(&path).unwrap(), PersonalBest::default());
+
+        let mut best = best();
+        best.splits[0].level = "maps/with spaces.map".to_string();
+        best.save(&path).unwrap();
+        assert_eq!(PersonalBest::load(&path).unwrap(), best);
+    }
+
+    #[test]
+    fn malformed_personal_best_is_rejected() {
+        assert_eq!(PersonalBest::parse("# comment\n\n").unwrap(), PersonalBest::default());
+        assert_eq!(PersonalBest::parse("a\t1.0\n").unwrap_err(), "line 1: expected 3 tab-separated fields, got 2");
+        assert_eq!(PersonalBest::parse("a\t1.0\t1.0\nb\tsoon\t2.0\n").unwrap_err(), "line 2: invalid time 'soon'");
+        assert!(PersonalBest::parse("a\tNaN\t1.0\n").is_err());
+        assert!(PersonalBest::parse("a\t-1\t1.0\n").is_err());
+    }
+
+    #[test]
+    fn run_time_formatting() {
+        assert_eq!(format_run_time(0.0), "0:00.00");
+        assert_eq!(format_run_time(59.999), "0:59.99");
+        assert_eq!(format_run_time(61.25), "1:01.25");
+        assert_eq!(format_run_time(600.0), "10:00.00");
+        assert_eq!(format_delta(-1.5), "-0:01.50");
+        assert_eq!(format_delta(0.0), "+0:00.00");
+    }
+
+    #[test]
+    fn summary_export_format() {
+        let run = splits(&[("a", 9.5), ("b", 23.5), ("c", 41.0), ("d", 50.0)]);
+        let expected = "level\tsplit\tsegment\tdelta\tstatus\n\
+                        a\t0:09.50\t0:09.50\t-0:00.50\tahead\n\
+                        b\t0:23.50\t0:14.00\t-0:01.50\tahead\n\
+                        c\t0:41.00\t0:17.50\t+0:01.00\tbehind\n\
+                        d\t0:50.00\t0:09.00\t-\t-\n\
+                        total\t0:50.00\n";
+        assert_eq!(summary(&run, &best()), expected);
+
+        let dir = TestDir::new("speedrun", "export");
+        let path = export_summary(dir.path(), &run, &best()).unwrap();
+        assert!(path.starts_with(dir.path()));
+        assert_eq!(fs::read_to_string(path).unwrap(), expected);
+    }
+}
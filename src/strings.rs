@@ -0,0 +1,195 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::fs;
+
+const FALLBACK_LANGUAGE: &str = "en";
+
+/// Idiomas incluidos en el ejecutable: código y contenido de su archivo en `lang/`.
+const BUILTIN: [(&str, &str); 2] = [
+    ("en", include_str!("../lang/en.toml")),
+    ("es", include_str!("../lang/es.toml")),
+];
+
+/// Textos visibles del juego en el idioma elegido. Las claves que falten se buscan en inglés
+/// y se avisa una sola vez por clave.
+pub struct Strings {
+    language: String,
+    table: HashMap<String, String>,
+    fallback: HashMap<String, String>,
+    missing: RefCell<HashSet<String>>, // Claves que ya se avisaron
+}
+
+impl Strings {
+    /// Carga un idioma por su código (`en`, `es`) o, si no es uno de los incluidos, desde la
+    /// ruta de un archivo con el mismo formato.
+    pub fn load(language: &str) -> Result<Self, String> {
+        let text = match BUILTIN.iter().find(|(code, _)| code.eq_ignore_ascii_case(language)) {
+            Some((_, text)) => text.to_string(),
+            None => fs::read_to_string(language).map_err(|err| err.to_string())?,
+        };
+        Ok(Self {
+            language: language.to_string(),
+            table: parse_strings(&text)?,
+            fallback: english(),
+            missing: RefCell::new(HashSet::new()),
+        })
+    }
+
+    /// Texto de `key`. Si el idioma no la tiene se usa el inglés y, si tampoco, la propia clave.
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        if let Some(text) = self.table.get(key) {
+            return text;
+        }
+        if self.missing.borrow_mut().insert(key.to_string()) {
+            eprintln!("warning: no text for '{}' in language '{}'", key, self.language);
+        }
+        self.fallback.get(key).map_or(key, String::as_str)
+    }
+
+    /// Texto de `key` con cada `{}` sustituido, en orden, por uno de `args`.
+    pub fn format(&self, key: &str, args: &[&dyn Display]) -> String {
+        let mut parts = self.get(key).split("{}");
+        let mut text = parts.next().unwrap_or_default().to_string();
+        for (i, part) in parts.enumerate() {
+            if let Some(arg) = args.get(i) {
+                text.push_str(&arg.to_string());
+            }
+            text.push_str(part);
+        }
+        text
+    }
+}
+
+/// Tabla en inglés incluida en el ejecutable. Es válida siempre, así que no puede fallar.
+fn english() -> HashMap<String, String> {
+    let (_, text) = BUILTIN.iter().find(|(code, _)| *code == FALLBACK_LANGUAGE).unwrap();
+    parse_strings(text).unwrap()
+}
+
+/// Lee un archivo de textos: una línea `clave = "texto"` por entrada (el subconjunto de TOML
+/// que hace falta), con líneas vacías y comentarios `#`. La clave puede ir entre comillas.
+fn parse_strings(text: &str) -> Result<HashMap<String, String>, String> {
+    let mut table = HashMap::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let error = |message: &str| format!("line {}: {}", i + 1, message);
+        let (key, value) = line.split_once('=').ok_or_else(|| error("expected key = \"text\""))?;
+        let key = key.trim();
+        let key = key.strip_prefix('"').and_then(|key| key.strip_suffix('"')).unwrap_or(key);
+        if key.is_empty() {
+            return Err(error("empty key"));
+        }
+        let value = value
+            .trim()
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .ok_or_else(|| error("text must be between double quotes"))?;
+        table.insert(key.to_string(), unescape(value));
+    }
+    Ok(table)
+}
+
+/// Resuelve los escapes `\"`, `\\` y `\n` de un texto entre comillas.
+fn unescape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            text.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some(other) => text.push(other),
+            None => text.push('\\'),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::measure_text;
+    use crate::test_dir::TestDir;
+
+    /// Textos de un archivo de idioma escrito en una carpeta temporal propia de cada prueba.
+    fn load_file(name: &str, text: &str) -> Strings {
+        let dir = TestDir::new("strings", name);
+        let path = dir.join("lang.toml");
+        fs::write(&path, text).unwrap();
+        Strings::load(path.to_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn builtin_languages_load() {
+        assert_eq!(Strings::load("es").unwrap().get("hud.recording"), "GRAB");
+        assert_eq!(Strings::load("EN").unwrap().get("hud.recording"), "REC");
+        assert!(Strings::load("no/such/language.toml").is_err());
+    }
+
+    #[test]
+    fn missing_key_falls_back_to_english_and_warns_once() {
+        let strings = load_file("partial", "\"hud.recording\" = \"GRABANDO\"\n");
+        assert_eq!(strings.get("hud.recording"), "GRABANDO");
+        assert_eq!(strings.get("hud.fps"), english()["hud.fps"]);
+        assert_eq!(strings.get("hud.fps"), english()["hud.fps"]);
+        assert_eq!(strings.missing.borrow().len(), 1);
+    }
+
+    #[test]
+    fn key_missing_everywhere_is_shown_as_is() {
+        let strings = Strings::load("es").unwrap();
+        assert_eq!(strings.get("hud.no_such_key"), "hud.no_such_key");
+        assert_eq!(strings.format("hud.no_such_key", &[&1]), "hud.no_such_key");
+    }
+
+    #[test]
+    fn format_fills_placeholders_in_order() {
+        let strings = load_file("format", "pair = \"{} de {}\"\n");
+        assert_eq!(strings.format("pair", &[&3, &"diez"]), "3 de diez");
+        assert_eq!(strings.format("pair", &[&3]), "3 de ");
+        assert_eq!(strings.format("pair", &[&1, &2, &3]), "1 de 2");
+    }
+
+    #[test]
+    fn parse_reports_bad_lines() {
+        assert_eq!(parse_strings("# nada\n\nkey\n").unwrap_err(), "line 3: expected key = \"text\"");
+        assert_eq!(parse_strings("\"\" = \"x\"").unwrap_err(), "line 1: empty key");
+        assert_eq!(parse_strings("key = x").unwrap_err(), "line 1: text must be between double quotes");
+        assert_eq!(parse_strings(r#"key = "a \"b\" \\ c\nd""#).unwrap()["key"], "a \"b\" \\ c\nd");
+    }
+
+    #[test]
+    fn builtin_languages_have_the_same_keys() {
+        let english = english();
+        for (code, text) in BUILTIN {
+            let table = parse_strings(text).unwrap();
+            let mut keys: Vec<&String> = table.keys().collect();
+            let mut expected: Vec<&String> = english.keys().collect();
+            keys.sort();
+            expected.sort();
+            assert_eq!(keys, expected, "keys of '{}'", code);
+        }
+    }
+
+    #[test]
+    fn builtin_texts_render_every_character() {
+        // measure_text solo cuenta los caracteres que la fuente puede dibujar
+        let advance = measure_text("0", 1).0 + 1;
+        for (code, text) in BUILTIN {
+            for (key, value) in parse_strings(text).unwrap() {
+                let value = value.replace("{}", "");
+                let glyphs = value.chars().count();
+                let expected = (glyphs * advance).saturating_sub(1);
+                assert_eq!(measure_text(&value, 1).0, expected, "'{}' in '{}' drops characters: {}", key, code, value);
+            }
+        }
+    }
+}
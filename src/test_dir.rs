@@ -0,0 +1,33 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Carpeta temporal propia de una prueba, que se borra con todo su contenido al salir de ella,
+/// también si la prueba falla.
+pub struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    /// Crea una carpeta vacía cuyo nombre combina el módulo, la prueba y el proceso, para que las
+    /// pruebas que corren en paralelo no se pisen.
+    pub fn new(module: &str, name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("{}-test-{}-{}", module, name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn join(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,100 @@
+const MAX_TICKS: u32 = 8;        // Pasos por cuadro como máximo; si la simulación se atrasa más, se descarta el resto
+const TICK_EPSILON: f64 = 1e-9;  // Margen para que el redondeo no deje un paso completo sin dar
+
+/// Acumulador de paso fijo: la simulación avanza siempre en pasos de `step` segundos, tantos
+/// como quepan en el tiempo acumulado, sin importar cuánto dure cada cuadro.
+pub struct FixedTimestep {
+    step: f64,
+    accumulator: f64,
+}
+
+impl FixedTimestep {
+    pub fn new(step: f64) -> Self {
+        Self { step, accumulator: 0.0 }
+    }
+
+    /// Duración de cada paso (en segundos).
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+
+    /// Suma `elapsed` segundos de simulación (el tiempo real por la escala de tiempo) y devuelve
+    /// cuántos pasos fijos hay que dar en este cuadro. Lo que sobra queda para el siguiente.
+    pub fn advance(&mut self, elapsed: f64) -> u32 {
+        self.accumulator += elapsed.max(0.0);
+        let ticks = ((self.accumulator + TICK_EPSILON) / self.step).floor() as u32;
+        if ticks > MAX_TICKS {
+            self.accumulator = 0.0;
+            return MAX_TICKS;
+        }
+        self.accumulator = (self.accumulator - ticks as f64 * self.step).max(0.0);
+        ticks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Map;
+    use crate::player::Player;
+
+    const STEP: f64 = 1.0 / 60.0;
+
+    /// Distancia recorrida caminando hacia adelante durante `seconds` segundos reales, con
+    /// cuadros de duración irregular, a escala de tiempo `time_scale`.
+    fn walk(seconds: f64, time_scale: f64) -> f64 {
+        let map = Map::new(64, 8).unwrap();
+        let mut player = Player::new(1.5, 4.5, 0.0);
+        let mut timestep = FixedTimestep::new(STEP);
+        let frames: [f64; 5] = [0.011, 0.023, 0.016, 0.031, 0.009];
+        let mut elapsed = 0.0;
+        let mut frame = 0;
+        while elapsed < seconds - 1e-12 {
+            let dt = frames[frame % frames.len()].min(seconds - elapsed);
+            elapsed += dt;
+            frame += 1;
+            for _ in 0..timestep.advance(dt * time_scale) {
+                player.move_forward(timestep.step(), &map);
+            }
+        }
+        player.x - 1.5
+    }
+
+    #[test]
+    fn whole_steps_are_consumed() {
+        let mut timestep = FixedTimestep::new(STEP);
+        assert_eq!(timestep.advance(STEP * 3.0), 3);
+        assert_eq!(timestep.advance(0.0), 0);
+    }
+
+    #[test]
+    fn remainder_carries_to_next_frame() {
+        let mut timestep = FixedTimestep::new(STEP);
+        assert_eq!(timestep.advance(STEP * 0.6), 0);
+        assert_eq!(timestep.advance(STEP * 0.6), 1);
+        assert_eq!(timestep.advance(STEP * 0.8), 1);
+    }
+
+    #[test]
+    fn long_frames_are_capped() {
+        let mut timestep = FixedTimestep::new(STEP);
+        assert_eq!(timestep.advance(10.0), MAX_TICKS);
+        assert_eq!(timestep.advance(0.0), 0);
+    }
+
+    #[test]
+    fn paused_simulation_does_not_step() {
+        let mut timestep = FixedTimestep::new(STEP);
+        for _ in 0..100 {
+            assert_eq!(timestep.advance(0.016 * 0.0), 0);
+        }
+    }
+
+    #[test]
+    fn half_timescale_walks_half_the_distance() {
+        let full = walk(2.0, 1.0);
+        let half = walk(2.0, 0.5);
+        assert!((full - 2.0).abs() < STEP, "full speed walked {}", full);
+        assert!((half - full / 2.0).abs() < STEP, "half speed walked {}, full {}", half, full);
+    }
+}
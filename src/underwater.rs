@@ -0,0 +1,114 @@
+use crate::color::Color;
+use crate::framebuffer::{Framebuffer, Viewport};
+
+const TINT: Color = Color::from_u32(0x1E5A8C); // Color del agua que tiñe la vista sumergida
+const TINT_AMOUNT: f64 = 0.35;                  // Cuánto se acerca la imagen al color del agua
+const WAVE_AMPLITUDE: f64 = 1.0 / 160.0;        // Desplazamiento máximo, en fracción del ancho de la vista
+const WAVE_LENGTH: f64 = 1.0 / 12.0;            // Largo de una onda, en fracción del alto de la vista
+const WAVE_SPEED: f64 = 3.0;                    // Radianes por segundo
+
+/// Desplazamiento horizontal, en píxeles, de la fila `row` de una vista de `width` x `height`
+/// en el instante `time`: una onda senoidal que baja por la imagen.
+fn row_offset(row: usize, width: usize, height: usize, time: f64) -> isize {
+    let phase = row as f64 / (height as f64 * WAVE_LENGTH) * std::f64::consts::TAU - time * WAVE_SPEED;
+    (phase.sin() * width as f64 * WAVE_AMPLITUDE).round() as isize
+}
+
+/// Efecto de estar bajo el agua sobre la región `area` del framebuffer: ondula las filas y tiñe
+/// la imagen de azul. Se aplica antes de dibujar el HUD para que este no se deforme.
+pub fn apply(framebuffer: &mut Framebuffer, area: Viewport, time: f64) {
+    if area.width == 0 {
+        return;
+    }
+
+    let mut row = vec![0; area.width];
+    for y in area.y..area.y + area.height {
+        let start = y * framebuffer.width + area.x;
+        let pixels = &mut framebuffer.buffer[start..start + area.width];
+        row.copy_from_slice(pixels);
+
+        // Cada píxel toma el de su fila desplazada; en los bordes se repite el último píxel
+        let offset = row_offset(y - area.y, area.width, area.height, time);
+        for (x, pixel) in pixels.iter_mut().enumerate() {
+            let source = (x as isize - offset).clamp(0, area.width as isize - 1) as usize;
+            *pixel = Color::from_u32(row[source]).lerp(TINT, TINT_AMOUNT).drawable().to_u32();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    const STONE: Color = Color::from_u32(0x606060);
+
+    fn tinted(color: Color) -> Color {
+        color.lerp(TINT, TINT_AMOUNT).drawable()
+    }
+
+    #[test]
+    fn row_offset_is_a_bounded_wave() {
+        // 640 de ancho: como mucho 4 píxeles; 480 de alto: una onda cada 40 filas
+        let peak = (640.0 * WAVE_AMPLITUDE).round() as isize;
+        assert_eq!(peak, 4);
+        assert_eq!(row_offset(0, 640, 480, 0.0), 0);
+        assert_eq!(row_offset(10, 640, 480, 0.0), peak);
+        assert_eq!(row_offset(30, 640, 480, 0.0), -peak);
+        assert!((0..480).all(|row| row_offset(row, 640, 480, 1.7).abs() <= peak));
+    }
+
+    #[test]
+    fn wave_moves_down_the_view_over_time() {
+        // Después de un cuarto de período la cresta de la fila 10 llega a la fila 20
+        let quarter = TAU / 4.0 / WAVE_SPEED;
+        assert_eq!(row_offset(20, 640, 480, quarter), 4);
+        assert_eq!(row_offset(10, 640, 480, TAU / WAVE_SPEED), row_offset(10, 640, 480, 0.0));
+    }
+
+    #[test]
+    fn view_is_tinted_inside_the_area_only() {
+        let mut framebuffer = Framebuffer::new(8, 6).unwrap();
+        framebuffer.buffer.fill(STONE.to_u32());
+        apply(&mut framebuffer, Viewport::new(2, 1, 4, 3), 0.0);
+        for y in 0..6 {
+            for x in 0..8 {
+                let inside = (2..6).contains(&x) && (1..4).contains(&y);
+                let expected = if inside { tinted(STONE) } else { STONE };
+                assert_eq!(framebuffer.pixel(x, y), expected, "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn rows_wobble_sideways() {
+        let mut framebuffer = Framebuffer::new(640, 480).unwrap();
+        for y in 0..480 {
+            framebuffer.point(100, y, Color::WHITE);
+        }
+        apply(&mut framebuffer, Viewport::new(0, 0, 640, 480), 0.0);
+        // La fila 0 no se mueve y la fila 10, en la cresta, se corre 4 píxeles a la derecha
+        assert_eq!(framebuffer.pixel(100, 0), tinted(Color::WHITE));
+        assert_eq!(framebuffer.pixel(104, 10), tinted(Color::WHITE));
+        assert_eq!(framebuffer.pixel(100, 10), tinted(Color::BLACK));
+        assert_eq!(framebuffer.pixel(96, 30), tinted(Color::WHITE));
+    }
+
+    #[test]
+    fn edges_repeat_the_last_pixel() {
+        let mut framebuffer = Framebuffer::new(640, 480).unwrap();
+        for y in 0..480 {
+            framebuffer.point(0, y, Color::WHITE);
+        }
+        apply(&mut framebuffer, Viewport::new(0, 0, 640, 480), 0.0);
+        // En la cresta el borde izquierdo se estira en lugar de dejar un hueco
+        assert!((0..=4).all(|x| framebuffer.pixel(x, 10) == tinted(Color::WHITE)));
+    }
+
+    #[test]
+    fn empty_area_is_ignored() {
+        let mut framebuffer = Framebuffer::new(4, 4).unwrap();
+        apply(&mut framebuffer, Viewport::new(0, 0, 0, 4), 0.0);
+        assert!(framebuffer.buffer.iter().all(|&pixel| pixel == 0));
+    }
+}